@@ -223,6 +223,33 @@ impl<'a> IndexDatabase<'a> {
         self.get_live_cell_infos(key_prefix, key_start, terminator)
     }
 
+    // All live cells created by a transaction (direct lookup by out point prefix)
+    pub fn get_live_cells_by_tx<F: FnMut(usize, &LiveCellInfo) -> (bool, bool)>(
+        &self,
+        tx_hash: Byte32,
+        mut terminator: F,
+    ) -> Vec<LiveCellInfo> {
+        let reader = RocksReader::new(self.db, self.cf);
+        let mut key_prefix = KeyType::LiveCellMap.to_bytes();
+        key_prefix.extend(tx_hash.as_slice().to_vec());
+
+        let mut infos = Vec::new();
+        for (idx, (key_bytes, value_bytes)) in reader.iter_from(&key_prefix).enumerate() {
+            if key_bytes[..key_prefix.len()] != key_prefix[..] {
+                break;
+            }
+            let live_cell_info: LiveCellInfo = bincode::deserialize(&value_bytes).unwrap();
+            let (stop, push_info) = terminator(idx, &live_cell_info);
+            if push_info {
+                infos.push(live_cell_info);
+            }
+            if stop {
+                break;
+            }
+        }
+        infos
+    }
+
     pub fn get_live_cell_infos<F: FnMut(usize, &LiveCellInfo) -> (bool, bool)>(
         &self,
         key_prefix: Key,
@@ -259,6 +286,81 @@ impl<'a> IndexDatabase<'a> {
         infos
     }
 
+    // Scan live cell index keys (until block number > to_number), only the cells picked by
+    // `sample` are read from database. The number of scanned cells is also returned.
+    pub fn sample_live_cell_infos<F: FnMut() -> bool>(
+        &self,
+        key_prefix: Key,
+        key_start: Key,
+        to_number: u64,
+        mut sample: F,
+    ) -> (usize, Vec<LiveCellInfo>) {
+        let reader = RocksReader::new(self.db, self.cf);
+        let key_prefix = key_prefix.to_bytes();
+        let key_start = key_start.to_bytes();
+
+        let mut scanned = 0;
+        let mut infos = Vec::new();
+        for (key_bytes, value_bytes) in reader.iter_from(&key_start) {
+            if key_bytes[..key_prefix.len()] != key_prefix[..] {
+                break;
+            }
+            let number = match Key::from_bytes(&key_bytes) {
+                Key::LockLiveCellIndex(_, number, _)
+                | Key::TypeLiveCellIndex(_, number, _)
+                | Key::CodeLiveCellIndex(_, number, _) => number,
+                _ => break,
+            };
+            if number > to_number {
+                break;
+            }
+            scanned += 1;
+            if sample() {
+                let out_point = OutPoint::new_unchecked(value_bytes.into());
+                let info = reader
+                    .get(&Key::LiveCellMap(out_point).to_bytes())
+                    .map(|bytes| bincode::deserialize(&bytes).unwrap())
+                    .unwrap();
+                infos.push(info);
+            }
+        }
+        (scanned, infos)
+    }
+
+    // Cells consumed in block range [from_number, to_number] with the consumed block number. Only
+    // recent blocks (kept for rollback) have the consumed cells, the first available block number
+    // is also returned.
+    pub fn get_consumed_cells<F: FnMut(&LiveCellInfo) -> bool>(
+        &self,
+        from_number: u64,
+        to_number: u64,
+        mut filter: F,
+    ) -> (Vec<(LiveCellInfo, u64)>, Option<u64>) {
+        let reader = RocksReader::new(self.db, self.cf);
+        let first_number = reader
+            .iter_from(&KeyType::BlockDelta.to_bytes())
+            .next()
+            .and_then(|(key_bytes, _)| match Key::from_bytes(&key_bytes) {
+                Key::BlockDelta(number) => Some(number),
+                _ => None,
+            });
+        let mut cells = Vec::new();
+        for (key_bytes, value_bytes) in reader.iter_from(&Key::BlockDelta(from_number).to_bytes()) {
+            match Key::from_bytes(&key_bytes) {
+                Key::BlockDelta(number) if number <= to_number => {
+                    let block_delta: BlockDeltaInfo = bincode::deserialize(&value_bytes).unwrap();
+                    for info in block_delta.consumed_cells() {
+                        if filter(info) {
+                            cells.push((info.clone(), number));
+                        }
+                    }
+                }
+                _ => break,
+            }
+        }
+        (cells, first_number)
+    }
+
     pub fn get_top_n(&self, n: usize) -> Vec<(Byte32, Option<AddressPayload>, u64)> {
         let reader = RocksReader::new(self.db, self.cf);
         let key_prefix: Vec<u8> = KeyType::LockTotalCapacityIndex.to_bytes();
@@ -301,7 +403,22 @@ impl<'a> IndexDatabase<'a> {
             blocks.push(block);
             blocks
         };
+        self.apply_blocks(blocks);
+    }
+
+    // Apply the blocks buffered during init, call this before close the database so that the
+    // next sync can resume from the last applied block.
+    pub fn flush(&mut self) {
+        if !self.init_block_buf.is_empty() {
+            let blocks = self.init_block_buf.split_off(0);
+            self.apply_blocks(blocks);
+        }
+    }
 
+    fn apply_blocks(&mut self, blocks: Vec<BlockView>) {
+        if blocks.is_empty() {
+            return;
+        }
         let mut txn = RocksTxn::new(self.db, self.cf);
         let blocks_len = blocks.len();
         for (idx, block) in blocks.into_iter().enumerate() {
@@ -55,6 +55,10 @@ impl BlockDeltaInfo {
     pub(crate) fn number(&self) -> u64 {
         self.header_info.header().number()
     }
+    // Cells consumed by this block
+    pub(crate) fn consumed_cells(&self) -> impl Iterator<Item = &LiveCellInfo> {
+        self.txs.iter().flat_map(|tx| tx.inputs.iter())
+    }
     pub(crate) fn parent_header(&self) -> Option<HeaderView> {
         self.parent_header
             .as_ref()
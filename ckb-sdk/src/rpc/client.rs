@@ -64,6 +64,7 @@ jsonrpc_client!(pub struct RawRpcClient {
     // Pool
     pub fn send_transaction(&mut self, tx: Transaction) -> RpcRequest<H256>;
     pub fn tx_pool_info(&mut self) -> RpcRequest<TxPoolInfo>;
+    pub fn get_raw_tx_pool(&mut self, verbose: Option<bool>) -> RpcRequest<types::RawTxPool>;
 
     // Stats
     pub fn get_blockchain_info(&mut self) -> RpcRequest<ChainInfo>;
@@ -92,54 +93,158 @@ pub type RawHttpRpcClient = RawRpcClient<HttpHandle>;
 pub struct HttpRpcClient {
     url: String,
     client: RawHttpRpcClient,
+    // Tried in order when a read request to current url failed
+    fallback_urls: Vec<String>,
+    // Chain name of the first successful endpoint, fallback endpoints must be on the same chain
+    chain: Option<String>,
 }
 
 impl HttpRpcClient {
     pub fn new(url: String) -> HttpRpcClient {
         let client = RawHttpRpcClient::from_uri(url.as_str());
-        HttpRpcClient { url, client }
+        HttpRpcClient {
+            url,
+            client,
+            fallback_urls: Vec::new(),
+            chain: None,
+        }
+    }
+
+    pub fn with_fallback_urls(mut self, fallback_urls: Vec<String>) -> HttpRpcClient {
+        self.fallback_urls = fallback_urls;
+        self
     }
 
     pub fn url(&self) -> &str {
         self.url.as_str()
     }
+    // Current url first, then the fallback urls
+    pub fn urls(&self) -> Vec<String> {
+        std::iter::once(self.url.clone())
+            .chain(self.fallback_urls.iter().cloned())
+            .collect()
+    }
     pub fn client(&mut self) -> &mut RawHttpRpcClient {
         &mut self.client
     }
+
+    // Record the chain of current url, or check current url is on the recorded chain. Nothing to
+    // check without fallback urls.
+    fn check_chain(&mut self) -> Result<(), String> {
+        if self.fallback_urls.is_empty() {
+            return Ok(());
+        }
+        let chain = self
+            .client
+            .get_blockchain_info()
+            .call()
+            .map_err(|err| err.to_string())?
+            .chain;
+        match self.chain.as_ref() {
+            Some(expected) if expected != &chain => Err(format!(
+                "RPC endpoint {} is on chain {}, expected {}",
+                self.url, chain, expected
+            )),
+            Some(_) => Ok(()),
+            None => {
+                self.chain = Some(chain);
+                Ok(())
+            }
+        }
+    }
+
+    // Send a read request, switch to the next healthy fallback url (on the same chain) if failed.
+    // Write requests (send_transaction etc.) are only sent to current url.
+    fn call_read<T, F>(&mut self, mut func: F) -> Result<T, String>
+    where
+        F: FnMut(&mut RawHttpRpcClient) -> Result<T, String>,
+    {
+        let first_err = match func(&mut self.client) {
+            Ok(value) => {
+                if self.chain.is_none() {
+                    // Failing to get the chain only disables the failover
+                    let _ = self.check_chain();
+                }
+                return Ok(value);
+            }
+            Err(err) => err,
+        };
+        if self.fallback_urls.is_empty() {
+            return Err(first_err);
+        }
+        // A fallback url can not be checked before the chain is known
+        let expected = match self.chain.clone() {
+            Some(chain) => chain,
+            None => {
+                return Err(format!(
+                    "{} (fallback urls are not tried, the chain of {} is unknown)",
+                    first_err, self.url
+                ))
+            }
+        };
+        for _ in 0..self.fallback_urls.len() {
+            let url = self.fallback_urls.remove(0);
+            let mut client = RawHttpRpcClient::from_uri(url.as_str());
+            let chain_opt = client
+                .get_blockchain_info()
+                .call()
+                .ok()
+                .map(|info| info.chain);
+            if chain_opt.as_ref() == Some(&expected) {
+                if let Ok(value) = func(&mut client) {
+                    log::warn!("RPC endpoint {} failed, switched to {}", self.url, url);
+                    let old_url = std::mem::replace(&mut self.url, url);
+                    self.fallback_urls.push(old_url);
+                    self.client = client;
+                    return Ok(value);
+                }
+            }
+            self.fallback_urls.push(url);
+        }
+        Err(first_err)
+    }
 }
 
 impl HttpRpcClient {
     // Chain
     pub fn get_block(&mut self, hash: H256) -> Result<Option<types::BlockView>, String> {
-        self.client
-            .get_block(hash)
-            .call()
-            .map(|opt| opt.map(Into::into))
-            .map_err(|err| err.to_string())
+        self.call_read(|client| {
+            client
+                .get_block(hash.clone())
+                .call()
+                .map(|opt| opt.map(Into::into))
+                .map_err(|err| err.to_string())
+        })
     }
     pub fn get_block_by_number(&mut self, number: u64) -> Result<Option<types::BlockView>, String> {
-        self.client
-            .get_block_by_number(BlockNumber::from(number))
-            .call()
-            .map(|opt| opt.map(Into::into))
-            .map_err(|err| err.to_string())
+        self.call_read(|client| {
+            client
+                .get_block_by_number(BlockNumber::from(number))
+                .call()
+                .map(|opt| opt.map(Into::into))
+                .map_err(|err| err.to_string())
+        })
     }
     pub fn get_block_hash(&mut self, number: u64) -> Result<Option<H256>, String> {
-        self.client
-            .get_block_hash(BlockNumber::from(number))
-            .call()
-            .map(|opt| opt.map(Into::into))
-            .map_err(|err| err.to_string())
+        self.call_read(|client| {
+            client
+                .get_block_hash(BlockNumber::from(number))
+                .call()
+                .map(|opt| opt.map(Into::into))
+                .map_err(|err| err.to_string())
+        })
     }
     pub fn get_cellbase_output_capacity_details(
         &mut self,
         hash: H256,
     ) -> Result<Option<types::BlockReward>, String> {
-        self.client
-            .get_cellbase_output_capacity_details(hash)
-            .call()
-            .map(|opt| opt.map(Into::into))
-            .map_err(|err| err.to_string())
+        self.call_read(|client| {
+            client
+                .get_cellbase_output_capacity_details(hash.clone())
+                .call()
+                .map(|opt| opt.map(Into::into))
+                .map_err(|err| err.to_string())
+        })
     }
     pub fn get_cells_by_lock_hash(
         &mut self,
@@ -147,42 +252,56 @@ impl HttpRpcClient {
         from: u64,
         to: u64,
     ) -> Result<Vec<types::CellOutputWithOutPoint>, String> {
-        self.client
-            .get_cells_by_lock_hash(lock_hash, BlockNumber::from(from), BlockNumber::from(to))
-            .call()
-            .map(|vec| vec.into_iter().map(Into::into).collect())
-            .map_err(|err| err.to_string())
+        self.call_read(|client| {
+            client
+                .get_cells_by_lock_hash(
+                    lock_hash.clone(),
+                    BlockNumber::from(from),
+                    BlockNumber::from(to),
+                )
+                .call()
+                .map(|vec| vec.into_iter().map(Into::into).collect())
+                .map_err(|err| err.to_string())
+        })
     }
     pub fn get_current_epoch(&mut self) -> Result<types::EpochView, String> {
-        self.client
-            .get_current_epoch()
-            .call()
-            .map(Into::into)
-            .map_err(|err| err.to_string())
+        self.call_read(|client| {
+            client
+                .get_current_epoch()
+                .call()
+                .map(Into::into)
+                .map_err(|err| err.to_string())
+        })
     }
     pub fn get_epoch_by_number(&mut self, number: u64) -> Result<Option<types::EpochView>, String> {
-        self.client
-            .get_epoch_by_number(EpochNumber::from(number))
-            .call()
-            .map(|opt| opt.map(Into::into))
-            .map_err(|err| err.to_string())
+        self.call_read(|client| {
+            client
+                .get_epoch_by_number(EpochNumber::from(number))
+                .call()
+                .map(|opt| opt.map(Into::into))
+                .map_err(|err| err.to_string())
+        })
     }
     pub fn get_header(&mut self, hash: H256) -> Result<Option<types::HeaderView>, String> {
-        self.client
-            .get_header(hash)
-            .call()
-            .map(|opt| opt.map(Into::into))
-            .map_err(|err| err.to_string())
+        self.call_read(|client| {
+            client
+                .get_header(hash.clone())
+                .call()
+                .map(|opt| opt.map(Into::into))
+                .map_err(|err| err.to_string())
+        })
     }
     pub fn get_header_by_number(
         &mut self,
         number: u64,
     ) -> Result<Option<types::HeaderView>, String> {
-        self.client
-            .get_header_by_number(BlockNumber::from(number))
-            .call()
-            .map(|opt| opt.map(Into::into))
-            .map_err(|err| err.to_string())
+        self.call_read(|client| {
+            client
+                .get_header_by_number(BlockNumber::from(number))
+                .call()
+                .map(|opt| opt.map(Into::into))
+                .map_err(|err| err.to_string())
+        })
     }
     // TODO: Make `cell::CellData` public
     pub fn get_live_cell(
@@ -190,34 +309,42 @@ impl HttpRpcClient {
         out_point: packed::OutPoint,
         with_data: bool,
     ) -> Result<CellWithStatus, String> {
-        self.client
-            .get_live_cell(out_point.into(), with_data)
-            .call()
-            .map_err(|err| err.to_string())
+        self.call_read(|client| {
+            client
+                .get_live_cell(out_point.clone().into(), with_data)
+                .call()
+                .map_err(|err| err.to_string())
+        })
     }
     pub fn get_tip_block_number(&mut self) -> Result<u64, String> {
-        self.client
-            .get_tip_block_number()
-            .call()
-            .map(Into::into)
-            .map_err(|err| err.to_string())
+        self.call_read(|client| {
+            client
+                .get_tip_block_number()
+                .call()
+                .map(Into::into)
+                .map_err(|err| err.to_string())
+        })
     }
     pub fn get_tip_header(&mut self) -> Result<types::HeaderView, String> {
-        self.client
-            .get_tip_header()
-            .call()
-            .map(Into::into)
-            .map_err(|err| err.to_string())
+        self.call_read(|client| {
+            client
+                .get_tip_header()
+                .call()
+                .map(Into::into)
+                .map_err(|err| err.to_string())
+        })
     }
     pub fn get_transaction(
         &mut self,
         hash: H256,
     ) -> Result<Option<types::TransactionWithStatus>, String> {
-        self.client
-            .get_transaction(hash)
-            .call()
-            .map(|opt| opt.map(Into::into))
-            .map_err(|err| err.to_string())
+        self.call_read(|client| {
+            client
+                .get_transaction(hash.clone())
+                .call()
+                .map(|opt| opt.map(Into::into))
+                .map_err(|err| err.to_string())
+        })
     }
 
     // Indexer
@@ -234,16 +361,18 @@ impl HttpRpcClient {
         per_page: u64,
         reverse_order: Option<bool>,
     ) -> Result<Vec<types::LiveCell>, String> {
-        self.client
-            .get_live_cells_by_lock_hash(
-                lock_hash,
-                Uint64::from(page),
-                Uint64::from(per_page),
-                reverse_order,
-            )
-            .call()
-            .map(|vec| vec.into_iter().map(Into::into).collect())
-            .map_err(|err| err.to_string())
+        self.call_read(|client| {
+            client
+                .get_live_cells_by_lock_hash(
+                    lock_hash.clone(),
+                    Uint64::from(page),
+                    Uint64::from(per_page),
+                    reverse_order,
+                )
+                .call()
+                .map(|vec| vec.into_iter().map(Into::into).collect())
+                .map_err(|err| err.to_string())
+        })
     }
     pub fn get_transactions_by_lock_hash(
         &mut self,
@@ -252,16 +381,18 @@ impl HttpRpcClient {
         per_page: u64,
         reverse_order: Option<bool>,
     ) -> Result<Vec<types::CellTransaction>, String> {
-        self.client
-            .get_transactions_by_lock_hash(
-                lock_hash,
-                Uint64::from(page),
-                Uint64::from(per_page),
-                reverse_order,
-            )
-            .call()
-            .map(|vec| vec.into_iter().map(Into::into).collect())
-            .map_err(|err| err.to_string())
+        self.call_read(|client| {
+            client
+                .get_transactions_by_lock_hash(
+                    lock_hash.clone(),
+                    Uint64::from(page),
+                    Uint64::from(per_page),
+                    reverse_order,
+                )
+                .call()
+                .map(|vec| vec.into_iter().map(Into::into).collect())
+                .map_err(|err| err.to_string())
+        })
     }
     pub fn index_lock_hash(
         &mut self,
@@ -313,6 +444,7 @@ impl HttpRpcClient {
 
     // Pool
     pub fn send_transaction(&mut self, tx: packed::Transaction) -> Result<H256, String> {
+        self.check_chain()?;
         self.client
             .send_transaction(tx.into())
             .call()
@@ -325,15 +457,23 @@ impl HttpRpcClient {
             .map(Into::into)
             .map_err(|err| err.to_string())
     }
-
-    // Stats
-    pub fn get_blockchain_info(&mut self) -> Result<types::ChainInfo, String> {
+    pub fn get_raw_tx_pool(&mut self) -> Result<types::RawTxPool, String> {
         self.client
-            .get_blockchain_info()
+            .get_raw_tx_pool(Some(false))
             .call()
-            .map(Into::into)
             .map_err(|err| err.to_string())
     }
+
+    // Stats
+    pub fn get_blockchain_info(&mut self) -> Result<types::ChainInfo, String> {
+        self.call_read(|client| {
+            client
+                .get_blockchain_info()
+                .call()
+                .map(Into::into)
+                .map_err(|err| err.to_string())
+        })
+    }
     pub fn get_peers_state(&mut self) -> Result<Vec<PeerState>, String> {
         self.client
             .get_peers_state()
@@ -384,3 +524,105 @@ impl HttpRpcClient {
             .map_err(|err| err.to_string())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+
+    // A json-rpc node answering get_blockchain_info and get_tip_block_number, or an error for
+    // every request when it is not alive
+    fn spawn_node(chain: &'static str, tip: u64, alive: Arc<AtomicBool>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let stream = stream.unwrap();
+                let alive = Arc::clone(&alive);
+                thread::spawn(move || serve(stream, chain, tip, &alive));
+            }
+        });
+        url
+    }
+
+    fn serve(stream: TcpStream, chain: &str, tip: u64, alive: &AtomicBool) {
+        let mut writer = stream.try_clone().unwrap();
+        let mut reader = BufReader::new(stream);
+        loop {
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line).unwrap_or(0) == 0 {
+                    return;
+                }
+                let line = line.trim_end().to_lowercase();
+                if line.is_empty() {
+                    break;
+                }
+                if line.starts_with("content-length:") {
+                    content_length = line["content-length:".len()..].trim().parse().unwrap();
+                }
+            }
+            let mut body = vec![0u8; content_length];
+            reader.read_exact(&mut body).unwrap();
+            let request: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            let result = match request["method"].as_str().unwrap() {
+                "get_blockchain_info" => serde_json::json!({
+                    "chain": chain,
+                    "median_time": "0x0",
+                    "epoch": "0x0",
+                    "difficulty": "0x1",
+                    "is_initial_block_download": false,
+                    "alerts": [],
+                }),
+                "get_tip_block_number" => serde_json::json!(format!("{:#x}", tip)),
+                method => panic!("unexpected method {}", method),
+            };
+            let response = if alive.load(Ordering::SeqCst) {
+                serde_json::json!({"jsonrpc": "2.0", "result": result, "id": request["id"]})
+            } else {
+                serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "error": {"code": -32000, "message": "node is down"},
+                    "id": request["id"],
+                })
+            }
+            .to_string();
+            write!(
+                writer,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                response.len(),
+                response
+            )
+            .unwrap();
+        }
+    }
+
+    #[test]
+    fn test_call_read_fallback() {
+        let primary_alive = Arc::new(AtomicBool::new(false));
+        let primary = spawn_node("ckb", 1, Arc::clone(&primary_alive));
+        let other_chain = spawn_node("ckb_testnet", 2, Arc::new(AtomicBool::new(true)));
+        let same_chain = spawn_node("ckb", 3, Arc::new(AtomicBool::new(true)));
+        let mut client = HttpRpcClient::new(primary.clone())
+            .with_fallback_urls(vec![other_chain.clone(), same_chain.clone()]);
+
+        // The chain is unknown while the primary is dead, no fallback is used
+        assert!(client.get_tip_block_number().is_err());
+        assert_eq!(client.url(), primary);
+
+        // The chain is recorded by the first successful call
+        primary_alive.store(true, Ordering::SeqCst);
+        assert_eq!(client.get_tip_block_number(), Ok(1));
+
+        // Switched to the fallback on the same chain, the one on another chain is skipped
+        primary_alive.store(false, Ordering::SeqCst);
+        assert_eq!(client.get_tip_block_number(), Ok(3));
+        assert_eq!(client.url(), same_chain);
+        assert_eq!(client.urls(), vec![same_chain, other_chain, primary]);
+    }
+}
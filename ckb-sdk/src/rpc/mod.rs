@@ -8,6 +8,7 @@ pub use types::{
     Alert, AlertMessage, BannedAddr, Block, BlockReward, BlockView, Byte32, CellDep, CellInput,
     CellOutput, CellOutputWithOutPoint, CellTransaction, ChainInfo, DepType, EpochView, Header,
     HeaderView, JsonBytes, LiveCell, LockHashIndexState, Node, NodeAddress, OutPoint,
-    ProposalShortId, Script, ScriptHashType, Transaction, TransactionPoint, TransactionView,
-    TransactionWithStatus, TxPoolInfo, TxStatus, Uint128, UncleBlock, UncleBlockView,
+    ProposalShortId, RawTxPool, Script, ScriptHashType, Transaction, TransactionPoint,
+    TransactionView, TransactionWithStatus, TxPoolInfo, TxStatus, Uint128, UncleBlock,
+    UncleBlockView,
 };
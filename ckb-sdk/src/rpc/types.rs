@@ -816,6 +816,13 @@ impl From<rpc_types::BannedAddr> for BannedAddr {
 // =========
 //  pool.rs
 // =========
+// Not in ckb-jsonrpc-types yet, the result of `get_raw_tx_pool` with verbose = false
+#[derive(Clone, Default, Serialize, Deserialize, PartialEq, Eq, Hash, Debug)]
+pub struct RawTxPool {
+    pub pending: Vec<H256>,
+    pub proposed: Vec<H256>,
+}
+
 #[derive(Clone, Default, Serialize, Deserialize, PartialEq, Eq, Hash, Debug)]
 pub struct TxPoolInfo {
     pub pending: Uint64,
@@ -1,4 +1,4 @@
-use ckb_hash::{blake2b_256, new_blake2b};
+use ckb_hash::{blake2b_256, new_blake2b, Blake2b};
 use ckb_types::{
     bytes::{Bytes, BytesMut},
     core::{ScriptHashType, TransactionBuilder, TransactionView},
@@ -7,7 +7,10 @@ use ckb_types::{
     prelude::*,
     H160, H256,
 };
+use rayon::prelude::*;
 use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use tiny_keccak::Keccak;
 
 use crate::constants::{MULTISIG_TYPE_HASH, SECP_SIGNATURE_SIZE, SIGHASH_TYPE_HASH};
 use crate::{AddressPayload, AddressType, CodeHashIndex, GenesisInfo, Since};
@@ -23,6 +26,8 @@ pub struct TxHelper {
     multisig_configs: HashMap<H160, MultisigConfig>,
     // Only support sighash/multisig signatures
     signatures: HashMap<Bytes, HashSet<Bytes>>,
+    // Hash function for the signing message
+    hash_fn: HashFn,
 }
 
 impl Default for TxHelper {
@@ -31,6 +36,7 @@ impl Default for TxHelper {
             transaction: TransactionBuilder::default().build(),
             multisig_configs: HashMap::default(),
             signatures: HashMap::default(),
+            hash_fn: HashFn::default(),
         }
     }
 }
@@ -41,6 +47,7 @@ impl TxHelper {
             transaction,
             multisig_configs: HashMap::default(),
             signatures: HashMap::default(),
+            hash_fn: HashFn::default(),
         }
     }
 
@@ -53,6 +60,12 @@ impl TxHelper {
     pub fn signatures(&self) -> &HashMap<Bytes, HashSet<Bytes>> {
         &self.signatures
     }
+    pub fn hash_fn(&self) -> HashFn {
+        self.hash_fn
+    }
+    pub fn set_hash_fn(&mut self, hash_fn: HashFn) {
+        self.hash_fn = hash_fn;
+    }
 
     pub fn clear_inputs(&mut self) {
         self.transaction = self
@@ -108,7 +121,15 @@ impl TxHelper {
             .build();
 
         self.transaction = self.transaction.as_advanced_builder().input(input).build();
-        let mut cell_deps: HashSet<CellDep> = HashSet::default();
+        // Keep the cell deps added by `add_cell_dep`
+        let sighash_dep = genesis_info.sighash_dep();
+        let multisig_dep = genesis_info.multisig_dep();
+        let mut cell_deps: HashSet<CellDep> = self
+            .transaction
+            .cell_deps()
+            .into_iter()
+            .filter(|dep| dep != &sighash_dep && dep != &multisig_dep)
+            .collect();
         for ((code_hash, _), _) in self.input_group(get_live_cell, skip_check)?.into_iter() {
             let code_hash: H256 = code_hash.unpack();
             if code_hash == SIGHASH_TYPE_HASH {
@@ -137,6 +158,30 @@ impl TxHelper {
             .build()
     }
 
+    pub fn add_cell_dep(&mut self, cell_dep: CellDep) {
+        if self
+            .transaction
+            .cell_deps()
+            .into_iter()
+            .any(|dep| dep == cell_dep)
+        {
+            return;
+        }
+        self.transaction = self
+            .transaction
+            .as_advanced_builder()
+            .cell_dep(cell_dep)
+            .build()
+    }
+
+    pub fn add_header_dep(&mut self, block_hash: Byte32) {
+        self.transaction = self
+            .transaction
+            .as_advanced_builder()
+            .header_dep(block_hash)
+            .build()
+    }
+
     pub fn add_signature(&mut self, lock_arg: Bytes, signature: Bytes) -> Result<bool, String> {
         if lock_arg.len() != 20 && lock_arg.len() != 28 {
             return Err(format!(
@@ -204,6 +249,18 @@ impl TxHelper {
         witnesses
     }
 
+    /// Append a witness not belonging to any input group (e.g. metadata), it is covered by the
+    /// signatures. Must be called after all inputs are added.
+    pub fn add_extra_witness(&mut self, witness: Bytes) {
+        let mut witnesses = self.init_witnesses();
+        witnesses.push(witness.pack());
+        self.transaction = self
+            .transaction
+            .as_advanced_builder()
+            .set_witnesses(witnesses)
+            .build();
+    }
+
     pub fn sign_inputs<S, C>(
         &self,
         mut signer: S,
@@ -240,8 +297,10 @@ impl TxHelper {
                 let signature = build_signature(
                     &self.transaction.hash(),
                     &idxs,
+                    self.transaction.inputs().len(),
                     &witnesses,
                     self.multisig_configs.get(&multisig_hash160),
+                    self.hash_fn,
                     |message: &H256| signer(&lock_args, message).map(|sig| sig.unwrap()),
                 )?;
                 signatures.insert(lock_arg, signature);
@@ -250,6 +309,97 @@ impl TxHelper {
         Ok(signatures)
     }
 
+    /// The message to be signed of each input group: (lock_arg, input indexes, message)
+    pub fn sighash_messages<C>(
+        &self,
+        get_live_cell: C,
+        skip_check: bool,
+    ) -> Result<Vec<(Bytes, Vec<usize>, H256)>, String>
+    where
+        C: FnMut(OutPoint, bool) -> Result<CellOutput, String>,
+    {
+        let witnesses = self.init_witnesses();
+        let mut messages = Vec::new();
+        for ((_code_hash, lock_arg), idxs) in
+            self.input_group(get_live_cell, skip_check)?.into_iter()
+        {
+            let multisig_hash160 = H160::from_slice(&lock_arg[..20]).unwrap();
+            let mut message_opt = None;
+            build_signature(
+                &self.transaction.hash(),
+                &idxs,
+                self.transaction.inputs().len(),
+                &witnesses,
+                self.multisig_configs.get(&multisig_hash160),
+                self.hash_fn,
+                |message: &H256| {
+                    message_opt = Some(message.clone());
+                    Ok([0u8; SECP_SIGNATURE_SIZE])
+                },
+            )?;
+            messages.push((lock_arg, idxs, message_opt.unwrap()));
+        }
+        messages.sort_by_key(|(_, idxs, _)| idxs[0]);
+        Ok(messages)
+    }
+
+    /// Same as `sign_inputs`, but sign all input groups concurrently
+    pub fn sign_inputs_parallel<S, C>(
+        &self,
+        signer: S,
+        get_live_cell: C,
+        skip_check: bool,
+    ) -> Result<HashMap<Bytes, Bytes>, String>
+    where
+        S: Fn(&HashSet<H160>, &H256) -> Result<Option<[u8; 65]>, String> + Sync,
+        C: FnMut(OutPoint, bool) -> Result<CellOutput, String>,
+    {
+        let all_sighash_lock_args = self
+            .multisig_configs
+            .iter()
+            .map(|(hash160, config)| (hash160.clone(), config.sighash_lock_args()))
+            .collect::<HashMap<_, _>>();
+
+        let tx_hash = self.transaction.hash();
+        let inputs_len = self.transaction.inputs().len();
+        let witnesses = self.init_witnesses();
+        let input_groups = self
+            .input_group(get_live_cell, skip_check)?
+            .into_iter()
+            .collect::<Vec<_>>();
+        let signatures = input_groups
+            .into_par_iter()
+            .map(|((code_hash, lock_arg), idxs)| {
+                let multisig_hash160 = H160::from_slice(&lock_arg[..20]).unwrap();
+                let lock_args = if code_hash == MULTISIG_TYPE_HASH.pack() {
+                    all_sighash_lock_args
+                        .get(&multisig_hash160)
+                        .unwrap()
+                        .clone()
+                } else {
+                    let mut lock_args = HashSet::default();
+                    lock_args.insert(H160::from_slice(lock_arg.as_ref()).unwrap());
+                    lock_args
+                };
+                if signer(&lock_args, &h256!("0x0"))?.is_some() {
+                    let signature = build_signature(
+                        &tx_hash,
+                        &idxs,
+                        inputs_len,
+                        &witnesses,
+                        self.multisig_configs.get(&multisig_hash160),
+                        self.hash_fn,
+                        |message: &H256| signer(&lock_args, message).map(|sig| sig.unwrap()),
+                    )?;
+                    Ok(Some((lock_arg, signature)))
+                } else {
+                    Ok(None)
+                }
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        Ok(signatures.into_iter().flatten().collect())
+    }
+
     pub fn build_tx<F: FnMut(OutPoint, bool) -> Result<CellOutput, String>>(
         &self,
         get_live_cell: F,
@@ -488,8 +638,10 @@ pub fn check_lock_script(lock: &Script) -> Result<(), String> {
 pub fn build_signature<S: FnMut(&H256) -> Result<[u8; SECP_SIGNATURE_SIZE], String>>(
     tx_hash: &Byte32,
     input_group_idxs: &[usize],
+    inputs_len: usize,
     witnesses: &[packed::Bytes],
     multisig_config_opt: Option<&MultisigConfig>,
+    hash_fn: HashFn,
     mut signer: S,
 ) -> Result<Bytes, String> {
     let init_witness_idx = input_group_idxs[0];
@@ -518,17 +670,156 @@ pub fn build_signature<S: FnMut(&H256) -> Result<[u8; SECP_SIGNATURE_SIZE], Stri
             .build()
     };
 
-    let mut blake2b = new_blake2b();
-    blake2b.update(tx_hash.as_slice());
-    blake2b.update(&(init_witness.as_bytes().len() as u64).to_le_bytes());
-    blake2b.update(&init_witness.as_bytes());
+    let mut hasher = hash_fn.hasher();
+    hasher.update(tx_hash.as_slice());
+    hasher.update(&(init_witness.as_bytes().len() as u64).to_le_bytes());
+    hasher.update(&init_witness.as_bytes());
     for idx in input_group_idxs.iter().skip(1).cloned() {
         let other_witness: &packed::Bytes = &witnesses[idx];
-        blake2b.update(&(other_witness.len() as u64).to_le_bytes());
-        blake2b.update(&other_witness.raw_data());
+        hasher.update(&(other_witness.len() as u64).to_le_bytes());
+        hasher.update(&other_witness.raw_data());
+    }
+    // Witnesses not belonging to any input group
+    for extra_witness in witnesses.iter().skip(inputs_len) {
+        hasher.update(&(extra_witness.len() as u64).to_le_bytes());
+        hasher.update(&extra_witness.raw_data());
     }
-    let mut message = [0u8; 32];
-    blake2b.finalize(&mut message);
-    let message = H256::from(message);
+    let message = H256::from(hasher.finalize());
     signer(&message).map(|data| Bytes::from(data.to_vec()))
 }
+
+/// Hash function for computing the signing message (default CKB lock use blake2b_256)
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum HashFn {
+    Blake2b256,
+    Sha3_256,
+}
+
+impl Default for HashFn {
+    fn default() -> HashFn {
+        HashFn::Blake2b256
+    }
+}
+
+impl FromStr for HashFn {
+    type Err = String;
+    fn from_str(input: &str) -> Result<HashFn, String> {
+        match input {
+            "blake2b_256" => Ok(HashFn::Blake2b256),
+            "sha3_256" => Ok(HashFn::Sha3_256),
+            _ => Err(format!("Invalid hash function: {}", input)),
+        }
+    }
+}
+
+impl HashFn {
+    pub fn hasher(self) -> Hasher {
+        match self {
+            HashFn::Blake2b256 => Hasher::Blake2b(new_blake2b()),
+            HashFn::Sha3_256 => Hasher::Sha3(Keccak::new_sha3_256()),
+        }
+    }
+}
+
+pub enum Hasher {
+    Blake2b(Blake2b),
+    Sha3(Keccak),
+}
+
+impl Hasher {
+    pub fn update(&mut self, data: &[u8]) {
+        match self {
+            Hasher::Blake2b(blake2b) => blake2b.update(data),
+            Hasher::Sha3(sha3) => sha3.update(data),
+        }
+    }
+    pub fn finalize(self) -> [u8; 32] {
+        let mut result = [0u8; 32];
+        match self {
+            Hasher::Blake2b(blake2b) => blake2b.finalize(&mut result),
+            Hasher::Sha3(sha3) => sha3.finalize(&mut result),
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // The signing message passed to the signer by build_signature
+    fn signing_message(witnesses: &[packed::Bytes], inputs_len: usize, hash_fn: HashFn) -> H256 {
+        let mut message = H256::default();
+        build_signature(
+            &Byte32::zero(),
+            &[0],
+            inputs_len,
+            witnesses,
+            None,
+            hash_fn,
+            |msg: &H256| {
+                message = msg.clone();
+                Ok([0u8; SECP_SIGNATURE_SIZE])
+            },
+        )
+        .unwrap();
+        message
+    }
+
+    // Message of one sighash input group with an empty witness, plus the extra witnesses
+    fn expected_message(hash_fn: HashFn, extra_witnesses: &[Bytes]) -> H256 {
+        let init_witness = WitnessArgs::new_builder()
+            .lock(Some(Bytes::from(vec![0u8; SECP_SIGNATURE_SIZE])).pack())
+            .build()
+            .as_bytes();
+        let mut data = Byte32::zero().as_slice().to_vec();
+        data.extend_from_slice(&(init_witness.len() as u64).to_le_bytes());
+        data.extend_from_slice(&init_witness);
+        for witness in extra_witnesses {
+            data.extend_from_slice(&(witness.len() as u64).to_le_bytes());
+            data.extend_from_slice(witness);
+        }
+        let result = match hash_fn {
+            HashFn::Blake2b256 => blake2b_256(&data),
+            HashFn::Sha3_256 => {
+                let mut result = [0u8; 32];
+                let mut sha3 = Keccak::new_sha3_256();
+                sha3.update(&data);
+                sha3.finalize(&mut result);
+                result
+            }
+        };
+        H256::from(result)
+    }
+
+    #[test]
+    fn test_build_signature_extra_witness() {
+        let extra = Bytes::from(&b"extra witness"[..]);
+        let witnesses = vec![Bytes::new().pack()];
+        let witnesses_with_extra = vec![Bytes::new().pack(), extra.pack()];
+
+        let message = signing_message(&witnesses, 1, HashFn::Blake2b256);
+        assert_eq!(message, expected_message(HashFn::Blake2b256, &[]));
+        let message_with_extra = signing_message(&witnesses_with_extra, 1, HashFn::Blake2b256);
+        assert_eq!(
+            message_with_extra,
+            expected_message(HashFn::Blake2b256, &[extra])
+        );
+        assert_ne!(message, message_with_extra);
+    }
+
+    #[test]
+    fn test_build_signature_sha3() {
+        let extra = Bytes::from(&b"extra witness"[..]);
+        let witnesses = vec![Bytes::new().pack()];
+        let witnesses_with_extra = vec![Bytes::new().pack(), extra.pack()];
+
+        let message = signing_message(&witnesses, 1, HashFn::Sha3_256);
+        assert_eq!(message, expected_message(HashFn::Sha3_256, &[]));
+        assert_ne!(message, signing_message(&witnesses, 1, HashFn::Blake2b256));
+        assert_eq!(
+            signing_message(&witnesses_with_extra, 1, HashFn::Sha3_256),
+            expected_message(HashFn::Sha3_256, &[extra])
+        );
+    }
+}
@@ -30,12 +30,13 @@ impl FromStr for HumanCapacity {
     type Err = String;
     fn from_str(input: &str) -> Result<Self, Self::Err> {
         let parts = input.trim().split('.').collect::<Vec<_>>();
-        let mut capacity = ONE_CKB
-            * parts
-                .get(0)
-                .ok_or_else(|| "Missing input".to_owned())?
-                .parse::<u64>()
-                .map_err(|err| err.to_string())?;
+        let mut capacity = parts
+            .get(0)
+            .ok_or_else(|| "Missing input".to_owned())?
+            .parse::<u64>()
+            .map_err(|err| err.to_string())?
+            .checked_mul(ONE_CKB)
+            .ok_or_else(|| "capacity overflow".to_owned())?;
         if let Some(shannon_str) = parts.get(1) {
             let shannon_str = shannon_str.trim();
             if shannon_str.len() > 8 {
@@ -45,7 +46,9 @@ impl FromStr for HumanCapacity {
             for _ in 0..(8 - shannon_str.len()) {
                 shannon *= 10;
             }
-            capacity += u64::from(shannon);
+            capacity = capacity
+                .checked_add(u64::from(shannon))
+                .ok_or_else(|| "capacity overflow".to_owned())?;
         }
         Ok(capacity.into())
     }
@@ -118,5 +121,6 @@ mod test {
         assert!(HumanCapacity::from_str("abc").is_err());
         assert!(HumanCapacity::from_str("-234").is_err());
         assert!(HumanCapacity::from_str("-234.3").is_err());
+        assert!(HumanCapacity::from_str("184467440738").is_err());
     }
 }
@@ -64,7 +64,7 @@ impl KeyStore {
 
     pub fn new_account(&mut self, password: &[u8]) -> Result<H160, Error> {
         let privkey = MasterPrivKey::try_new(1024)?;
-        let key = Key::new(privkey);
+        let key = Key::new(privkey).with_source(KeySource::Generated);
         let abs_path = self.storage.store_key(key.filename(), &key, password)?;
         let hash160 = key.hash160().clone();
         self.files.insert(hash160.clone(), abs_path);
@@ -74,6 +74,11 @@ impl KeyStore {
         self.refresh_dir().ok();
         &self.files
     }
+    /// How the account was created, None for keys saved without the tag
+    pub fn get_source(&self, hash160: &H160) -> Result<Option<KeySource>, Error> {
+        let filepath = self.get_filepath(hash160)?;
+        self.storage.get_source(filepath)
+    }
     pub fn has_account(&mut self, hash160: &H160) -> bool {
         self.refresh_dir().ok();
         self.files.contains_key(hash160)
@@ -125,7 +130,7 @@ impl KeyStore {
         password: &[u8],
         new_password: &[u8],
     ) -> Result<H160, Error> {
-        let key = Key::from_json(data, password)?;
+        let key = Key::from_json(data, password)?.with_source(KeySource::KeystoreImport);
         if self.files.contains_key(key.hash160()) {
             Err(Error::KeyExists(key.hash160().clone()))
         } else {
@@ -139,7 +144,7 @@ impl KeyStore {
         key: &secp256k1::SecretKey,
         password: &[u8],
     ) -> Result<H160, Error> {
-        let key = Key::new(MasterPrivKey::from_secp_key(key));
+        let key = Key::new(MasterPrivKey::from_secp_key(key)).with_source(KeySource::Privkey);
         if self.files.contains_key(key.hash160()) {
             Err(Error::KeyExists(key.hash160().clone()))
         } else {
@@ -377,6 +382,15 @@ impl PassphraseKeyStore {
         Ok(key)
     }
 
+    // Reads the key source without decrypting the key.
+    fn get_source<P: AsRef<Path>>(&self, filename: P) -> Result<Option<KeySource>, Error> {
+        let filepath = self.join_path(filename);
+        let mut file = fs::File::open(&filepath)?;
+        let data: serde_json::Value = serde_json::from_reader(&mut file)
+            .map_err(|err| Error::ParseJsonFailed(err.to_string()))?;
+        Ok(Key::source_from_json(&data))
+    }
+
     // Writes and encrypts the key.
     fn store_key<P: AsRef<Path>>(
         &self,
@@ -479,6 +493,42 @@ pub enum KeyChain {
     Change = 1,
 }
 
+/// Where the key of an account comes from (saved as plain tag in keystore file)
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum KeySource {
+    Generated,
+    Privkey,
+    ExtendedPrivkey,
+    Mnemonic,
+    KeystoreImport,
+}
+
+impl KeySource {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            KeySource::Generated => "generated",
+            KeySource::Privkey => "privkey",
+            KeySource::ExtendedPrivkey => "xprv",
+            KeySource::Mnemonic => "mnemonic",
+            KeySource::KeystoreImport => "keystore-import",
+        }
+    }
+}
+
+impl FromStr for KeySource {
+    type Err = String;
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "generated" => Ok(KeySource::Generated),
+            "privkey" => Ok(KeySource::Privkey),
+            "xprv" => Ok(KeySource::ExtendedPrivkey),
+            "mnemonic" => Ok(KeySource::Mnemonic),
+            "keystore-import" => Ok(KeySource::KeystoreImport),
+            _ => Err(format!("Invalid key source: {}", input)),
+        }
+    }
+}
+
 pub struct DerivedKeySet {
     pub external: Vec<(DerivationPath, H160)>,
     pub change: Vec<(DerivationPath, H160)>,
@@ -507,6 +557,8 @@ pub struct Key {
     hash160: H160,
     // The extended secp256k1 private key (privkey + chaincode)
     master_privkey: MasterPrivKey,
+    // Not encrypted, None for keys created before this tag is added
+    source: Option<KeySource>,
 }
 
 impl Key {
@@ -517,13 +569,29 @@ impl Key {
             id,
             hash160,
             master_privkey,
+            source: None,
         }
     }
 
+    pub fn with_source(mut self, source: KeySource) -> Key {
+        self.source = Some(source);
+        self
+    }
+
     pub fn hash160(&self) -> &H160 {
         &self.hash160
     }
 
+    pub fn source(&self) -> Option<KeySource> {
+        self.source
+    }
+
+    fn source_from_json(data: &serde_json::Value) -> Option<KeySource> {
+        data.get("source")
+            .and_then(|value| value.as_str())
+            .and_then(|value| value.parse().ok())
+    }
+
     pub fn derived_key_set(
         &self,
         external_max_len: u32,
@@ -654,6 +722,7 @@ impl Key {
             id,
             hash160,
             master_privkey,
+            source: Key::source_from_json(data),
         })
     }
 
@@ -663,13 +732,17 @@ impl Key {
         let hash160_hex = format!("{:x}", self.hash160);
         let master_privkey = self.master_privkey.to_bytes();
         let crypto = Crypto::encrypt_key_scrypt(&master_privkey, password, scrypt_type);
-        serde_json::json!({
+        let mut data = serde_json::json!({
             "origin": KEYSTORE_ORIGIN,
             "id": id_str,
             "version": KEYSTORE_VERSION,
             "hash160": hash160_hex,
             "crypto": crypto.to_json(),
-        })
+        });
+        if let Some(source) = self.source {
+            data["source"] = serde_json::json!(source.as_str());
+        }
+        data
     }
 }
 
@@ -737,6 +810,17 @@ impl MasterPrivKey {
             .expect("Derive sub-privkey error")
     }
 
+    pub fn sub_master_privkey<P>(&self, path: &P) -> MasterPrivKey
+    where
+        P: ?Sized + AsRef<[ChildNumber]>,
+    {
+        let sub_sk = self.sub_privkey(path);
+        MasterPrivKey {
+            secp_secret_key: sub_sk.private_key,
+            chain_code: sub_sk.chain_code.0,
+        }
+    }
+
     pub fn sign<P>(&self, message: &H256, path: &P) -> secp256k1::Signature
     where
         P: ?Sized + AsRef<[ChildNumber]>,
@@ -9,5 +9,6 @@ pub use bip32::{
 pub use error::Error as WalletError;
 pub use keystore::{
     zeroize_privkey, zeroize_slice, CipherParams, Crypto, DerivedKeySet, Error as KeyStoreError,
-    KdfParams, Key, KeyChain, KeyStore, KeyTimeout, MasterPrivKey, ScryptParams, ScryptType,
+    KdfParams, Key, KeyChain, KeySource, KeyStore, KeyTimeout, MasterPrivKey, ScryptParams,
+    ScryptType,
 };
@@ -1,5 +1,4 @@
 use std::fs;
-use std::io::Write;
 use std::path::PathBuf;
 use std::time::{Duration, Instant};
 
@@ -12,14 +11,15 @@ use rustyline::{Cmd, CompletionType, Config, EditMode, Editor, KeyPress};
 use serde_json::json;
 
 use crate::subcommands::{
-    AccountSubCommand, CliSubCommand, DAOSubCommand, MockTxSubCommand, MoleculeSubCommand,
-    RpcSubCommand, TxSubCommand, UtilSubCommand, WalletSubCommand,
+    AccountSubCommand, CliSubCommand, DAOSubCommand, LiveCellsCache, MockTxSubCommand,
+    MoleculeSubCommand, RpcSubCommand, TxSubCommand, UtilSubCommand, WalletSubCommand,
 };
 use crate::utils::{
+    arg_parser::{ArgParser, FromStrParser},
     completer::CkbCompleter,
     config::GlobalConfig,
     index::{IndexController, IndexRequest},
-    other::{check_alerts, get_key_store, get_network_type, index_dirname},
+    other::{check_alerts, get_key_store, get_network_type, index_dirname, update_config},
     printer::{ColorWhen, OutputFormat, Printable},
 };
 use ckb_sdk::{rpc::RawHttpRpcClient, wallet::KeyStore, GenesisInfo, HttpRpcClient};
@@ -29,7 +29,6 @@ const ENV_PATTERN: &str = r"\$\{\s*(?P<key>\S+)\s*\}";
 /// Interactive command line
 pub struct InteractiveEnv {
     config: GlobalConfig,
-    config_file: PathBuf,
     history_file: PathBuf,
     index_dir: PathBuf,
     parser: clap::App<'static, 'static>,
@@ -38,6 +37,7 @@ pub struct InteractiveEnv {
     raw_rpc_client: RawHttpRpcClient,
     index_controller: IndexController,
     genesis_info: Option<GenesisInfo>,
+    live_cells_cache: LiveCellsCache,
 }
 
 impl InteractiveEnv {
@@ -51,8 +51,6 @@ impl InteractiveEnv {
         }
         let mut history_file = ckb_cli_dir.clone();
         history_file.push("history");
-        let mut config_file = ckb_cli_dir.clone();
-        config_file.push("config");
         let mut index_dir = ckb_cli_dir.clone();
         index_dir.push(index_dirname());
 
@@ -73,7 +71,6 @@ impl InteractiveEnv {
         let key_store = get_key_store(&ckb_cli_dir)?;
         Ok(InteractiveEnv {
             config,
-            config_file,
             index_dir,
             history_file,
             parser,
@@ -82,6 +79,7 @@ impl InteractiveEnv {
             key_store,
             index_controller,
             genesis_info: None,
+            live_cells_cache: LiveCellsCache::default(),
         })
     }
 
@@ -236,6 +234,7 @@ impl InteractiveEnv {
                         self.config
                             .set_network(get_network_type(&mut self.rpc_client).ok());
                         self.genesis_info = None;
+                        self.live_cells_cache.clear();
                         Some(())
                     });
                     if m.is_present("color") {
@@ -261,20 +260,22 @@ impl InteractiveEnv {
                     }
 
                     self.config.print();
-                    let mut file = fs::File::create(self.config_file.as_path())
-                        .map_err(|err| format!("open config error: {:?}", err))?;
-                    let content = serde_json::to_string_pretty(&json!({
-                        "url": self.config.get_url().to_string(),
-                        "color": self.config.color(),
-                        "debug": self.config.debug(),
-                        "output_format": self.config.output_format().to_string(),
-                        "completion_style": self.config.completion_style(),
-                        "edit_style": self.config.edit_style(),
-                    }))
-                    .unwrap();
-                    file.write_all(content.as_bytes())
-                        .map_err(|err| format!("save config error: {:?}", err))?;
-                    Ok(())
+                    let data_length_limit = m
+                        .value_of("data-length-limit")
+                        .map(|input| FromStrParser::<usize>::default().parse(input))
+                        .transpose()?;
+                    let config = &self.config;
+                    update_config(|configs| {
+                        configs["url"] = json!(config.get_url().to_string());
+                        configs["color"] = json!(config.color());
+                        configs["debug"] = json!(config.debug());
+                        configs["output_format"] = json!(config.output_format().to_string());
+                        configs["completion_style"] = json!(config.completion_style());
+                        configs["edit_style"] = json!(config.edit_style());
+                        if let Some(limit) = data_length_limit {
+                            configs["data_length_limit"] = json!(limit);
+                        }
+                    })
                 }
                 ("set", Some(m)) => {
                     let key = m.value_of("key").unwrap().to_owned();
@@ -348,6 +349,7 @@ impl InteractiveEnv {
                         self.index_dir.clone(),
                         self.index_controller.clone(),
                     )
+                    .with_live_cells_cache(&mut self.live_cells_cache)
                     .process(&sub_matches, format, color, debug)?;
                     println!("{}", output);
                     Ok(())
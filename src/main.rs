@@ -23,7 +23,7 @@ use subcommands::{
 };
 use utils::other::sync_to_tip;
 use utils::{
-    arg_parser::{ArgParser, UrlParser},
+    arg_parser::{ArgParser, FromStrParser, UrlParser},
     config::GlobalConfig,
     index::IndexThreadState,
     other::{check_alerts, get_key_store, get_network_type, index_dirname},
@@ -88,7 +88,21 @@ fn main() -> Result<(), io::Error> {
 
     let api_uri = config.get_url().to_string();
     let index_controller = start_index_thread(api_uri.as_str(), index_dir.clone(), index_state);
-    let mut rpc_client = HttpRpcClient::new(api_uri.clone());
+    // On SIGINT, wait for the index thread to finish the current block and close the db
+    let signal_controller = index_controller.clone();
+    if let Err(err) = ctrlc::set_handler(move || {
+        eprintln!("Interrupted, stopping index thread...");
+        signal_controller.shutdown();
+        process::exit(130);
+    }) {
+        log::warn!("Set SIGINT handler failed: {}", err);
+    }
+    // Extra --url values are the failover endpoints of read requests
+    let fallback_urls = matches
+        .values_of("url")
+        .map(|values| values.skip(1).map(ToOwned::to_owned).collect())
+        .unwrap_or_default();
+    let mut rpc_client = HttpRpcClient::new(api_uri.clone()).with_fallback_urls(fallback_urls);
     let mut raw_rpc_client = RawHttpRpcClient::from_uri(api_uri.as_str());
     check_alerts(&mut rpc_client);
     config.set_network(get_network_type(&mut rpc_client).ok());
@@ -264,8 +278,10 @@ pub fn build_cli<'a>(version_short: &'a str, version_long: &'a str) -> App<'a, '
             Arg::with_name("url")
                 .long("url")
                 .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
                 .validator(|input| UrlParser.validate(input))
-                .help("RPC API server url"),
+                .help("RPC API server url, can be given multiple times: the others are tried in order when a read request failed (transactions are only sent to the healthy one in use on the same chain, not for interactive mode)"),
         )
         .arg(
             Arg::with_name("output-format")
@@ -347,6 +363,13 @@ pub fn build_interactive() -> App<'static, 'static> {
                     Arg::with_name("edit_style")
                         .long("edit_style")
                         .help("Switch edit style"),
+                )
+                .arg(
+                    Arg::with_name("data-length-limit")
+                        .long("data-length-limit")
+                        .takes_value(true)
+                        .validator(|input| FromStrParser::<usize>::default().validate(input))
+                        .help("Default max data length (bytes) of `wallet transfer` output (default: 512KB)"),
                 ),
         )
         .subcommand(SubCommand::with_name("info").about("Display global variables"))
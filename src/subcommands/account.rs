@@ -4,7 +4,7 @@ use std::path::{Path, PathBuf};
 use std::time::Duration;
 
 use ckb_sdk::{
-    wallet::{DerivationPath, Key, KeyStore, MasterPrivKey},
+    wallet::{DerivationPath, Key, KeySource, KeyStore, MasterPrivKey, ScryptType},
     Address, AddressPayload, NetworkType,
 };
 use ckb_types::{packed::Script, prelude::*, H160, H256};
@@ -12,12 +12,12 @@ use clap::{App, Arg, ArgMatches, SubCommand};
 
 use super::CliSubCommand;
 use crate::utils::{
-    arg::lock_arg,
+    arg::{from_account, lock_arg},
     arg_parser::{
-        ArgParser, DurationParser, ExtendedPrivkeyPathParser, FilePathParser, FixedHashParser,
-        FromStrParser, PrivkeyPathParser, PrivkeyWrapper,
+        AccountParser, ArgParser, DerivationPathParser, DurationParser, ExtendedPrivkeyPathParser,
+        FilePathParser, FixedHashParser, FromStrParser, PrivkeyPathParser, PrivkeyWrapper,
     },
-    other::read_password,
+    other::{read_default_account, read_password, write_default_account, Password},
     printer::{OutputFormat, Printable},
 };
 
@@ -41,7 +41,13 @@ impl<'a> AccountSubCommand<'a> {
         SubCommand::with_name(name)
             .about("Manage accounts")
             .subcommands(vec![
-                SubCommand::with_name("list").about("List all accounts"),
+                SubCommand::with_name("list")
+                    .about("List all accounts")
+                    .arg(
+                        Arg::with_name("show-source")
+                            .long("show-source")
+                            .help("Show how each account is created (generated/privkey/xprv/mnemonic/keystore-import, unknown for accounts created by old version)")
+                    ),
                 SubCommand::with_name("new").about("Create a new account and print related information."),
                 SubCommand::with_name("import")
                     .about("Import an unencrypted private key from <privkey-path> and create a new account.")
@@ -56,6 +62,14 @@ impl<'a> AccountSubCommand<'a> {
                          .clone()
                          .required_unless("privkey-path")
                          .validator(|input| ExtendedPrivkeyPathParser.validate(input))
+                    )
+                    .arg(
+                        Arg::with_name("path")
+                            .long("path")
+                            .takes_value(true)
+                            .requires("extended-privkey-path")
+                            .validator(|input| DerivationPathParser.validate(input))
+                            .help("Import the key derived from the extended private key with this BIP-32 path instead, hardened segments end with ' (eg: m/44'/309'/0')")
                     ),
                 SubCommand::with_name("import-keystore")
                     .about("Import key from encrypted keystore json file and create a new account.")
@@ -78,6 +92,10 @@ impl<'a> AccountSubCommand<'a> {
                             .required(true)
                             .help("How long before the key expired, format: 30s, 15m, 1h (repeat unlock will increase the time)")
                     ),
+                SubCommand::with_name("set-default")
+                    .about("Set the default account, used when --from-account is absent")
+                    .arg(from_account().required(true)),
+                SubCommand::with_name("get-default").about("Show the default account"),
                 SubCommand::with_name("update")
                     .about("Update password of an account")
                     .arg(lock_arg().required(true)),
@@ -90,6 +108,37 @@ impl<'a> AccountSubCommand<'a> {
                             .required(true)
                             .help("Output extended private key path (PrivKey + ChainCode)")
                     ),
+                SubCommand::with_name("export-keystore")
+                    .about("Export an account as encrypted keystore json file (re-encrypted with a new password)")
+                    .arg(from_account().required(true))
+                    .arg(
+                        Arg::with_name("output")
+                            .long("output")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| FilePathParser::new(false).validate(input))
+                            .help("Output keystore file path (json format)")
+                    ),
+                SubCommand::with_name("backup-all")
+                    .about("Export all accounts into one backup file, encrypted with a backup password (KEEP IT SAFE)")
+                    .arg(
+                        Arg::with_name("output")
+                            .long("output")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| FilePathParser::new(false).validate(input))
+                            .help("Output backup file path (json format), write `ckb-cli-backup.json` in it if a directory is given")
+                    ),
+                SubCommand::with_name("restore-all")
+                    .about("Import all accounts from a backup file created by `account backup-all`")
+                    .arg(
+                        Arg::with_name("path")
+                            .long("path")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| FilePathParser::new(true).validate(input))
+                            .help("The backup file path (json format)")
+                    ),
                 SubCommand::with_name("bip44-addresses")
                     .about("Extended receiving/change Addresses (see: BIP-44)")
                     .arg(
@@ -148,7 +197,8 @@ impl<'a> CliSubCommand for AccountSubCommand<'a> {
         _debug: bool,
     ) -> Result<String, String> {
         match matches.subcommand() {
-            ("list", _) => {
+            ("list", Some(m)) => {
+                let show_source = m.is_present("show-source");
                 let mut accounts = self
                     .key_store
                     .get_accounts()
@@ -164,7 +214,7 @@ impl<'a> CliSubCommand for AccountSubCommand<'a> {
                         let lock_hash: H256 = Script::from(&address_payload)
                             .calc_script_hash()
                             .unpack();
-                        serde_json::json!({
+                        let mut value = serde_json::json!({
                             "#": idx,
                             "lock_arg": format!("{:#x}", lock_arg),
                             "lock_hash": format!("{:#x}", lock_hash),
@@ -172,9 +222,19 @@ impl<'a> CliSubCommand for AccountSubCommand<'a> {
                                 "mainnet": Address::new(NetworkType::Mainnet, address_payload.clone()).to_string(),
                                 "testnet": Address::new(NetworkType::Testnet, address_payload).to_string(),
                             },
-                        })
+                        });
+                        if show_source {
+                            let source = self
+                                .key_store
+                                .get_source(&lock_arg)
+                                .map_err(|err| err.to_string())?
+                                .map(KeySource::as_str)
+                                .unwrap_or("unknown");
+                            value["source"] = serde_json::json!(source);
+                        }
+                        Ok(value)
                     })
-                    .collect::<Vec<_>>();
+                    .collect::<Result<Vec<_>, String>>()?;
                 Ok(serde_json::json!(resp).render(format, color))
             }
             ("new", _) => {
@@ -206,9 +266,14 @@ impl<'a> CliSubCommand for AccountSubCommand<'a> {
                         .import_secp_key(&secp_key, password.as_bytes())
                         .map_err(|err| err.to_string())?
                 } else {
-                    let master_privkey: MasterPrivKey =
+                    let mut master_privkey: MasterPrivKey =
                         ExtendedPrivkeyPathParser.from_matches(m, "extended-privkey-path")?;
-                    let key = Key::new(master_privkey);
+                    let path: Option<DerivationPath> =
+                        DerivationPathParser.from_matches_opt(m, "path", false)?;
+                    if let Some(path) = path {
+                        master_privkey = master_privkey.sub_master_privkey(&path);
+                    }
+                    let key = Key::new(master_privkey).with_source(KeySource::ExtendedPrivkey);
                     self.key_store
                         .import_key(&key, password.as_bytes())
                         .map_err(|err| err.to_string())?
@@ -260,6 +325,20 @@ impl<'a> CliSubCommand for AccountSubCommand<'a> {
                 });
                 Ok(resp.render(format, color))
             }
+            ("set-default", Some(m)) => {
+                let lock_arg: H160 = AccountParser::default().from_matches(m, "from-account")?;
+                if !self.key_store.get_accounts().contains_key(&lock_arg) {
+                    return Err(format!("Account not found: {:#x}", lock_arg));
+                }
+                write_default_account(&lock_arg)?;
+                Ok("success".to_owned())
+            }
+            ("get-default", _) => {
+                let resp = serde_json::json!({
+                    "default_account": read_default_account()?.map(|lock_arg| format!("{:#x}", lock_arg)),
+                });
+                Ok(resp.render(format, color))
+            }
             ("update", Some(m)) => {
                 let lock_arg: H160 =
                     FixedHashParser::<H160>::default().from_matches(m, "lock-arg")?;
@@ -296,6 +375,102 @@ impl<'a> CliSubCommand for AccountSubCommand<'a> {
                     key_path
                 ))
             }
+            ("export-keystore", Some(m)) => {
+                let lock_arg: H160 = AccountParser::default().from_matches(m, "from-account")?;
+                let output: PathBuf = FilePathParser::new(false).from_matches(m, "output")?;
+                if output.exists() {
+                    return Err(format!("File exists: {}", output.to_string_lossy()));
+                }
+                let password = read_password(false, None)?;
+                let new_password = read_password(true, Some("Export password"))?;
+                let data = self
+                    .key_store
+                    .export(
+                        &lock_arg,
+                        password.as_bytes(),
+                        new_password.as_bytes(),
+                        ScryptType::default(),
+                    )
+                    .map_err(|err| err.to_string())?;
+                // Make sure the exported file can be imported back to the same account
+                let key = Key::from_json(&data, new_password.as_bytes())
+                    .map_err(|err| err.to_string())?;
+                if key.hash160() != &lock_arg {
+                    return Err(format!(
+                        "Exported keystore round-trip check failed: {:#x} => {:#x}",
+                        lock_arg,
+                        key.hash160()
+                    ));
+                }
+                let content = serde_json::to_string_pretty(&data).map_err(|err| err.to_string())?;
+                fs::write(&output, content).map_err(|err| err.to_string())?;
+                eprintln!("[WARNING] the exported keystore file is only as safe as its password");
+                let resp = serde_json::json!({
+                    "lock_arg": format!("{:#x}", lock_arg),
+                    "output": output,
+                });
+                Ok(resp.render(format, color))
+            }
+            ("backup-all", Some(m)) => {
+                let mut output: PathBuf = FilePathParser::new(false).from_matches(m, "output")?;
+                if output.is_dir() {
+                    output.push("ckb-cli-backup.json");
+                }
+                if output.exists() {
+                    return Err(format!("File exists: {}", output.to_string_lossy()));
+                }
+                let mut lock_args = self
+                    .key_store
+                    .get_accounts()
+                    .keys()
+                    .cloned()
+                    .collect::<Vec<_>>();
+                if lock_args.is_empty() {
+                    return Err("No account to backup".to_owned());
+                }
+                lock_args.sort();
+                let backup_password = read_password(true, Some("Backup password"))?;
+                let bundle = export_backup(
+                    self.key_store,
+                    &lock_args,
+                    backup_password.as_bytes(),
+                    ScryptType::default(),
+                    |lock_arg| read_password(false, Some(&format!("Password of {:#x}", lock_arg))),
+                )?;
+                let content =
+                    serde_json::to_string_pretty(&bundle).map_err(|err| err.to_string())?;
+                fs::write(&output, content).map_err(|err| err.to_string())?;
+                eprintln!("[WARNING] anyone with this backup file and its password controls ALL the accounts, store it offline and securely");
+                let resp = serde_json::json!({
+                    "accounts": lock_args.len(),
+                    "output": output,
+                });
+                Ok(resp.render(format, color))
+            }
+            ("restore-all", Some(m)) => {
+                let path: PathBuf = FilePathParser::new(true).from_matches(m, "path")?;
+                let content = fs::read_to_string(path).map_err(|err| err.to_string())?;
+                let bundle: serde_json::Value =
+                    serde_json::from_str(&content).map_err(|err| err.to_string())?;
+                let backup_password = read_password(false, Some("Backup password"))?;
+                let keys = read_backup_keys(self.key_store, &bundle, backup_password.as_bytes())?;
+                let new_password = read_password(true, None)?;
+                let mut restored = Vec::new();
+                for key in &keys {
+                    let lock_arg = self
+                        .key_store
+                        .import_key(key, new_password.as_bytes())
+                        .map_err(|err| err.to_string())?;
+                    restored.push(lock_arg);
+                }
+                let resp = serde_json::json!({
+                    "restored": restored
+                        .iter()
+                        .map(|lock_arg| format!("{:#x}", lock_arg))
+                        .collect::<Vec<_>>(),
+                });
+                Ok(resp.render(format, color))
+            }
             ("bip44-addresses", Some(m)) => {
                 let lock_arg: H160 =
                     FixedHashParser::<H160>::default().from_matches(m, "lock-arg")?;
@@ -363,3 +538,141 @@ impl<'a> CliSubCommand for AccountSubCommand<'a> {
         }
     }
 }
+
+// Re-encrypt the keystores of the accounts with the backup password into one bundle
+fn export_backup<F>(
+    key_store: &KeyStore,
+    lock_args: &[H160],
+    backup_password: &[u8],
+    scrypt_type: ScryptType,
+    mut get_password: F,
+) -> Result<serde_json::Value, String>
+where
+    F: FnMut(&H160) -> Result<Password, String>,
+{
+    let mut keystores = Vec::new();
+    for lock_arg in lock_args {
+        let password = get_password(lock_arg)?;
+        let data = key_store
+            .export(lock_arg, password.as_bytes(), backup_password, scrypt_type)
+            .map_err(|err| format!("Export {:#x} failed: {}", lock_arg, err))?;
+        keystores.push(data);
+    }
+    Ok(serde_json::json!({
+        "version": 1,
+        "lock_args": lock_args
+            .iter()
+            .map(|lock_arg| format!("{:#x}", lock_arg))
+            .collect::<Vec<_>>(),
+        "keystores": keystores,
+    }))
+}
+
+// Decrypt all the keys of a backup bundle and check them against its lock_args and the existing
+// accounts. Nothing is stored here, so a bad bundle restores no account at all.
+fn read_backup_keys(
+    key_store: &mut KeyStore,
+    bundle: &serde_json::Value,
+    backup_password: &[u8],
+) -> Result<Vec<Key>, String> {
+    let mut expected = bundle["lock_args"]
+        .as_array()
+        .ok_or_else(|| "Invalid backup file: missing lock_args".to_owned())?
+        .iter()
+        .map(|value| {
+            value
+                .as_str()
+                .ok_or_else(|| "Invalid backup file: bad lock_arg".to_owned())
+                .and_then(|s| FixedHashParser::<H160>::default().parse(s))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    let keys = bundle["keystores"]
+        .as_array()
+        .ok_or_else(|| "Invalid backup file: missing keystores".to_owned())?
+        .iter()
+        .enumerate()
+        .map(|(idx, data)| {
+            Key::from_json(data, backup_password)
+                .map(|key| key.with_source(KeySource::KeystoreImport))
+                .map_err(|err| format!("Decrypt keystore #{} failed: {}", idx, err))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    let mut restored = keys
+        .iter()
+        .map(|key| key.hash160().clone())
+        .collect::<Vec<_>>();
+    expected.sort();
+    restored.sort();
+    if expected != restored {
+        return Err(format!(
+            "Accounts in the backup mismatch its lock_args: expected {}, found {}",
+            expected.len(),
+            restored.len()
+        ));
+    }
+    if restored.windows(2).any(|pair| pair[0] == pair[1]) {
+        return Err("Invalid backup file: duplicated account".to_owned());
+    }
+    if let Some(lock_arg) = restored
+        .iter()
+        .find(|lock_arg| key_store.has_account(lock_arg))
+    {
+        return Err(format!("Account already exists: {:#x}", lock_arg));
+    }
+    Ok(keys)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::process;
+
+    fn temp_key_store(name: &str) -> KeyStore {
+        let dir = env::temp_dir().join(format!("ckb-cli-test-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        KeyStore::from_dir(dir, ScryptType::Light).unwrap()
+    }
+
+    #[test]
+    fn test_backup_restore_round_trip() {
+        let mut source = temp_key_store("backup-source");
+        let lock_args = vec![
+            source.new_account(b"password-0").unwrap(),
+            source.new_account(b"password-1").unwrap(),
+        ];
+        let bundle = export_backup(
+            &source,
+            &lock_args,
+            b"backup",
+            ScryptType::Light,
+            |lock_arg| {
+                let idx = lock_args.iter().position(|arg| arg == lock_arg).unwrap();
+                Ok(Password::new(format!("password-{}", idx)))
+            },
+        )
+        .unwrap();
+
+        let mut target = temp_key_store("backup-target");
+        assert!(read_backup_keys(&mut target, &bundle, b"wrong").is_err());
+        for key in read_backup_keys(&mut target, &bundle, b"backup").unwrap() {
+            target.import_key(&key, b"new").unwrap();
+        }
+        for (idx, lock_arg) in lock_args.iter().enumerate() {
+            let password = format!("password-{}", idx);
+            let expected = source.export_key(lock_arg, password.as_bytes()).unwrap();
+            let restored = target.export_key(lock_arg, b"new").unwrap();
+            assert_eq!(restored.to_bytes().to_vec(), expected.to_bytes().to_vec());
+        }
+        // Restoring again is refused before any key is stored
+        assert!(read_backup_keys(&mut target, &bundle, b"backup").is_err());
+
+        // The accounts must match the lock_args of the bundle
+        let mut partial = bundle.clone();
+        partial["lock_args"] = serde_json::json!([format!("{:#x}", lock_args[0])]);
+        let mut empty = temp_key_store("backup-empty");
+        assert!(read_backup_keys(&mut empty, &partial, b"backup").is_err());
+        assert!(empty.get_accounts().is_empty());
+    }
+}
@@ -24,7 +24,9 @@ use jsonrpc_server_utils::cors::AccessControlAllowOrigin;
 use jsonrpc_server_utils::hosts::DomainsValidation;
 use serde::{Deserialize, Serialize};
 
-use super::{CliSubCommand, LiveCells, TransferArgs, WalletSubCommand};
+use super::{
+    CapacityValidationMode, CliSubCommand, LiveCells, Recipient, TransferArgs, WalletSubCommand,
+};
 use crate::utils::{
     arg,
     arg_parser::{AddressParser, ArgParser, FromStrParser, PrivkeyPathParser, PrivkeyWrapper},
@@ -261,8 +263,11 @@ impl ApiRpc for ApiRpcImpl {
         log::info!("[call]: tranfer({:?})", args);
         if let Some(privkey_path) = self.privkey_path.clone() {
             self.with_wallet(|cmd| {
-                cmd.transfer(args.into_full_args(privkey_path), false)
-                    .map_err(RpcError::invalid_params)
+                cmd.transfer(
+                    args.into_full_args(privkey_path),
+                    CapacityValidationMode::default(),
+                )
+                .map_err(RpcError::invalid_params)
             })
             .map(|tx| tx.hash().unpack())
         } else {
@@ -438,15 +443,15 @@ impl HttpTransferArgs {
         let tx_fee = HumanCapacity::from(self.tx_fee).to_string();
         TransferArgs {
             privkey_path: Some(privkey_path),
-            from_account: None,
             from_locked_address: self.from_locked_address,
-            password: None,
-            derive_receiving_address_length: None,
-            derive_change_address: None,
-            capacity,
+            recipients: vec![Recipient {
+                address: Some(self.to_address),
+                lock: None,
+                capacity,
+                data: self.to_data.unwrap_or_default(),
+            }],
             tx_fee,
-            to_address: self.to_address,
-            to_data: self.to_data,
+            ..Default::default()
         }
     }
 }
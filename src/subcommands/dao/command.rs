@@ -1,12 +1,14 @@
-use crate::subcommands::dao::util::{calculate_dao_maximum_withdraw, send_transaction};
+use crate::subcommands::dao::util::{
+    calculate_dao_maximum_withdraw, send_transaction, send_transaction_hash,
+};
 use crate::subcommands::{CliSubCommand, DAOSubCommand};
 use crate::utils::{
     arg,
     arg_parser::{
-        AddressParser, ArgParser, CapacityParser, FixedHashParser, OutPointParser,
+        AccountParser, ArgParser, CapacityParser, FixedHashParser, OutPointParser,
         PrivkeyPathParser, PrivkeyWrapper,
     },
-    other::{get_address, get_network_type},
+    other::{get_address, get_default_account, get_network_type},
     printer::{OutputFormat, Printable},
 };
 use ckb_crypto::secp::SECP256K1;
@@ -33,7 +35,14 @@ impl<'a> CliSubCommand for DAOSubCommand<'a> {
                 self.transact_args = Some(TransactArgs::from_matches(m, network_type)?);
                 let capacity: u64 = CapacityParser.from_matches(m, "capacity")?;
                 let transaction = self.deposit(capacity)?;
-                send_transaction(self.rpc_client(), transaction, format, color, debug)
+                let tx_hash =
+                    send_transaction_hash(self.rpc_client(), transaction, format, color, debug)?;
+                // The deposit cell is the first output, in the format of `prepare --out-point`
+                let resp = serde_json::json!({
+                    "tx_hash": tx_hash,
+                    "deposit_out_point": format!("{:#x}-0", tx_hash),
+                });
+                Ok(resp.render(format, color))
             }
             ("prepare", Some(m)) => {
                 self.transact_args = Some(TransactArgs::from_matches(m, network_type)?);
@@ -126,6 +135,7 @@ pub(crate) struct TransactArgs {
     pub(crate) privkey: Option<PrivkeyWrapper>,
     pub(crate) address: Address,
     pub(crate) tx_fee: u64,
+    pub(crate) password_file: Option<String>,
 }
 
 impl QueryArgs {
@@ -156,35 +166,29 @@ impl TransactArgs {
             let payload = AddressPayload::from_pubkey(&pubkey);
             Address::new(network_type, payload)
         } else {
-            let account: Option<H160> = FixedHashParser::<H160>::default()
+            let account: Option<H160> = AccountParser::new(Some(network_type))
                 .from_matches_opt(m, "from-account", false)
-                .or_else(|err| {
-                    let result: Result<Option<Address>, String> = AddressParser::new_sighash()
-                        .set_network(network_type)
-                        .from_matches_opt(m, "from-account", false);
-                    result
-                        .map(|address_opt| {
-                            address_opt
-                                .map(|address| H160::from_slice(&address.payload().args()).unwrap())
-                        })
-                        .map_err(|_| format!("Invalid value for '--from-account': {}", err))
-                })?;
+                .map_err(|err| format!("Invalid value for '--from-account': {}", err))?
+                .or(get_default_account(m)?);
             let payload = AddressPayload::from_pubkey_hash(account.unwrap());
             Address::new(network_type, payload)
         };
         assert_eq!(address.payload().code_hash(), SIGHASH_TYPE_HASH.pack());
         let tx_fee: u64 = CapacityParser.from_matches(m, "tx-fee")?;
+        let password_file = m.value_of("password-file").map(|s| s.to_string());
         Ok(Self {
             privkey,
             address,
             tx_fee,
+            password_file,
         })
     }
 
     fn args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
         vec![
-            arg::privkey_path().required_unless(arg::from_account().b.name),
-            arg::from_account().required_unless(arg::privkey_path().b.name),
+            arg::privkey_path(),
+            arg::from_account().conflicts_with(arg::privkey_path().b.name),
+            arg::password_file().conflicts_with(arg::privkey_path().b.name),
             arg::tx_fee().required(true),
         ]
     }
@@ -3,7 +3,7 @@ use self::command::TransactArgs;
 use crate::utils::index::IndexController;
 use crate::utils::other::{
     get_max_mature_number, get_network_type, get_privkey_signer, is_mature, read_password,
-    serialize_signature,
+    read_password_file, serialize_signature, Password,
 };
 use byteorder::{ByteOrder, LittleEndian};
 use ckb_hash::new_blake2b;
@@ -27,7 +27,7 @@ use std::path::PathBuf;
 
 mod builder;
 mod command;
-mod util;
+pub(crate) mod util;
 
 // Should CLI handle "immature header problem"?
 pub struct DAOSubCommand<'a> {
@@ -263,7 +263,10 @@ impl<'a> DAOSubCommand<'a> {
                 if let Some(ref privkey) = self.transact_args().privkey {
                     get_privkey_signer(privkey.clone())
                 } else {
-                    let password = read_password(false, None)?;
+                    let password = match self.transact_args().password_file.as_ref() {
+                        Some(path) => read_password_file(path)?,
+                        None => read_password(false, None)?,
+                    };
                     get_keystore_signer(self.key_store.clone(), account.clone(), password)
                 }
             };
@@ -320,7 +323,7 @@ impl<'a> DAOSubCommand<'a> {
 }
 
 // TODO remove the duplicated function later
-fn get_keystore_signer(key_store: KeyStore, account: H160, password: String) -> SignerFn {
+fn get_keystore_signer(key_store: KeyStore, account: H160, password: Password) -> SignerFn {
     Box::new(move |lock_args: &HashSet<H160>, message: &H256| {
         if lock_args.contains(&account) {
             if message == &h256!("0x0") {
@@ -2,6 +2,7 @@ use crate::utils::{
     other::check_lack_of_capacity,
     printer::{OutputFormat, Printable},
 };
+use byteorder::{ByteOrder, LittleEndian};
 use ckb_dao_utils::extract_dao_data;
 use ckb_index::LiveCellInfo;
 use ckb_sdk::HttpRpcClient;
@@ -11,6 +12,7 @@ use ckb_types::{
     core::{EpochNumber, EpochNumberWithFraction, HeaderView},
     packed,
     prelude::*,
+    H256,
 };
 
 pub(crate) fn calculate_dao_maximum_withdraw(
@@ -76,6 +78,49 @@ pub(crate) fn calculate_dao_maximum_withdraw(
     ))
 }
 
+// The capacity a DAO cell can be withdrawn now: deposit cells are counted to the tip header,
+// prepared cells to their prepare header
+pub(crate) fn calculate_dao_redeemable(
+    rpc_client: &mut HttpRpcClient,
+    dao_cell: &LiveCellInfo,
+) -> Result<u64, String> {
+    let cell = rpc_client
+        .get_live_cell(dao_cell.out_point(), true)?
+        .cell
+        .ok_or_else(|| "invalid dao cell, the cell is not live".to_string())?;
+    let content = cell
+        .data
+        .ok_or_else(|| "invalid dao cell, the data is not found".to_string())?
+        .content;
+    if content.len() != 8 {
+        return Err("invalid dao cell, the data is not 8 bytes".to_string());
+    }
+    if LittleEndian::read_u64(content.as_bytes()) != 0 {
+        return calculate_dao_maximum_withdraw(rpc_client, dao_cell);
+    }
+    let deposit_block_hash = rpc_client
+        .get_transaction(dao_cell.tx_hash.clone())?
+        .ok_or_else(|| "invalid deposit out_point, the tx is not found".to_string())?
+        .tx_status
+        .block_hash
+        .ok_or("invalid deposit out_point, the tx is not committed")?;
+    let deposit_header: HeaderView = rpc_client
+        .get_header(deposit_block_hash)?
+        .ok_or_else(|| "failed to get deposit_header".to_string())?
+        .into();
+    let tip_header: HeaderView = rpc_client.get_tip_header()?.into();
+    let output: CellOutput = cell.output.into();
+    let occupied_capacity = output
+        .occupied_capacity(Capacity::bytes(content.len()).unwrap())
+        .unwrap();
+    Ok(calculate_dao_maximum_withdraw4(
+        &deposit_header,
+        &tip_header,
+        &output,
+        occupied_capacity.as_u64(),
+    ))
+}
+
 pub(crate) fn calculate_dao_maximum_withdraw4(
     deposit_header: &HeaderView,
     prepare_header: &HeaderView,
@@ -98,6 +143,17 @@ pub(crate) fn send_transaction(
     color: bool,
     debug: bool,
 ) -> Result<String, String> {
+    let resp = send_transaction_hash(rpc_client, transaction, format, color, debug)?;
+    Ok(resp.render(format, color))
+}
+
+pub(crate) fn send_transaction_hash(
+    rpc_client: &mut HttpRpcClient,
+    transaction: TransactionView,
+    format: OutputFormat,
+    color: bool,
+    debug: bool,
+) -> Result<H256, String> {
     check_lack_of_capacity(&transaction)?;
     let transaction_view: ckb_jsonrpc_types::TransactionView = transaction.clone().into();
     if debug {
@@ -107,8 +163,7 @@ pub(crate) fn send_transaction(
         );
     }
 
-    let resp = rpc_client.send_transaction(transaction.data())?;
-    Ok(resp.render(format, color))
+    rpc_client.send_transaction(transaction.data())
 }
 
 pub(crate) fn minimal_unlock_point(
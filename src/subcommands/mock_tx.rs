@@ -235,8 +235,8 @@ impl<'a> CliSubCommand for MockTxSubCommand<'a> {
     }
 }
 
-struct Loader<'a> {
-    rpc_client: &'a mut HttpRpcClient,
+pub(crate) struct Loader<'a> {
+    pub(crate) rpc_client: &'a mut HttpRpcClient,
 }
 
 impl<'a> MockResourceLoader for Loader<'a> {
@@ -21,7 +21,10 @@ pub use molecule::MoleculeSubCommand;
 pub use rpc::RpcSubCommand;
 pub use tx::TxSubCommand;
 pub use util::UtilSubCommand;
-pub use wallet::{start_index_thread, LiveCells, TransferArgs, WalletSubCommand};
+pub use wallet::{
+    start_index_thread, CapacityValidationMode, ChangeThresholdAction, LiveCells, LiveCellsCache,
+    Recipient, TransferArgs, WalletSubCommand,
+};
 
 use clap::ArgMatches;
 
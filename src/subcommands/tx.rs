@@ -5,10 +5,11 @@ use std::io::Write;
 use std::path::PathBuf;
 use std::str::FromStr;
 
+use byteorder::{ByteOrder, LittleEndian};
 use ckb_jsonrpc_types as json_types;
 use ckb_jsonrpc_types::JsonBytes;
 use ckb_sdk::{
-    constants::{MULTISIG_TYPE_HASH, SECP_SIGNATURE_SIZE},
+    constants::{DAO_TYPE_HASH, MULTISIG_TYPE_HASH, SECP_SIGNATURE_SIZE},
     wallet::KeyStore,
     Address, AddressPayload, CodeHashIndex, GenesisInfo, HttpRpcClient, HumanCapacity,
     MultisigConfig, NetworkType, SignerFn, TxHelper,
@@ -33,8 +34,9 @@ use crate::utils::{
         HexParser, PrivkeyPathParser, PrivkeyWrapper,
     },
     other::{
-        check_capacity, get_genesis_info, get_live_cell, get_live_cell_with_cache,
-        get_network_type, get_privkey_signer, get_to_data, read_password, serialize_signature,
+        check_capacity, get_default_account, get_genesis_info, get_live_cell,
+        get_live_cell_with_cache, get_network_type, get_password, get_privkey_signer, get_to_data,
+        serialize_signature, Password,
     },
     printer::{OutputFormat, Printable},
 };
@@ -135,6 +137,11 @@ impl<'a> TxSubCommand<'a> {
                             .help("Transaction output index"),
                     )
                     .arg(arg_since_absolute_epoch.clone())
+                    .arg(
+                        Arg::with_name("attach-header-dep-for-dao")
+                            .long("attach-header-dep-for-dao")
+                            .help("If the input is a DAO cell, add the required header deps (the block of this cell, and the deposit block for a withdrawing cell)"),
+                    )
                     .arg(arg_tx_file.clone())
                     .arg(arg_skip_check.clone()),
                 SubCommand::with_name("add-output")
@@ -205,8 +212,9 @@ impl<'a> TxSubCommand<'a> {
                     .arg(arg_tx_file.clone()),
                 SubCommand::with_name("sign-inputs")
                     .about("Sign all sighash/multisig inputs in this transaction")
-                    .arg(arg::privkey_path().required_unless(arg::from_account().b.name))
-                    .arg(arg::from_account().required_unless(arg::privkey_path().b.name))
+                    .arg(arg::privkey_path())
+                    .arg(arg::from_account().conflicts_with(arg::privkey_path().b.name))
+                    .arg(arg::password_file().conflicts_with(arg::privkey_path().b.name))
                     .arg(arg_tx_file.clone())
                     .arg(
                         Arg::with_name("add-signatures")
@@ -234,6 +242,39 @@ impl<'a> TxSubCommand<'a> {
                     .arg(arg_require_first_n.clone())
                     .arg(arg_threshold.clone())
                     .arg(arg_since_absolute_epoch.clone()),
+                SubCommand::with_name("convert")
+                    .about("Convert transaction between json (TransactionView) and molecule hex format")
+                    .arg(
+                        Arg::with_name("from")
+                            .long("from")
+                            .takes_value(true)
+                            .required(true)
+                            .possible_values(&["json", "hex"])
+                            .help("The input format"),
+                    )
+                    .arg(
+                        Arg::with_name("to")
+                            .long("to")
+                            .takes_value(true)
+                            .required(true)
+                            .possible_values(&["json", "hex"])
+                            .help("The output format"),
+                    )
+                    .arg(
+                        Arg::with_name("input")
+                            .long("input")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| FilePathParser::new(true).validate(input))
+                            .help("The input transaction file"),
+                    )
+                    .arg(
+                        Arg::with_name("output")
+                            .long("output")
+                            .takes_value(true)
+                            .validator(|input| FilePathParser::new(false).validate(input))
+                            .help("The output transaction file (print to stdout if not given)"),
+                    ),
             ])
     }
 }
@@ -294,6 +335,11 @@ impl<'a> CliSubCommand for TxSubCommand<'a> {
                     .tx_hash(tx_hash.pack())
                     .index(index.pack())
                     .build();
+                let header_deps = if m.is_present("attach-header-dep-for-dao") {
+                    get_dao_header_deps(self.rpc_client, &out_point)?
+                } else {
+                    Vec::new()
+                };
                 let get_live_cell = |out_point, with_data| {
                     get_live_cell(self.rpc_client, out_point, with_data).map(|(output, _)| output)
                 };
@@ -304,7 +350,18 @@ impl<'a> CliSubCommand for TxSubCommand<'a> {
                         get_live_cell,
                         &genesis_info,
                         skip_check,
-                    )
+                    )?;
+                    for block_hash in header_deps {
+                        if !helper
+                            .transaction()
+                            .header_deps()
+                            .into_iter()
+                            .any(|hash| hash == block_hash)
+                        {
+                            helper.add_header_dep(block_hash);
+                        }
+                    }
+                    Ok(())
                 })?;
 
                 Ok(String::from("ok"))
@@ -457,13 +514,14 @@ impl<'a> CliSubCommand for TxSubCommand<'a> {
                 let privkey_opt: Option<PrivkeyWrapper> =
                     PrivkeyPathParser.from_matches_opt(m, "privkey-path", false)?;
                 let account_opt: Option<H160> = FixedHashParser::<H160>::default()
-                    .from_matches_opt(m, "from-account", false)?;
+                    .from_matches_opt(m, "from-account", false)?
+                    .or(get_default_account(m)?);
                 let skip_check: bool = m.is_present("skip-check");
 
                 let signer = if let Some(privkey) = privkey_opt {
                     get_privkey_signer(privkey)
                 } else {
-                    let password = read_password(false, None)?;
+                    let password = get_password(m, None)?;
                     let account = account_opt.unwrap();
                     let key_store = self.key_store.clone();
                     get_keystore_signer(key_store, account, password)
@@ -571,11 +629,74 @@ impl<'a> CliSubCommand for TxSubCommand<'a> {
                 });
                 Ok(resp.render(format, color))
             }
+            ("convert", Some(m)) => {
+                let input: PathBuf = FilePathParser::new(true).from_matches(m, "input")?;
+                let output_opt: Option<PathBuf> =
+                    FilePathParser::new(false).from_matches_opt(m, "output", false)?;
+                let content = fs::read_to_string(&input).map_err(|err| err.to_string())?;
+                let tx = match m.value_of("from") {
+                    Some("json") => parse_json_tx(&content)?,
+                    _ => parse_hex_tx(&content)?,
+                };
+                let tx_hash = tx.calc_tx_hash();
+                let (output, tx_converted) = match m.value_of("to") {
+                    Some("json") => {
+                        let tx_view = json_types::TransactionView::from(tx.into_view());
+                        let output = serde_json::to_string_pretty(&tx_view)
+                            .map_err(|err| err.to_string())?;
+                        (output.clone(), parse_json_tx(&output)?)
+                    }
+                    _ => {
+                        let output = format!("0x{}", hex_string(tx.as_slice()).unwrap());
+                        (output.clone(), parse_hex_tx(&output)?)
+                    }
+                };
+                if tx_converted.calc_tx_hash() != tx_hash {
+                    return Err(format!(
+                        "Transaction hash changed after convert: {:#x} => {:#x}",
+                        tx_hash,
+                        tx_converted.calc_tx_hash()
+                    ));
+                }
+                if let Some(output_path) = output_opt {
+                    fs::write(&output_path, output).map_err(|err| err.to_string())?;
+                    let tx_hash: H256 = tx_hash.unpack();
+                    Ok(tx_hash.render(format, color))
+                } else {
+                    Ok(output)
+                }
+            }
             _ => Err(matches.usage().to_owned()),
         }
     }
 }
 
+// Parse json format transaction (TransactionView or Transaction)
+pub(crate) fn parse_json_tx(content: &str) -> Result<packed::Transaction, String> {
+    if let Ok(tx_view) = serde_json::from_str::<json_types::TransactionView>(content) {
+        let tx: packed::Transaction = tx_view.inner.into();
+        if tx.calc_tx_hash() != tx_view.hash.pack() {
+            return Err(format!(
+                "Transaction hash not match, expected: {:#x}, got: {:#x}",
+                tx_view.hash,
+                tx.calc_tx_hash()
+            ));
+        }
+        Ok(tx)
+    } else {
+        serde_json::from_str::<json_types::Transaction>(content)
+            .map(Into::into)
+            .map_err(|err| format!("Invalid json transaction: {}", err))
+    }
+}
+
+// Parse molecule hex format transaction
+fn parse_hex_tx(content: &str) -> Result<packed::Transaction, String> {
+    let binary = HexParser.parse(content.trim())?;
+    packed::Transaction::from_slice(&binary)
+        .map_err(|err| format!("Invalid molecule transaction: {}", err))
+}
+
 fn print_cell_info(
     prefix: &str,
     network: NetworkType,
@@ -607,7 +728,7 @@ fn print_cell_info(
     );
 }
 
-fn get_keystore_signer(key_store: KeyStore, account: H160, password: String) -> SignerFn {
+fn get_keystore_signer(key_store: KeyStore, account: H160, password: Password) -> SignerFn {
     Box::new(move |lock_args: &HashSet<H160>, message: &H256| {
         if lock_args.contains(&account) {
             if message == &h256!("0x0") {
@@ -624,6 +745,38 @@ fn get_keystore_signer(key_store: KeyStore, account: H160, password: String) ->
     })
 }
 
+// Header deps required to spend a DAO cell: the block including the cell, and the deposit block
+// (block number in cell data) if it is a withdrawing cell. Empty if it is not a DAO cell.
+fn get_dao_header_deps(
+    rpc_client: &mut HttpRpcClient,
+    out_point: &OutPoint,
+) -> Result<Vec<packed::Byte32>, String> {
+    let (output, data) = get_live_cell(rpc_client, out_point.clone(), true)?;
+    let is_dao = output
+        .type_()
+        .to_opt()
+        .map(|script| script.code_hash() == DAO_TYPE_HASH.pack())
+        .unwrap_or(false);
+    if !is_dao {
+        return Ok(Vec::new());
+    }
+    let block_hash = rpc_client
+        .get_transaction(out_point.tx_hash().unpack())?
+        .and_then(|tx| tx.tx_status.block_hash)
+        .ok_or_else(|| "The transaction of DAO cell is not committed".to_string())?;
+    let mut header_deps = vec![block_hash.pack()];
+    if data.len() == 8 {
+        let deposit_number = LittleEndian::read_u64(&data[0..8]);
+        if deposit_number > 0 {
+            let deposit_header = rpc_client
+                .get_header_by_number(deposit_number)?
+                .ok_or_else(|| format!("Deposit block not found: #{}", deposit_number))?;
+            header_deps.push(deposit_header.hash.pack());
+        }
+    }
+    Ok(header_deps)
+}
+
 fn modify_tx_file<T, F: FnOnce(&mut TxHelper) -> Result<T, String>>(
     path: &PathBuf,
     network: NetworkType,
@@ -645,14 +798,14 @@ fn modify_tx_file<T, F: FnOnce(&mut TxHelper) -> Result<T, String>>(
 
 #[derive(Clone, Default, Serialize, Deserialize, PartialEq, Eq, Debug)]
 #[serde(deny_unknown_fields)]
-struct ReprTxHelper {
+pub(crate) struct ReprTxHelper {
     transaction: json_types::Transaction,
     multisig_configs: HashMap<H160, ReprMultisigConfig>,
     signatures: HashMap<JsonBytes, Vec<JsonBytes>>,
 }
 
 impl ReprTxHelper {
-    fn new(tx: TxHelper, network: NetworkType) -> Self {
+    pub(crate) fn new(tx: TxHelper, network: NetworkType) -> Self {
         ReprTxHelper {
             transaction: tx.transaction().data().into(),
             multisig_configs: tx
@@ -5,11 +5,11 @@ use ckb_sdk::{
     constants::{MULTISIG_TYPE_HASH, SIGHASH_TYPE_HASH},
     rpc::ChainInfo,
     wallet::KeyStore,
-    Address, AddressPayload, CodeHashIndex, HttpRpcClient, NetworkType, OldAddress,
+    Address, AddressPayload, CodeHashIndex, HttpRpcClient, HumanCapacity, NetworkType, OldAddress,
 };
 use ckb_types::{
-    bytes::BytesMut,
-    core::{EpochNumberWithFraction, ScriptHashType},
+    bytes::{Bytes, BytesMut},
+    core::{Capacity, EpochNumberWithFraction, ScriptHashType},
     packed,
     prelude::*,
     utilities::{compact_to_difficulty, difficulty_to_compact},
@@ -27,7 +27,9 @@ use crate::utils::{
         AddressParser, AddressPayloadOption, ArgParser, FixedHashParser, FromStrParser, HexParser,
         PrivkeyPathParser, PrivkeyWrapper, PubkeyHexParser,
     },
-    other::{get_address, read_password, serialize_signature},
+    other::{
+        get_address, get_default_account, read_password, read_password_file, serialize_signature,
+    },
     printer::{OutputFormat, Printable},
 };
 
@@ -107,14 +109,15 @@ impl<'a> UtilSubCommand<'a> {
                     .arg(arg_pubkey.clone().required(false))
                     .arg(arg_address.clone().required(false))
                     .arg(arg::lock_arg().clone()),
+                SubCommand::with_name("lock-arg")
+                    .about("Print the sighash lock arg (blake160 of public key) and address of a private key (from file) or public key")
+                    .arg(arg_privkey.clone().conflicts_with("pubkey"))
+                    .arg(arg_pubkey.clone().required_unless("privkey-path")),
                 SubCommand::with_name("sign-data")
                     .about("Sign data with secp256k1 signature ")
-                    .arg(arg::privkey_path().required_unless(arg::from_account().b.name))
-                    .arg(
-                        arg::from_account()
-                            .required_unless(arg::privkey_path().b.name)
-                            .conflicts_with(arg::privkey_path().b.name),
-                    )
+                    .arg(arg::privkey_path())
+                    .arg(arg::from_account().conflicts_with(arg::privkey_path().b.name))
+                    .arg(arg::password_file().conflicts_with(arg::privkey_path().b.name))
                     .arg(arg_recoverable.clone())
                     .arg(
                         binary_hex_arg
@@ -123,12 +126,9 @@ impl<'a> UtilSubCommand<'a> {
                     ),
                 SubCommand::with_name("sign-message")
                     .about("Sign message with secp256k1 signature")
-                    .arg(arg::privkey_path().required_unless(arg::from_account().b.name))
-                    .arg(
-                        arg::from_account()
-                            .required_unless(arg::privkey_path().b.name)
-                            .conflicts_with(arg::privkey_path().b.name),
-                    )
+                    .arg(arg::privkey_path())
+                    .arg(arg::from_account().conflicts_with(arg::privkey_path().b.name))
+                    .arg(arg::password_file().conflicts_with(arg::privkey_path().b.name))
                     .arg(arg_recoverable.clone())
                     .arg(arg_message.clone().help("The message to be signed (32 bytes)")),
                 SubCommand::with_name("verify-signature")
@@ -159,6 +159,49 @@ impl<'a> UtilSubCommand<'a> {
                             .long("prefix-160")
                             .help("Only show prefix 160 bits (Example: calculate lock_arg from pubkey)")
                     ),
+                SubCommand::with_name("min-capacity")
+                    .alias("capacity-calc")
+                    .about("Calculate the minimum capacity (occupied capacity) of a cell")
+                    .arg(
+                        Arg::with_name("lock-args")
+                            .long("lock-args")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| HexParser.validate(input))
+                            .help("Lock script args (code hash and hash type are always counted as 33 bytes)")
+                    )
+                    .arg(
+                        Arg::with_name("type-args")
+                            .long("type-args")
+                            .takes_value(true)
+                            .validator(|input| HexParser.validate(input))
+                            .help("Type script args, the cell has no type script if absent")
+                    )
+                    .arg(
+                        Arg::with_name("data-len")
+                            .long("data-len")
+                            .takes_value(true)
+                            .validator(|input| FromStrParser::<usize>::default().validate(input))
+                            .help("Cell data length in bytes")
+                    )
+                    .arg(
+                        Arg::with_name("data")
+                            .long("data")
+                            .takes_value(true)
+                            .conflicts_with("data-len")
+                            .validator(|input| HexParser.validate(input))
+                            .help("Cell data (hex), only the length is used")
+                    ),
+                SubCommand::with_name("parse-witness")
+                    .about("Decode a witness as molecule WitnessArgs (lock, input_type, output_type) and detect the signature in lock")
+                    .arg(
+                        Arg::with_name("witness")
+                            .long("witness")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| HexParser.validate(input))
+                            .help("The witness (hex)")
+                    ),
                 SubCommand::with_name("compact-to-difficulty")
                     .about("Convert compact target value to difficulty value")
                     .arg(Arg::with_name("compact-target")
@@ -284,6 +327,24 @@ message = "0x"
                 });
                 Ok(resp.render(format, color))
             }
+            ("lock-arg", Some(m)) => {
+                let privkey_opt: Option<PrivkeyWrapper> =
+                    PrivkeyPathParser.from_matches_opt(m, "privkey-path", false)?;
+                let pubkey = match privkey_opt {
+                    Some(privkey) => secp256k1::PublicKey::from_secret_key(&SECP256K1, &privkey),
+                    None => PubkeyHexParser.from_matches(m, "pubkey")?,
+                };
+                let address_payload = AddressPayload::from_pubkey(&pubkey);
+                let lock_arg = H160::from_slice(address_payload.args().as_ref()).unwrap();
+                let resp = serde_json::json!({
+                    "lock_arg": format!("{:#x}", lock_arg),
+                    "address": {
+                        "mainnet": Address::new(NetworkType::Mainnet, address_payload.clone()).to_string(),
+                        "testnet": Address::new(NetworkType::Testnet, address_payload).to_string(),
+                    },
+                });
+                Ok(resp.render(format, color))
+            }
             ("sign-data", Some(m)) => {
                 let binary: Vec<u8> = HexParser.from_matches(m, "binary-hex")?;
                 let recoverable = m.is_present("recoverable");
@@ -302,6 +363,7 @@ message = "0x"
                             })
                             .map_err(|_| err)
                     })?;
+                let from_account_opt = from_account_opt.or(get_default_account(m)?);
 
                 let message = H256::from(blake2b_256(&binary));
                 let key_store_opt = from_account_opt
@@ -310,6 +372,7 @@ message = "0x"
                 let signature = sign_message(
                     from_privkey_opt.as_ref(),
                     key_store_opt,
+                    m.value_of("password-file"),
                     recoverable,
                     &message,
                 )?;
@@ -339,6 +402,7 @@ message = "0x"
                             })
                             .map_err(|_| err)
                     })?;
+                let from_account_opt = from_account_opt.or(get_default_account(m)?);
 
                 let key_store_opt = from_account_opt
                     .as_ref()
@@ -346,6 +410,7 @@ message = "0x"
                 let signature = sign_message(
                     from_privkey_opt.as_ref(),
                     key_store_opt,
+                    m.value_of("password-file"),
                     recoverable,
                     &message,
                 )?;
@@ -431,6 +496,72 @@ message = "0x"
                 };
                 Ok(format!("0x{}", hex_string(slice).unwrap()))
             }
+            ("min-capacity", Some(m)) => {
+                let lock_args: Vec<u8> = HexParser.from_matches(m, "lock-args")?;
+                let type_args_opt: Option<Vec<u8>> =
+                    HexParser.from_matches_opt(m, "type-args", false)?;
+                let data_len = if let Some(data_len) =
+                    FromStrParser::<usize>::default().from_matches_opt(m, "data-len", false)?
+                {
+                    data_len
+                } else {
+                    let data: Option<Vec<u8>> = HexParser.from_matches_opt(m, "data", false)?;
+                    data.map(|data| data.len()).unwrap_or(0)
+                };
+                let lock = packed::Script::new_builder()
+                    .code_hash(SIGHASH_TYPE_HASH.pack())
+                    .hash_type(ScriptHashType::Type.into())
+                    .args(Bytes::from(lock_args).pack())
+                    .build();
+                let type_opt = type_args_opt.map(|type_args| {
+                    packed::Script::new_builder()
+                        .hash_type(ScriptHashType::Type.into())
+                        .args(Bytes::from(type_args).pack())
+                        .build()
+                });
+                let data_capacity = Capacity::bytes(data_len).map_err(|err| err.to_string())?;
+                let output = packed::CellOutput::new_builder()
+                    .lock(lock)
+                    .type_(type_opt.pack())
+                    .build_exact_capacity(data_capacity)
+                    .map_err(|err| err.to_string())?;
+                let capacity: u64 = output.capacity().unpack();
+                let resp = serde_json::json!({
+                    "shannons": capacity,
+                    "ckb": format!("{:#}", HumanCapacity::from(capacity)),
+                });
+                Ok(resp.render(format, color))
+            }
+            ("parse-witness", Some(m)) => {
+                let witness: Vec<u8> = HexParser.from_matches(m, "witness")?;
+                let witness_args = packed::WitnessArgs::from_slice(&witness)
+                    .map_err(|err| format!("Invalid WitnessArgs: {}", err))?;
+                let field_value = |field: Option<Bytes>| {
+                    field.map(|bytes| {
+                        serde_json::json!({
+                            "length": bytes.len(),
+                            "hex": format!("0x{}", hex_string(&bytes).unwrap()),
+                        })
+                    })
+                };
+                let lock: Option<Bytes> = witness_args.lock().to_opt().map(|bytes| bytes.unpack());
+                let lock_kind = lock.as_ref().map(|lock| witness_lock_kind(&lock[..]));
+                let input_type = witness_args
+                    .input_type()
+                    .to_opt()
+                    .map(|bytes| bytes.unpack());
+                let output_type = witness_args
+                    .output_type()
+                    .to_opt()
+                    .map(|bytes| bytes.unpack());
+                let resp = serde_json::json!({
+                    "lock": field_value(lock),
+                    "lock_kind": lock_kind,
+                    "input_type": field_value(input_type),
+                    "output_type": field_value(output_type),
+                });
+                Ok(resp.render(format, color))
+            }
             ("compact-to-difficulty", Some(m)) => {
                 let compact_target: u32 = FromStrParser::<u32>::default()
                     .from_matches(m, "compact-target")
@@ -538,9 +669,17 @@ message = "0x"
 fn sign_message(
     from_privkey_opt: Option<&PrivkeyWrapper>,
     from_account_opt: Option<(&KeyStore, &H160)>,
+    password_file: Option<&str>,
     recoverable: bool,
     message: &H256,
 ) -> Result<Vec<u8>, String> {
+    let get_password = || {
+        if let Some(path) = password_file {
+            read_password_file(path)
+        } else {
+            read_password(false, None)
+        }
+    };
     match (from_privkey_opt, from_account_opt, recoverable) {
         (Some(privkey), _, false) => {
             let message = secp256k1::Message::from_slice(message.as_bytes()).unwrap();
@@ -554,14 +693,14 @@ fn sign_message(
             Ok(serialize_signature(&SECP256K1.sign_recoverable(&message, privkey)).to_vec())
         }
         (None, Some((key_store, account)), false) => {
-            let password = read_password(false, None)?;
+            let password = get_password()?;
             key_store
                 .sign_with_password(account, &[], message, password.as_bytes())
                 .map(|sig| sig.serialize_compact().to_vec())
                 .map_err(|err| err.to_string())
         }
         (None, Some((key_store, account)), true) => {
-            let password = read_password(false, None)?;
+            let password = get_password()?;
             key_store
                 .sign_recoverable_with_password(account, &[], message, password.as_bytes())
                 .map(|sig| serialize_signature(&sig).to_vec())
@@ -609,6 +748,32 @@ fn to_timestamp(input: &str) -> Result<u64, String> {
     Ok(date.timestamp_millis() as u64)
 }
 
+// Guess the content of the lock field of WitnessArgs by its layout
+fn witness_lock_kind(lock: &[u8]) -> &'static str {
+    let is_zero = |bytes: &[u8]| bytes.iter().all(|byte| *byte == 0);
+    if lock.len() == 65 {
+        if is_zero(lock) {
+            "sighash signature placeholder"
+        } else {
+            "sighash signature"
+        }
+    } else if lock.len() > 4
+        && lock[0] == 0
+        && lock.len() == 4 + 20 * lock[3] as usize + 65 * lock[2] as usize
+    {
+        // multisig script (reserved, require_first_n, threshold, pubkeys) + signatures
+        if is_zero(&lock[4 + 20 * lock[3] as usize..]) {
+            "multisig signature placeholder"
+        } else {
+            "multisig signature"
+        }
+    } else if lock.is_empty() {
+        "empty"
+    } else {
+        "unknown"
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -627,4 +792,23 @@ mod test {
         );
         assert_eq!(epoch, EpochNumberWithFraction::new(3, 300, EPOCH_LENGTH))
     }
+
+    #[test]
+    fn test_witness_lock_kind() {
+        assert_eq!(witness_lock_kind(&[]), "empty");
+        assert_eq!(
+            witness_lock_kind(&[0u8; 65]),
+            "sighash signature placeholder"
+        );
+        assert_eq!(witness_lock_kind(&[1u8; 65]), "sighash signature");
+
+        // 2-of-3 multisig
+        let mut lock = vec![0u8, 0, 2, 3];
+        lock.extend(vec![1u8; 20 * 3]);
+        lock.extend(vec![0u8; 65 * 2]);
+        assert_eq!(witness_lock_kind(&lock), "multisig signature placeholder");
+        lock[4 + 60] = 1;
+        assert_eq!(witness_lock_kind(&lock), "multisig signature");
+        assert_eq!(witness_lock_kind(&lock[..100]), "unknown");
+    }
 }
@@ -15,7 +15,9 @@ use ckb_types::{
 use ckb_util::RwLock;
 use crossbeam_channel::Receiver;
 
-use crate::utils::index::{IndexController, IndexRequest, IndexResponse, IndexThreadState};
+use crate::utils::index::{
+    IndexController, IndexRequest, IndexResponse, IndexThreadState, SyncProgress,
+};
 use crate::utils::other::get_network_type;
 
 pub fn start_index_thread(
@@ -25,6 +27,8 @@ pub fn start_index_thread(
 ) -> IndexController {
     let (sender, receiver) = crossbeam_channel::bounded::<Request<IndexRequest, IndexResponse>>(1);
     let shutdown = Arc::new(AtomicBool::new(false));
+    let progress = Arc::new(RwLock::new(SyncProgress::default()));
+    let progress_clone = Arc::clone(&progress);
     let state_clone = Arc::clone(&state);
     let shutdown_clone = Arc::clone(&shutdown);
     let mut rpc_client = HttpRpcClient::new(url.to_owned());
@@ -51,6 +55,7 @@ pub fn start_index_thread(
                     &mut rpc_client,
                     &index_dir,
                     &state,
+                    &progress_clone,
                     &shutdown_clone,
                 ) {
                     Ok(true) => {
@@ -72,7 +77,7 @@ pub fn start_index_thread(
         })
         .expect("Spawn index thread failed");
 
-    IndexController::new(state_clone, sender, shutdown)
+    IndexController::new(state_clone, progress, sender, shutdown)
 }
 
 fn process(
@@ -80,6 +85,7 @@ fn process(
     rpc_client: &mut HttpRpcClient,
     index_dir: &PathBuf,
     state: &Arc<RwLock<IndexThreadState>>,
+    progress: &Arc<RwLock<SyncProgress>>,
     shutdown: &Arc<AtomicBool>,
 ) -> Result<bool, String> {
     if let Some(exit) = try_recv(&receiver, rpc_client) {
@@ -116,10 +122,13 @@ fn process(
                 }
                 db.update_tip(tip_header.clone());
                 while tip_header.number() > db.last_number().unwrap() {
+                    // Apply the buffered blocks before exit, so next time can resume from here
                     if shutdown.load(Ordering::Relaxed) {
+                        db.flush();
                         return Ok(Some(true));
                     }
                     if let Some(exit) = try_recv(&receiver, rpc_client) {
+                        db.flush();
                         return Ok(Some(exit));
                     }
                     if let Some(next_block) =
@@ -130,6 +139,9 @@ fn process(
                         state
                             .write()
                             .processing(db.last_header().cloned(), tip_header.number());
+                        progress
+                            .write()
+                            .update(db.last_number().unwrap(), tip_header.number());
                     } else {
                         log::warn!("fork happening, wait a second");
                         thread::sleep(Duration::from_secs(1));
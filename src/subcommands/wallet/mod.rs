@@ -1,47 +1,128 @@
 mod index;
 
-use std::collections::{HashMap, HashSet};
-use std::path::PathBuf;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::convert::TryFrom;
+use std::fs;
+use std::io::{self, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
+use chrono::NaiveDateTime;
+use ckb_hash::blake2b_256;
+use ckb_jsonrpc_types::{self as json_types, JsonBytes};
 use ckb_types::{
     bytes::Bytes,
-    core::{BlockView, Capacity, ScriptHashType, TransactionView},
+    core::{
+        BlockView, Capacity, DepType, EpochNumberWithFraction, ScriptHashType, TransactionView,
+    },
     h256,
-    packed::{Byte32, CellOutput, OutPoint, Script},
+    packed::{Byte32, CellDep, CellOutput, OutPoint, OutPointVec, Script, WitnessArgs},
     prelude::*,
     H160, H256,
 };
 use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use faster_hex::hex_string;
+use rand::{rngs::StdRng, FromEntropy, Rng, SeedableRng};
 use serde::{Deserialize, Serialize};
 
+use super::dao::util::calculate_dao_redeemable;
+use super::mock_tx::Loader;
+use super::tx::{parse_json_tx, ReprTxHelper};
 use super::CliSubCommand;
 use crate::utils::{
     arg,
     arg_parser::{
-        AddressParser, ArgParser, CapacityParser, FixedHashParser, FromStrParser,
-        PrivkeyPathParser, PrivkeyWrapper,
+        AccountParser, AddressParser, ArgParser, CapacityParser, CapacityUnit,
+        DerivationPathParser, FilePathParser, FixedHashParser, FromStrParser, HexParser, JsonPath,
+        JsonPathParser, OutPointParser, PrivkeyPathParser, PrivkeyWrapper, UrlParser,
     },
     index::IndexController,
     other::{
-        check_capacity, get_address, get_arg_value, get_live_cell_with_cache,
-        get_max_mature_number, get_network_type, get_privkey_signer, get_to_data, is_mature,
-        read_password, serialize_signature,
+        check_capacity, compress_data, decompress_data, fetch_ckb_usd_price, get_address,
+        get_arg_value, get_default_account, get_live_cell, get_live_cell_with_cache,
+        get_max_mature_number, get_network_type, get_password, get_privkey_signer, get_to_data,
+        get_tx_history_file, is_cellbase, is_mature, read_data_length_limit, read_mnemonic_privkey,
+        read_password, read_password_file, serialize_signature, wait_index_synced, Password,
+        DEFAULT_DATA_LENGTH_LIMIT,
     },
     printer::{OutputFormat, Printable},
+    trace::{Trace, REDACTED},
 };
-use ckb_index::{with_index_db, IndexDatabase, LiveCellInfo};
+use ckb_index::{with_index_db, IndexDatabase, IndexKey, LiveCellInfo};
 use ckb_sdk::{
     constants::{
-        DAO_TYPE_HASH, MIN_SECP_CELL_CAPACITY, MULTISIG_TYPE_HASH, ONE_CKB, SIGHASH_TYPE_HASH,
+        DAO_TYPE_HASH, MIN_SECP_CELL_CAPACITY, MULTISIG_TYPE_HASH, ONE_CKB, SECP_SIGNATURE_SIZE,
+        SIGHASH_TYPE_HASH, TYPE_ID_CODE_HASH,
     },
-    wallet::{DerivationPath, KeyStore},
-    Address, AddressPayload, GenesisInfo, HttpRpcClient, HumanCapacity, MultisigConfig, SignerFn,
-    Since, SinceType, TxHelper, SECP256K1,
+    wallet::{DerivationPath, KeyStore, MasterPrivKey},
+    Address, AddressPayload, GenesisInfo, HashFn, HttpRpcClient, HumanCapacity, MockInfo,
+    MockTransaction, MockTransactionHelper, MultisigConfig, SignerFn, Since, SinceType, TxHelper,
+    SECP256K1,
 };
 pub use index::start_index_thread;
 
 // Max derived change address to search
 const DERIVE_CHANGE_ADDRESS_MAX_LEN: u32 = 10000;
+// Max distance from tip to the anchor block (added as header dep)
+const MAX_ANCHOR_BLOCK_DISTANCE: u64 = 200;
+// Public CKB/USD price api of --denominate usd
+const DEFAULT_PRICE_ORACLE_URL: &str =
+    "https://api.coingecko.com/api/v3/simple/price?ids=nervos-network&vs_currencies=usd";
+// Default minimal fee rate of ckb node (shannons/KB)
+const MIN_FEE_RATE: u64 = 1000;
+// Max rounds of the --fee-rate estimate loop
+const FEE_RATE_MAX_ITERATIONS: usize = 8;
+// Derivation path of --from-mnemonic-phrase when --mnemonic-path is absent
+const MNEMONIC_DEFAULT_PATH: &str = "m/44'/309'/0'/0/0";
+// Prefix of the metadata witness (by transfer --tx-comment)
+const TX_COMMENT_MAGIC: &[u8; 4] = b"CMNT";
+// Max serialized size of the transfer --tx-comment json
+const TX_COMMENT_MAX_SIZE: usize = 256;
+// Max seconds to wait for the consolidation transaction committed and indexed
+const CONSOLIDATE_TIMEOUT_SECS: u64 = 600;
+// Number of exported cells between two checkpoints (by --checkpoint-resume)
+const EXPORT_CHECKPOINT_INTERVAL: u64 = 10000;
+// Arguments of get-live-cells, a new one must be added here to be part of the cache key
+const GET_LIVE_CELLS_ARGS: &[&str] = &[
+    "lock-hash",
+    "type-hash",
+    "code-hash",
+    "address",
+    "by-created-in-tx",
+    "limit",
+    "from",
+    "to",
+    "fast-mode",
+    "output",
+    "cellbase-only",
+    "no-cellbase",
+    "sampling-rate",
+    "sampling-seed",
+    "capacity-stats",
+    "histogram-buckets",
+    "output-summary-only",
+    "capacity-sum-only",
+    "dust-threshold",
+    "include-consumed",
+    "out-points-only",
+    "with-tx-meta",
+    "input-data-decompression",
+    "show-since",
+    "with-epoch",
+    "annotate-with-block-time",
+    "output-type-id-only",
+    "resolve-lock-script",
+    "follow-dep-group",
+    "resolve-dep-groups",
+    "deduplicate",
+    "export-json",
+    "checkpoint-resume",
+    "no-cache",
+];
 
 pub struct WalletSubCommand<'a> {
     rpc_client: &'a mut HttpRpcClient,
@@ -49,6 +130,7 @@ pub struct WalletSubCommand<'a> {
     genesis_info: Option<GenesisInfo>,
     index_dir: PathBuf,
     index_controller: IndexController,
+    live_cells_cache: Option<&'a mut LiveCellsCache>,
 }
 
 impl<'a> WalletSubCommand<'a> {
@@ -65,9 +147,16 @@ impl<'a> WalletSubCommand<'a> {
             genesis_info,
             index_dir,
             index_controller,
+            live_cells_cache: None,
         }
     }
 
+    // Reuse `get-live-cells` results across commands (interactive mode)
+    pub fn with_live_cells_cache(mut self, cache: &'a mut LiveCellsCache) -> WalletSubCommand<'a> {
+        self.live_cells_cache = Some(cache);
+        self
+    }
+
     fn genesis_info(&mut self) -> Result<GenesisInfo, String> {
         if self.genesis_info.is_none() {
             let genesis_block: BlockView = self
@@ -105,20 +194,432 @@ impl<'a> WalletSubCommand<'a> {
             .subcommands(vec![
                 SubCommand::with_name("transfer")
                     .about("Transfer capacity to an address (can have data)")
-                    .arg(arg::privkey_path().required_unless(arg::from_account().b.name))
+                    .arg(arg::privkey_path())
                     .arg(
                         arg::from_account()
-                            .required_unless(arg::privkey_path().b.name)
-                            .conflicts_with(arg::privkey_path().b.name),
+                            .conflicts_with(arg::privkey_path().b.name)
+                            .multiple(true)
+                            .number_of_values(1)
+                            .help("The account's lock-arg or sighash address, repeat to spend the cells of several accounts in one transaction (the first one is the from-address, a shared --password-file or one password prompt per account)"),
+                    )
+                    .arg(arg::password_file().conflicts_with(arg::privkey_path().b.name))
+                    .arg(
+                        Arg::with_name("from-mnemonic-phrase")
+                            .long("from-mnemonic-phrase")
+                            .conflicts_with_all(&["privkey-path", "from-account", "password-file"])
+                            .help("Read a BIP-39 mnemonic phrase from terminal and sign with the key of its first address (m/44'/309'/0'/0/0), the key is never saved"),
+                    )
+                    .arg(
+                        Arg::with_name("mnemonic-path")
+                            .long("mnemonic-path")
+                            .takes_value(true)
+                            .requires("from-mnemonic-phrase")
+                            .validator(|input| DerivationPathParser.validate(input))
+                            .help("Derive the signing key from the mnemonic phrase with this BIP-32 path, hardened segments end with ' (default: m/44'/309'/0'/0/0)"),
                     )
                     .arg(arg::from_locked_address())
-                    .arg(arg::to_address().required(true))
-                    .arg(arg::to_data())
+                    .arg(
+                        arg::to_address()
+                            .required_unless("to-lock-file")
+                            .multiple(true)
+                            .number_of_values(1)
+                            .help("Target address, repeat with --capacity/--to-capacity (and --to-data) for more recipients in one transaction"),
+                    )
+                    .arg(
+                        Arg::with_name("to-lock-file")
+                            .long("to-lock-file")
+                            .takes_value(true)
+                            .conflicts_with(arg::to_address().b.name)
+                            .validator(|input| FilePathParser::new(true).validate(input))
+                            .help("Use the lock script (json format, same as RPC) in this file as the to-output's lock"),
+                    )
+                    .arg(arg::to_data().multiple(true).number_of_values(1))
                     .arg(arg::to_data_path())
-                    .arg(arg::capacity().required(true))
-                    .arg(arg::tx_fee().required(true))
+                    .arg(
+                        arg::capacity()
+                            .alias("to-capacity")
+                            .required(true)
+                            .multiple(true)
+                            .number_of_values(1)
+                            .help("The capacity (unit: CKB, format: 123.335) of each --to-address in order, alias: --to-capacity"),
+                    )
+                    .arg(arg::tx_fee().required_unless("fee-rate"))
+                    .arg(
+                        Arg::with_name("fee-rate")
+                            .long("fee-rate")
+                            .takes_value(true)
+                            .conflicts_with_all(&["tx-fee", "preview-change-address"])
+                            .validator(|input| FromStrParser::<u64>::default().validate(input))
+                            .help("Pay the fee by this rate (unit: shannons/KB, at least 1000) instead of --tx-fee, the fee is re-estimated until it covers the transaction size"),
+                    )
+                    .arg(
+                        Arg::with_name("capacity-unit")
+                            .long("capacity-unit")
+                            .takes_value(true)
+                            .possible_values(&["ckb", "shannon"])
+                            .default_value("ckb")
+                            .help("The unit of --capacity, --tx-fee, --input-capacity-min, --input-capacity-target, --gas-reserve and --burn-capacity (shannon: integer only)"),
+                    )
+                    .arg(
+                        Arg::with_name("change-threshold-action")
+                            .long("change-threshold-action")
+                            .takes_value(true)
+                            .possible_values(&["fee", "error", "bump-to-address"])
+                            .default_value("fee")
+                            .help("What to do when the change is below the minimum cell capacity (61 CKB): fee (pay as transaction fee), error (abort the transfer), bump-to-address (add to the output of the first to-address)"),
+                    )
+                    .arg(
+                        Arg::with_name("capacity-validation-mode")
+                            .long("capacity-validation-mode")
+                            .takes_value(true)
+                            .possible_values(&["strict", "permissive"])
+                            .default_value("strict")
+                            .help("How the output capacity is checked: strict (occupied capacity of all the cell fields, only secp256k1/multisig input locks), permissive (only the minimum secp256k1 cell capacity, for non-standard lock scripts)"),
+                    )
+                    .arg(
+                        Arg::with_name("priority-fee")
+                            .long("priority-fee")
+                            .takes_value(true)
+                            .validator(|input| FromStrParser::<u64>::default().validate(input))
+                            .help("Extra fee (unit: shannon) paid on top of --tx-fee, taken from the change (if the change is too small to hold a cell, it's all paid as fee)"),
+                    )
                     .arg(arg::derive_receiving_address_length())
-                    .arg(arg::derive_change_address().conflicts_with(arg::privkey_path().b.name)),
+                    .arg(arg::derive_change_address().conflicts_with(arg::privkey_path().b.name))
+                    .arg(
+                        Arg::with_name("change-to")
+                            .long("change-to")
+                            .takes_value(true)
+                            .conflicts_with("derive-change-address")
+                            .validator(|input| AddressParser::default().validate(input))
+                            .help("Send the change to this address instead of the from-address"),
+                    )
+                    .arg(
+                        Arg::with_name("simulate-vm-cycles")
+                            .long("simulate-vm-cycles")
+                            .help("Run the scripts of the built transaction locally and report the consumed cycles before sending"),
+                    )
+                    .arg(
+                        Arg::with_name("auto-consolidate-threshold")
+                            .long("auto-consolidate-threshold")
+                            .takes_value(true)
+                            .validator(|input| FromStrParser::<usize>::default().validate(input))
+                            .conflicts_with_all(&["fee-rate", "fee-estimate-only", "no-broadcast", "preview-change-address", "input-from-query", "input-capacity-target", "fee-input", "from-locked-address"])
+                            .help("If the transfer needs more than this many inputs, first send a transaction consolidating them into one cell to the from-address (wait until committed), then spend that cell (default: 0, disabled)"),
+                    )
+                    .arg(
+                        Arg::with_name("yes")
+                            .long("yes")
+                            .help("Do not ask for confirmation of the consolidation (by --auto-consolidate-threshold)"),
+                    )
+                    .arg(
+                        Arg::with_name("simulate-since-validation")
+                            .long("simulate-since-validation")
+                            .help("Check the since of each input (eg: from --from-locked-address) against the tip epoch, block number and median time, abort before sending if any is not satisfied"),
+                    )
+                    .arg(
+                        Arg::with_name("change-data")
+                            .long("change-data")
+                            .takes_value(true)
+                            .validator(|input| HexParser.validate(input))
+                            .help("Hex data store in change cell (change cell is always created)"),
+                    )
+                    .arg(
+                        Arg::with_name("change-type-code-hash")
+                            .long("change-type-code-hash")
+                            .takes_value(true)
+                            .validator(|input| FixedHashParser::<H256>::default().validate(input))
+                            .help("The code hash of change cell's type script (change cell is always created)"),
+                    )
+                    .arg(
+                        Arg::with_name("change-type-hash-type")
+                            .long("change-type-hash-type")
+                            .takes_value(true)
+                            .requires("change-type-code-hash")
+                            .possible_values(&["data", "type"])
+                            .default_value("type")
+                            .help("The hash type of change cell's type script"),
+                    )
+                    .arg(
+                        Arg::with_name("change-type-args")
+                            .long("change-type-args")
+                            .takes_value(true)
+                            .requires("change-type-code-hash")
+                            .validator(|input| HexParser.validate(input))
+                            .help("The args of change cell's type script (hex string)"),
+                    )
+                    .arg(
+                        Arg::with_name("parallel-sign")
+                            .long("parallel-sign")
+                            .help("Sign input groups concurrently (keystore key is decrypted only once)"),
+                    )
+                    .arg(
+                        Arg::with_name("block-hash")
+                            .long("block-hash")
+                            .takes_value(true)
+                            .validator(|input| FixedHashParser::<H256>::default().validate(input))
+                            .help("Add this block's header to header deps as an anchor (must be within the last 200 blocks)"),
+                    )
+                    .arg(
+                        Arg::with_name("anchor-to-tip")
+                            .long("anchor-to-tip")
+                            .conflicts_with("block-hash")
+                            .help("Add current tip block's header to header deps as an anchor"),
+                    )
+                    .arg(
+                        Arg::with_name("require-cell-data-empty")
+                            .long("require-cell-data-empty")
+                            .help("Only use cells with empty data as inputs (default)"),
+                    )
+                    .arg(
+                        Arg::with_name("allow-cell-data")
+                            .long("allow-cell-data")
+                            .conflicts_with("require-cell-data-empty")
+                            .help("Also use cells with data as inputs (WARNING: the data will be destroyed)"),
+                    )
+                    .arg(
+                        Arg::with_name("dump-sighash")
+                            .long("dump-sighash")
+                            .help("Output the message (sighash) to be signed of each input group, for verifying external signers"),
+                    )
+                    .arg(
+                        Arg::with_name("preserve-cell-order")
+                            .long("preserve-cell-order")
+                            .help("Keep the inputs in the order of the index database (lock hash, then creation order). This is the default behavior, inputs are never re-sorted"),
+                    )
+                    .arg(
+                        Arg::with_name("input-capacity-min")
+                            .long("input-capacity-min")
+                            .takes_value(true)
+                            .validator(|input| CapacityParser.validate(input))
+                            .help("Skip input cells with capacity below this value (unit: CKB)"),
+                    )
+                    .arg(
+                        Arg::with_name("input-from-query")
+                            .long("input-from-query")
+                            .takes_value(true)
+                            .help("Only select input cells matching this search key json (same as `get_cells` of ckb-indexer: script, script_type (lock/type), script_search_mode (exact/prefix), filter.block_range and filter.output_capacity_range). Cells must still be owned by the sender, cells with type script can be selected by a type query"),
+                    )
+                    .arg(
+                        Arg::with_name("dao-aware-selection")
+                            .long("dao-aware-selection")
+                            .help("DAO cells are never spent by transfer, if capacity is not enough, also report the redeemable capacity (with compensation) of the DAO cells"),
+                    )
+                    .arg(
+                        Arg::with_name("recipient-index")
+                            .long("recipient-index")
+                            .takes_value(true)
+                            .validator(|input| FromStrParser::<usize>::default().validate(input))
+                            .help("Output index of the to-output (the first one by --split-output), the burn and change outputs keep their order around it (default: 0)"),
+                    )
+                    .arg(
+                        Arg::with_name("set-data-length-limit")
+                            .long("set-data-length-limit")
+                            .takes_value(true)
+                            .validator(|input| FromStrParser::<usize>::default().validate(input))
+                            .help("Max length (bytes) of --to-data/--to-data-path, default is `data_length_limit` in config file or 512KB"),
+                    )
+                    .arg(
+                        Arg::with_name("output-data-compression")
+                            .long("output-data-compression")
+                            .takes_value(true)
+                            .possible_values(&["gzip", "lz4"])
+                            .help("Compress --to-data/--to-data-path before storing it in the to-output (the capacity is calculated by the compressed size). The consuming script must decompress it, only for protocol developers control both sides"),
+                    )
+                    .arg(
+                        Arg::with_name("split-output")
+                            .long("split-output")
+                            .takes_value(true)
+                            .validator(|input| FromStrParser::<u32>::default().validate(input))
+                            .conflicts_with_all(&["to-data", "to-data-path"])
+                            .help("Deliver the capacity of each recipient as this many equal cells (the last one absorbs the rounding)"),
+                    )
+                    .arg(
+                        Arg::with_name("input-capacity-target")
+                            .long("input-capacity-target")
+                            .takes_value(true)
+                            .validator(|input| CapacityParser.validate(input))
+                            .conflicts_with("fee-input")
+                            .help("Select inputs totaling as close to (never above) this capacity as possible, instead of stopping at the first enough one (unit: CKB)"),
+                    )
+                    .arg(
+                        Arg::with_name("tx-comment")
+                            .long("tx-comment")
+                            .takes_value(true)
+                            .help("A json object (max 256 bytes serialized) appended as an extra witness (after the input witnesses, prefixed by 4 bytes magic `CMNT`), see `wallet decode-tx-comment`"),
+                    )
+                    .arg(
+                        Arg::with_name("input-size-hint")
+                            .long("input-size-hint")
+                            .takes_value(true)
+                            .validator(|input| FromStrParser::<usize>::default().validate(input))
+                            .help("Witness size (bytes) of each input used by the fee estimation (--fee-estimate-only, --fee-rate) instead of a secp256k1 signature, only needed for lock scripts with variable witness size (eg: multisig)"),
+                    )
+                    .arg(
+                        Arg::with_name("require-lock-hash")
+                            .long("require-lock-hash")
+                            .takes_value(true)
+                            .validator(|input| FixedHashParser::<H256>::default().validate(input))
+                            .help("Abort before selecting inputs if the lock hash of the from-address is not this one"),
+                    )
+                    .arg(
+                        Arg::with_name("gas-reserve")
+                            .long("gas-reserve")
+                            .takes_value(true)
+                            .validator(|input| CapacityParser.validate(input))
+                            .help("Keep at least this capacity in the from-address after the transfer, inputs above the reserve are never selected (unit: CKB)"),
+                    )
+                    .arg(
+                        Arg::with_name("trace")
+                            .long("trace")
+                            .takes_value(true)
+                            .validator(|input| FilePathParser::new(false).validate(input))
+                            .help("Write an execution trace (RPC calls, index queries, key derivation, witness construction) to this JSON file for debugging, passwords and private keys are redacted"),
+                    )
+                    .arg(
+                        Arg::with_name("fee-input")
+                            .long("fee-input")
+                            .takes_value(true)
+                            .validator(|input| OutPointParser.validate(input))
+                            .help("Pay the transaction fee by this cell ({tx-hash}-{index}, owned by the sender, no type script and data), other inputs only cover the capacity, the rest of the fee cell goes to change"),
+                    )
+                    .arg(
+                        Arg::with_name("cell-dep-from-type-id")
+                            .long("cell-dep-from-type-id")
+                            .takes_value(true)
+                            .validator(|input| FixedHashParser::<H256>::default().validate(input))
+                            .help("Add the live cell with this Type ID type script hash as a code cell dep (resolved by local index, for upgradable scripts)"),
+                    )
+                    .arg(
+                        Arg::with_name("capacity-precheck")
+                            .long("capacity-precheck")
+                            .help("Sum up the capacity of the sender before collecting inputs, fail early with a breakdown (total, immature, DAO, dust, ...) if not enough"),
+                    )
+                    .arg(
+                        Arg::with_name("auto-adjust-capacity")
+                            .long("auto-adjust-capacity")
+                            .help("Round the capacity of each recipient up to the minimum capacity of its output if it is too low"),
+                    )
+                    .arg(
+                        Arg::with_name("fee-estimate-only")
+                            .long("fee-estimate-only")
+                            .help("Only select inputs and estimate the transaction size and fee, without signing or sending"),
+                    )
+                    .arg(
+                        Arg::with_name("no-broadcast")
+                            .long("no-broadcast")
+                            .conflicts_with("fee-estimate-only")
+                            .help("Build and sign the transaction but do not send it, output the signed transaction (json for `rpc send_transaction`, and molecule serialized hex)"),
+                    )
+                    .arg(
+                        Arg::with_name("protect-inputs")
+                            .long("protect-inputs")
+                            .conflicts_with_all(&["no-broadcast", "fee-estimate-only", "dry-run"])
+                            .help("Before sending, abort if any input is already spent by a transaction in the tx-pool of the node (requires `get_raw_tx_pool` RPC)"),
+                    )
+                    .arg(
+                        Arg::with_name("dry-run")
+                            .long("dry-run")
+                            .alias("skip-send")
+                            .conflicts_with_all(&["no-broadcast", "fee-estimate-only", "post-broadcast-hook", "auto-consolidate-threshold"])
+                            .help("Build and sign the transaction but do not send it, output the transaction view json (alias: --skip-send). The 1.0 CKB transaction fee limit is not checked"),
+                    )
+                    .arg(
+                        Arg::with_name("tx-file")
+                            .long("tx-file")
+                            .takes_value(true)
+                            .validator(|input| FilePathParser::new(false).validate(input))
+                            .conflicts_with_all(&["no-broadcast", "dry-run", "fee-estimate-only", "post-broadcast-hook", "auto-consolidate-threshold", "protect-inputs"])
+                            .help("Write the signed transaction (json, TransactionView) to this file instead of sending it. The input cells are still resolved by RPC: build online, send later (eg: by `tx convert` and `rpc send_transaction`)"),
+                    )
+                    .arg(
+                        Arg::with_name("verify-against-rpc")
+                            .long("verify-against-rpc")
+                            .help("Check every selected input cell is still live by `get_live_cell` RPC, consumed cells (the index database is behind) are replaced by other cells"),
+                    )
+                    .arg(
+                        Arg::with_name("output-file")
+                            .long("output-file")
+                            .takes_value(true)
+                            .requires("no-broadcast")
+                            .validator(|input| FilePathParser::new(false).validate(input))
+                            .help("Write the molecule serialized hex of the signed transaction to this file (by --no-broadcast)"),
+                    )
+                    .arg(
+                        Arg::with_name("sign-hash-type")
+                            .long("sign-hash-type")
+                            .takes_value(true)
+                            .possible_values(&["blake2b_256", "sha3_256"])
+                            .default_value("blake2b_256")
+                            .help("The hash algorithm for the signing message (default CKB lock only accepts blake2b_256, sha3_256 is for testing custom lock scripts)"),
+                    )
+                    .arg(
+                        Arg::with_name("verbose-witness")
+                            .long("verbose-witness")
+                            .help("Also print the lock arg and signature of each input's witness (with --debug)"),
+                    )
+                    .arg(
+                        Arg::with_name("add-burn-output")
+                            .long("add-burn-output")
+                            .requires("burn-capacity")
+                            .help("Add an output with unspendable lock script (WARNING: the capacity will be destroyed permanently)"),
+                    )
+                    .arg(
+                        Arg::with_name("burn-capacity")
+                            .long("burn-capacity")
+                            .takes_value(true)
+                            .requires("add-burn-output")
+                            .validator(|input| CapacityParser.validate(input))
+                            .help("The capacity of the burn output (unit: CKB)"),
+                    )
+                    .arg(
+                        Arg::with_name("memo")
+                            .long("memo")
+                            .takes_value(true)
+                            .help("A local note of this transfer (saved in tx-history, never on chain)"),
+                    )
+                    .arg(
+                        Arg::with_name("preview-change-address")
+                            .long("preview-change-address")
+                            .help("Only resolve and print the address the change output goes to (checked against the derived addresses by --derive-change-address), no transaction is built"),
+                    )
+                    .arg(
+                        Arg::with_name("post-broadcast-hook")
+                            .long("post-broadcast-hook")
+                            .takes_value(true)
+                            .conflicts_with_all(&["no-broadcast", "fee-estimate-only"])
+                            .validator(|input| FilePathParser::new(true).validate(input))
+                            .help("Run this script after the transaction is sent, with environment variables CKB_TX_HASH, CKB_FROM_ADDRESS, CKB_TO_ADDRESS, CKB_CAPACITY and CKB_FEE (capacity and fee in shannons, comma separated addresses and capacities for multiple recipients). A non-zero exit code only prints a warning"),
+                    )
+                    .arg(
+                        Arg::with_name("fail-on-hook-error")
+                            .long("fail-on-hook-error")
+                            .requires("post-broadcast-hook")
+                            .help("Return an error if --post-broadcast-hook fails (the transaction is sent anyway)"),
+                    )
+                    .arg(
+                        Arg::with_name("idempotency-key")
+                            .long("idempotency-key")
+                            .takes_value(true)
+                            .help("Saved in tx-history with the sent transaction, rerun with the same key returns the recorded tx hash instead of sending a new transaction (for safely retryable scripts)"),
+                    )
+                    .arg(
+                        Arg::with_name("output-json-path")
+                            .long("output-json-path")
+                            .takes_value(true)
+                            .validator(|input| JsonPathParser.validate(input))
+                            .help("Only print the selected field of the output (simplified jq path: .field.subfield or .field[index], e.g. .hash with --debug)"),
+                    )
+                    .arg(
+                        Arg::with_name("note")
+                            .long("note")
+                            .takes_value(true)
+                            .help("A human-readable note saved in the local tx-history after the transaction is sent (never on chain)"),
+                    )
+                    .arg(
+                        Arg::with_name("track")
+                            .long("track")
+                            .help("Also save the selected input out points and the outputs in tx-history, so the same transaction can be rebuilt later (e.g. to bump fee)"),
+                    ),
                 SubCommand::with_name("get-capacity")
                     .about("Get capacity by lock script hash or address or lock arg or pubkey")
                     .arg(arg::lock_hash())
@@ -127,13 +628,109 @@ impl<'a> WalletSubCommand<'a> {
                     .arg(arg::lock_arg())
                     .arg(arg::derive_receiving_address_length())
                     .arg(arg::derive_change_address_length())
-                    .arg(arg::derived().conflicts_with(arg::lock_hash().b.name)),
+                    .arg(arg::derived().conflicts_with(arg::lock_hash().b.name))
+                    .arg(arg::password_file().requires(arg::derived().b.name))
+                    .arg(
+                        Arg::with_name("gap-limit")
+                            .long("gap-limit")
+                            .takes_value(true)
+                            .requires(arg::derived().b.name)
+                            .validator(|input| FromStrParser::<u32>::default().validate(input))
+                            .help("Scan derived addresses until this many consecutive addresses have no capacity (BIP-44 gap limit, e.g. 20), instead of the fixed --derive-*-address-length"),
+                    )
+                    .arg(
+                        Arg::with_name("with-cells")
+                            .long("with-cells")
+                            .help("Also list all the live cells (same index query as the capacity)"),
+                    )
+                    .arg(
+                        Arg::with_name("denominate")
+                            .long("denominate")
+                            .takes_value(true)
+                            .possible_values(&["ckb", "shannon", "usd"])
+                            .default_value("ckb")
+                            .help("The unit of all capacity fields (usd: fetch CKB/USD price from --price-oracle-url)"),
+                    )
+                    .arg(
+                        Arg::with_name("price-oracle-url")
+                            .long("price-oracle-url")
+                            .takes_value(true)
+                            .default_value(DEFAULT_PRICE_ORACLE_URL)
+                            .validator(|input| UrlParser.validate(input))
+                            .help("Http(s) url returns current CKB/USD price (a number, or json object with `price`/`usd`/`nervos-network.usd` field), used by --denominate usd"),
+                    )
+                    .arg(
+                        Arg::with_name("format")
+                            .long("format")
+                            .takes_value(true)
+                            .possible_values(&["yaml", "json", "prometheus"])
+                            .help("Output format of this command (prometheus: text exposition format with balance in shannons)"),
+                    )
+                    .arg(
+                        Arg::with_name("compare")
+                            .long("compare")
+                            .takes_value(true)
+                            .validator(|input| AddressParser::default().validate(input))
+                            .conflicts_with_all(&["with-cells", "format"])
+                            .help("Compare with the capacity of this address, output each field of both and the delta (compare - current)"),
+                    )
+                    .arg(
+                        Arg::with_name("dao-aware")
+                            .long("dao-aware")
+                            .conflicts_with("format")
+                            .help("Also output the redeemable capacity (deposit + compensation, if withdrawn now) of DAO cells and the total worth, requires RPC to fetch headers"),
+                    )
+                    .arg(
+                        Arg::with_name("compare-to-address")
+                            .long("compare-to-address")
+                            .takes_value(true)
+                            .validator(|input| AddressParser::default().validate(input))
+                            .conflicts_with_all(&["compare", "with-cells", "format"])
+                            .help("Output the total capacity of both addresses, the ratio (primary / secondary) and the difference (primary - secondary)"),
+                    )
+                    .arg(
+                        Arg::with_name("all-accounts")
+                            .long("all-accounts")
+                            .conflicts_with_all(&["lock-hash", "address", "pubkey", "lock-arg", "compare", "compare-to-address", "with-cells", "format", "dao-aware"])
+                            .help("Get capacity of every account in the keystore (with their derived addresses by --derived, a shared --password-file or one password prompt per account), and the grand total"),
+                    )
+                    .arg(
+                        Arg::with_name("wait-synced")
+                            .long("wait-synced")
+                            .help("Wait until the index is near the tip (by --max-lag) before the query"),
+                    )
+                    .arg(
+                        Arg::with_name("max-lag")
+                            .long("max-lag")
+                            .takes_value(true)
+                            .default_value("0")
+                            .requires("wait-synced")
+                            .validator(|input| FromStrParser::<u64>::default().validate(input))
+                            .help("Max number of blocks the index can be behind the tip (by --wait-synced)"),
+                    )
+                    .arg(
+                        Arg::with_name("wait-timeout")
+                            .long("wait-timeout")
+                            .takes_value(true)
+                            .default_value("60")
+                            .requires("wait-synced")
+                            .validator(|input| FromStrParser::<u64>::default().validate(input))
+                            .help("Seconds to wait (by --wait-synced), the query runs with a staleness warning after timeout"),
+                    ),
                 SubCommand::with_name("get-live-cells")
                     .about("Get live cells by lock/type/code  hash")
                     .arg(arg::lock_hash())
                     .arg(arg::type_hash())
                     .arg(arg::code_hash())
                     .arg(arg::address())
+                    .arg(
+                        Arg::with_name("by-created-in-tx")
+                            .long("by-created-in-tx")
+                            .takes_value(true)
+                            .validator(|input| FixedHashParser::<H256>::default().validate(input))
+                            .conflicts_with_all(&["lock-hash", "type-hash", "code-hash", "address"])
+                            .help("Get the live cells created by this transaction (any lock or type)"),
+                    )
                     .arg(arg::live_cells_limit())
                     .arg(arg::from_block_number())
                     .arg(arg::to_block_number())
@@ -141,8 +738,270 @@ impl<'a> WalletSubCommand<'a> {
                         Arg::with_name("fast-mode")
                             .long("fast-mode")
                             .help("Only visit current range (by --from and --to) of live cells"),
+                    )
+                    .arg(
+                        Arg::with_name("output")
+                            .long("output")
+                            .takes_value(true)
+                            .default_value("default")
+                            .possible_values(&["default", "ckb-indexer-compatible"])
+                            .help("Live cell output style (ckb-indexer-compatible: same cell fields as ckb-indexer's get_cells, requires RPC to fetch cell output and data)"),
+                    )
+                    .arg(
+                        Arg::with_name("cellbase-only")
+                            .long("cellbase-only")
+                            .conflicts_with("no-cellbase")
+                            .help("Only list cellbase cells (mining rewards, subject to maturity)"),
+                    )
+                    .arg(
+                        Arg::with_name("no-cellbase")
+                            .long("no-cellbase")
+                            .help("Exclude cellbase cells"),
+                    )
+                    .arg(
+                        Arg::with_name("sampling-rate")
+                            .long("sampling-rate")
+                            .takes_value(true)
+                            .validator(|input| {
+                                let rate = FromStrParser::<f64>::default().parse(&input)?;
+                                if rate > 0.0 && rate <= 1.0 {
+                                    Ok(())
+                                } else {
+                                    Err("sampling rate must be in range (0.0, 1.0]".to_string())
+                                }
+                            })
+                            .conflicts_with_all(&["by-created-in-tx", "fast-mode", "include-consumed", "out-points-only", "cellbase-only", "no-cellbase"])
+                            .help("Randomly pick this fraction of cells (each cell is picked independently), and estimate the total capacity with 95% confidence interval"),
+                    )
+                    .arg(
+                        Arg::with_name("sampling-seed")
+                            .long("sampling-seed")
+                            .takes_value(true)
+                            .requires("sampling-rate")
+                            .validator(|input| FromStrParser::<u64>::default().validate(input))
+                            .help("Random seed of --sampling-rate (for reproducible result)"),
+                    )
+                    .arg(
+                        Arg::with_name("capacity-stats")
+                            .long("capacity-stats")
+                            .conflicts_with_all(&["sampling-rate", "include-consumed", "out-points-only", "with-tx-meta", "follow-dep-group", "fast-mode"])
+                            .help("Instead of listing cells, count all matched cells (ignore --limit) by capacity ranges (see --histogram-buckets)"),
+                    )
+                    .arg(
+                        Arg::with_name("histogram-buckets")
+                            .long("histogram-buckets")
+                            .takes_value(true)
+                            .default_value("100,1000,10000")
+                            .validator(|input| parse_histogram_buckets(&input).map(|_| ()))
+                            .help("Bucket boundaries (unit: CKB, ascending, comma separated) of --capacity-stats and --output-summary-only"),
+                    )
+                    .arg(
+                        Arg::with_name("output-summary-only")
+                            .long("output-summary-only")
+                            .alias("histogram")
+                            .conflicts_with_all(&["capacity-stats", "sampling-rate", "include-consumed", "out-points-only", "with-tx-meta", "follow-dep-group", "fast-mode"])
+                            .help("Only output a JSON array of {range, count, total_capacity} by --histogram-buckets, the last item is the dust cells (see --dust-threshold)"),
+                    )
+                    .arg(
+                        Arg::with_name("capacity-sum-only")
+                            .long("capacity-sum-only")
+                            .conflicts_with_all(&["capacity-stats", "output-summary-only", "sampling-rate", "include-consumed", "out-points-only", "with-tx-meta", "follow-dep-group", "resolve-dep-groups", "export-json"])
+                            .help("Only output {total_capacity_shannons, cell_count} of all matched cells (ignore --limit)"),
+                    )
+                    .arg(
+                        Arg::with_name("dust-threshold")
+                            .long("dust-threshold")
+                            .takes_value(true)
+                            .requires("output-summary-only")
+                            .validator(|input| CapacityParser.validate(input))
+                            .help("Cells below this capacity are counted as dust (unit: CKB, default: the first bucket boundary)"),
+                    )
+                    .arg(
+                        Arg::with_name("include-consumed")
+                            .long("include-consumed")
+                            .conflicts_with("out-points-only")
+                            .help("Also list the cells consumed in the block range (by --from and --to), only recent blocks kept by the index are available"),
+                    )
+                    .arg(
+                        Arg::with_name("out-points-only")
+                            .long("out-points-only")
+                            .conflicts_with_all(&["with-tx-meta", "follow-dep-group"])
+                            .help("Only output the out points ({tx-hash}-{index}, same format as --out-point)"),
+                    )
+                    .arg(
+                        Arg::with_name("with-tx-meta")
+                            .long("with-tx-meta")
+                            .help("Include block timestamp and confirmations of each live cell"),
+                    )
+                    .arg(
+                        Arg::with_name("input-data-decompression")
+                            .long("input-data-decompression")
+                            .takes_value(true)
+                            .possible_values(&["gzip", "lz4"])
+                            .help("Decompress the output_data of --output ckb-indexer-compatible (stored by transfer --output-data-compression)"),
+                    )
+                    .arg(
+                        Arg::with_name("show-since")
+                            .long("show-since")
+                            .conflicts_with("out-points-only")
+                            .help("Show the unlock condition of time-locked multisig cells (the since value in the last 8 bytes of 28 bytes lock args)"),
+                    )
+                    .arg(
+                        Arg::with_name("with-epoch")
+                            .long("with-epoch")
+                            .conflicts_with("out-points-only")
+                            .help("Include the epoch (number, index, length) of the block each live cell created in"),
+                    )
+                    .arg(
+                        Arg::with_name("annotate-with-block-time")
+                            .long("annotate-with-block-time")
+                            .conflicts_with("out-points-only")
+                            .help("Include the time (ISO 8601, UTC) of the block each live cell created in as `created_at_iso`"),
+                    )
+                    .arg(
+                        Arg::with_name("output-type-id-only")
+                            .long("output-type-id-only")
+                            .conflicts_with_all(&["sampling-rate", "capacity-stats", "output-summary-only", "export-json", "include-consumed"])
+                            .help("Only list Type ID cells (type script code hash is TYPE_ID_CODE_HASH), with the type_id_args fetched by RPC"),
+                    )
+                    .arg(
+                        Arg::with_name("resolve-lock-script")
+                            .long("resolve-lock-script")
+                            .conflicts_with("out-points-only")
+                            .help("Include the full lock script (code_hash, hash_type, args) of each cell, for locks can not be rendered as address"),
+                    )
+                    .arg(
+                        Arg::with_name("follow-dep-group")
+                            .long("follow-dep-group")
+                            .help("Expand dep_group cells (cell data is an out point vector) into their member cells"),
+                    )
+                    .arg(
+                        Arg::with_name("resolve-dep-groups")
+                            .long("resolve-dep-groups")
+                            .conflicts_with_all(&["capacity-stats", "output-summary-only", "out-points-only", "export-json", "follow-dep-group", "fast-mode"])
+                            .help("For cells with type script whose data is a dep_group (out point vector), add the member cells as `resolved_dep_cells`"),
+                    )
+                    .arg(
+                        Arg::with_name("deduplicate")
+                            .long("deduplicate")
+                            .help("Remove cells with duplicated out point (keep the one with higher block number) and warn the count, duplicates mean the index database is corrupted"),
+                    )
+                    .arg(
+                        Arg::with_name("export-json")
+                            .long("export-json")
+                            .takes_value(true)
+                            .validator(|input| FilePathParser::new(false).validate(input))
+                            .conflicts_with_all(&["capacity-stats", "output-summary-only", "sampling-rate", "include-consumed", "out-points-only", "with-tx-meta", "follow-dep-group", "fast-mode", "deduplicate"])
+                            .help("Write all matched live cells (ignore --limit) to this file, one json object per line"),
+                    )
+                    .arg(
+                        Arg::with_name("checkpoint-resume")
+                            .long("checkpoint-resume")
+                            .takes_value(true)
+                            .requires("export-json")
+                            .conflicts_with("by-created-in-tx")
+                            .validator(|input| FilePathParser::new(false).validate(input))
+                            .help("Save the progress of --export-json to this file every 10000 cells, rerun with the same file continues from the last checkpoint. The file is deleted on completion"),
+                    )
+                    .arg(
+                        Arg::with_name("no-cache")
+                            .long("no-cache")
+                            .help("Query the index database even if the same query is cached (interactive mode caches results until the index database processes a new block)"),
+                    ),
+                SubCommand::with_name("check")
+                    .about("Check the reachability, chain and tip block number of each RPC endpoint (by --url)"),
+                SubCommand::with_name("estimate-witness-size")
+                    .about("Estimate the witness size of an input group locked by the address (for fee calculation)")
+                    .arg(arg::address().required(true))
+                    .arg(
+                        Arg::with_name("multisig-threshold")
+                            .long("multisig-threshold")
+                            .takes_value(true)
+                            .requires("multisig-signers")
+                            .validator(|input| FromStrParser::<u8>::default().validate(input))
+                            .help("Signature threshold of the multisig address"),
+                    )
+                    .arg(
+                        Arg::with_name("multisig-signers")
+                            .long("multisig-signers")
+                            .takes_value(true)
+                            .requires("multisig-threshold")
+                            .validator(|input| FromStrParser::<u8>::default().validate(input))
+                            .help("Number of signers of the multisig address"),
+                    ),
+                SubCommand::with_name("sign-tx")
+                    .about("Sign the inputs of a previously built transaction file, signatures are written back to the file")
+                    .arg(arg::privkey_path())
+                    .arg(arg::from_account().conflicts_with(arg::privkey_path().b.name))
+                    .arg(arg::password_file().conflicts_with(arg::privkey_path().b.name))
+                    .arg(
+                        Arg::with_name("tx-file")
+                            .long("tx-file")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| FilePathParser::new(true).validate(input))
+                            .help("The transaction file: json TransactionView (by transfer --tx-file), or the multisig transaction file (by `tx init`, signatures are collected until the threshold is reached)"),
+                    )
+                    .arg(
+                        Arg::with_name("output")
+                            .long("output")
+                            .takes_value(true)
+                            .validator(|input| FilePathParser::new(false).validate(input))
+                            .help("Write the signed transaction to this file instead of --tx-file"),
+                    ),
+                SubCommand::with_name("get-tx-note")
+                    .about("Get the local note of a transaction (by transfer --note)")
+                    .arg(
+                        Arg::with_name("tx-hash")
+                            .long("tx-hash")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| FixedHashParser::<H256>::default().validate(input))
+                            .help("Transaction hash"),
+                    ),
+                SubCommand::with_name("list-tx-notes")
+                    .about("List all transactions with local notes (by transfer --note)"),
+                SubCommand::with_name("tx-history")
+                    .about("Show local records of sent transfers (newest first)")
+                    .arg(
+                        Arg::with_name("limit")
+                            .long("limit")
+                            .takes_value(true)
+                            .validator(|input| FromStrParser::<usize>::default().validate(input))
+                            .help("Only show the latest <limit> records"),
+                    ),
+                SubCommand::with_name("decode-tx-comment")
+                    .about("Decode the json metadata of a transaction (by transfer --tx-comment)")
+                    .arg(
+                        Arg::with_name("tx-hash")
+                            .long("tx-hash")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| FixedHashParser::<H256>::default().validate(input))
+                            .help("Transaction hash"),
+                    ),
+                SubCommand::with_name("index-diff")
+                    .about("Show cells of the address created and destroyed in a block range (consumed cells are only kept for recent blocks)")
+                    .arg(arg::address().required(true))
+                    .arg(
+                        Arg::with_name("from-block")
+                            .long("from-block")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| FromStrParser::<u64>::default().validate(input))
+                            .help("Start block number (inclusive)"),
+                    )
+                    .arg(
+                        Arg::with_name("to-block")
+                            .long("to-block")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(|input| FromStrParser::<u64>::default().validate(input))
+                            .help("End block number (inclusive)"),
                     ),
                 // Move to index subcommand
+                SubCommand::with_name("index-status")
+                    .about("Show index database sync status (progress, speed and ETA)"),
                 SubCommand::with_name("db-metrics")
                     .about("Show index database metrics")
                     .setting(AppSettings::Hidden),
@@ -155,89 +1014,584 @@ impl<'a> WalletSubCommand<'a> {
     pub fn transfer(
         &mut self,
         args: TransferArgs,
-        skip_check: bool,
+        validation_mode: CapacityValidationMode,
     ) -> Result<TransactionView, String> {
-        let TransferArgs {
-            privkey_path,
-            from_account,
-            from_locked_address,
-            password,
-            derive_receiving_address_length,
-            derive_change_address,
-            capacity,
-            tx_fee,
-            to_address,
-            to_data,
-        } = args;
+        self.transfer_with_info(args, validation_mode)
+            .map(|(tx, _)| tx)
+    }
 
-        let network_type = get_network_type(self.rpc_client)?;
-        let from_privkey: Option<PrivkeyWrapper> = privkey_path
-            .map(|input| PrivkeyPathParser.parse(&input))
-            .transpose()?;
-        let from_account: Option<H160> = from_account
-            .map(|input| {
-                FixedHashParser::<H160>::default()
-                    .parse(&input)
-                    .or_else(|err| {
-                        let result: Result<Address, String> = AddressParser::new_sighash()
-                            .set_network(network_type)
-                            .parse(&input);
-                        result
-                            .map(|address| H160::from_slice(&address.payload().args()).unwrap())
-                            .map_err(|_| err)
-                    })
-            })
-            .transpose()?;
-        let from_locked_address: Option<Address> = from_locked_address
-            .map(|input| {
-                AddressParser::default()
-                    .set_network(network_type)
-                    .set_full_type(MULTISIG_TYPE_HASH.clone())
-                    .parse(&input)
-            })
-            .transpose()?;
-        let to_capacity: u64 = CapacityParser.parse(&capacity)?.into();
-        let tx_fee: u64 = CapacityParser.parse(&tx_fee)?.into();
-        let receiving_address_length: u32 = derive_receiving_address_length
-            .map(|input| FromStrParser::<u32>::default().parse(&input))
-            .transpose()?
-            .unwrap_or(1000);
-        let last_change_address_opt: Option<Address> = derive_change_address
-            .map(|input| {
-                AddressParser::default()
-                    .set_network(network_type)
-                    .parse(&input)
-            })
-            .transpose()?;
-        let to_address: Address = AddressParser::default()
-            .set_network(network_type)
-            .parse(&to_address)?;
-        let to_data = to_data.unwrap_or_default();
+    pub fn transfer_with_info(
+        &mut self,
+        args: TransferArgs,
+        validation_mode: CapacityValidationMode,
+    ) -> Result<(TransactionView, TransferInfo), String> {
+        let trace_path = args.trace_path.clone();
+        let trace = Trace::new(trace_path.is_some());
+        trace.record(
+            "args",
+            serde_json::json!({
+                "privkey_path": args.privkey_path,
+                "from_mnemonic_phrase": args.from_mnemonic_phrase,
+                "mnemonic_path": args.mnemonic_path,
+                "from_account": args.from_account,
+                "from_locked_address": args.from_locked_address,
+                "password": args.password.as_ref().map(|_| REDACTED),
+                "recipients": args
+                    .recipients
+                    .iter()
+                    .map(|recipient| serde_json::json!({
+                        "to_address": recipient.address,
+                        "capacity": recipient.capacity,
+                        "to_data_len": recipient.data.len(),
+                    }))
+                    .collect::<Vec<_>>(),
+                "tx_fee": args.tx_fee,
+                "priority_fee": args.priority_fee,
+                "capacity_unit": format!("{:?}", args.capacity_unit),
+                "capacity_validation_mode": format!("{:?}", validation_mode),
+            }),
+        );
+        let result = self.transfer_with_trace(args, validation_mode, &trace);
+        if let Some(path) = trace_path {
+            trace.record(
+                "result",
+                match &result {
+                    Ok((tx, _)) => {
+                        let tx_hash: H256 = tx.hash().unpack();
+                        serde_json::json!({ "tx_hash": tx_hash })
+                    }
+                    Err(err) => serde_json::json!({ "error": err }),
+                },
+            );
+            trace.save(&path)?;
+        }
+        result
+    }
 
-        let (from_address_payload, password) = if let Some(from_privkey) = from_privkey.as_ref() {
-            let from_pubkey = secp256k1::PublicKey::from_secret_key(&SECP256K1, from_privkey);
-            (AddressPayload::from_pubkey(&from_pubkey), String::new())
-        } else {
-            let password = if let Some(password) = password {
-                password
-            } else {
-                read_password(false, None)?
+    // If the transfer needs more than `threshold` inputs, send a transaction moving the capacity
+    // it needs into one cell of the from-address first, then restrict the transfer inputs to the
+    // block the consolidation is committed in
+    fn auto_consolidate(
+        &mut self,
+        args: &mut TransferArgs,
+        threshold: usize,
+        yes: bool,
+        validation_mode: CapacityValidationMode,
+    ) -> Result<(), String> {
+        let mut estimate_args = args.clone();
+        estimate_args.fee_estimate_only = true;
+        estimate_args.trace_path = None;
+        let (_, info) = self.transfer_with_info(estimate_args, validation_mode)?;
+        let estimate = match info.fee_estimate {
+            Some(estimate) if estimate.inputs > threshold => estimate,
+            _ => return Ok(()),
+        };
+        let capacity_unit = args.capacity_unit;
+        let capacity = estimate.outputs_capacity + estimate.tx_fee;
+        if !yes {
+            eprint!(
+                "The transfer needs {} inputs (more than --auto-consolidate-threshold {}), send {:#} to {} in one cell first? [y/N] ",
+                estimate.inputs,
+                threshold,
+                HumanCapacity::from(capacity),
+                info.from_address,
+            );
+            io::stderr().flush().map_err(|err| err.to_string())?;
+            let mut answer = String::new();
+            io::stdin()
+                .read_line(&mut answer)
+                .map_err(|err| err.to_string())?;
+            if !answer.trim().eq_ignore_ascii_case("y") {
+                return Err("Consolidation cancelled".to_string());
+            }
+        }
+
+        // Same signer, input selection and fee as the transfer, a plain output to the from-address
+        let consolidate_args = TransferArgs {
+            recipients: vec![Recipient {
+                address: Some(info.from_address.clone()),
+                lock: None,
+                capacity: capacity_unit.format(capacity),
+                data: Bytes::new(),
+            }],
+            split_output: None,
+            tx_comment: None,
+            burn_capacity: None,
+            recipient_index: None,
+            change_data: None,
+            change_type: None,
+            trace_path: None,
+            ..args.clone()
+        };
+        let (tx, _) = self.transfer_with_info(consolidate_args, validation_mode)?;
+        let tx_hash: H256 = tx.hash().unpack();
+        eprintln!(
+            "[NOTE] consolidation transaction {:#x} ({} inputs) sent, waiting for it to be committed",
+            tx_hash,
+            tx.inputs().len()
+        );
+        let start = Instant::now();
+        let timeout = Duration::from_secs(CONSOLIDATE_TIMEOUT_SECS);
+        let block_hash = loop {
+            if let Some(block_hash) = self
+                .rpc_client
+                .get_transaction(tx_hash.clone())?
+                .and_then(|tx| tx.tx_status.block_hash)
+            {
+                break block_hash;
+            }
+            if start.elapsed() >= timeout {
+                return Err(format!(
+                    "Consolidation transaction {:#x} is not committed after {}s",
+                    tx_hash, CONSOLIDATE_TIMEOUT_SECS
+                ));
+            }
+            thread::sleep(Duration::from_secs(1));
+        };
+        let number = self
+            .rpc_client
+            .get_header(block_hash.clone())?
+            .ok_or_else(|| format!("Block header not found: {:#x}", block_hash))?
+            .inner
+            .number
+            .0;
+        if let Some(lag) = wait_index_synced(&self.index_controller, self.rpc_client, 0, timeout)? {
+            return Err(format!(
+                "Index is still {} blocks behind the tip after {}s, the consolidated cell is not available",
+                lag, CONSOLIDATE_TIMEOUT_SECS
+            ));
+        }
+
+        let from_address = AddressParser::default().parse(&info.from_address)?;
+        let query = serde_json::json!({
+            "script": json_types::Script::from(Script::from(from_address.payload())),
+            "script_type": "lock",
+            "filter": {
+                "block_range": [format!("{:#x}", number), format!("{:#x}", number + 1)],
+            },
+        });
+        args.input_from_query = Some(query.to_string());
+        Ok(())
+    }
+
+    // The fee depends on the transaction size, which depends on the inputs and the change cell
+    // selected by the fee. Estimate with the fee of last round until it covers the size, the fee
+    // never decreases so the loop converges.
+    pub fn transfer_with_fee_rate(
+        &mut self,
+        args: &mut TransferArgs,
+        fee_rate: u64,
+        validation_mode: CapacityValidationMode,
+    ) -> Result<(TransactionView, TransferInfo), String> {
+        if fee_rate < MIN_FEE_RATE {
+            return Err(format!(
+                "--fee-rate {} is less than the minimal fee rate {} (shannons/KB)",
+                fee_rate, MIN_FEE_RATE
+            ));
+        }
+        // Only the fee and the inputs change between the rounds
+        args.read_credentials()?;
+        let mut tx_fee = 0;
+        for _ in 0..FEE_RATE_MAX_ITERATIONS {
+            args.tx_fee = args.capacity_unit.format(tx_fee);
+            let mut estimate_args = args.clone();
+            estimate_args.fee_estimate_only = true;
+            let (_, info) = self.transfer_with_info(estimate_args, validation_mode)?;
+            let estimate = info
+                .fee_estimate
+                .ok_or_else(|| "Estimate transaction fee failed".to_string())?;
+            let required_fee = (estimate.tx_size as u64 * fee_rate + 999) / 1000;
+            if required_fee <= tx_fee {
+                return self.transfer_with_info(args.clone(), validation_mode);
+            }
+            tx_fee = required_fee;
+        }
+        Err(format!(
+            "Transaction fee is not stable after {} rounds of estimation (last: {:#})",
+            FEE_RATE_MAX_ITERATIONS,
+            HumanCapacity::from(tx_fee)
+        ))
+    }
+
+    fn transfer_with_trace(
+        &mut self,
+        args: TransferArgs,
+        validation_mode: CapacityValidationMode,
+        trace: &Trace,
+    ) -> Result<(TransactionView, TransferInfo), String> {
+        let network_type = trace.rpc(
+            "get_blockchain_info",
+            serde_json::json!([]),
+            get_network_type(self.rpc_client),
+        )?;
+        let priority_fee: u64 = args
+            .priority_fee
+            .as_ref()
+            .map(|input| FromStrParser::<u64>::default().parse(input))
+            .transpose()?
+            .unwrap_or(0);
+        let tx_fee: u64 = args.capacity_unit.parse(&args.tx_fee)? + priority_fee;
+        let tx_comment: Option<Bytes> = args
+            .tx_comment
+            .as_deref()
+            .map(encode_tx_comment)
+            .transpose()?;
+        let TransferOutputs {
+            to_outputs,
+            adjusted_capacities,
+            burn_output,
+            outputs_capacity,
+        } = parse_transfer_outputs(&args, network_type, validation_mode)?;
+
+        let genesis_info = self.genesis_info()?;
+        let anchor_block_hash = self.get_anchor_block_hash(&args, trace)?;
+        // For check index database is ready
+        self.with_db(|_| ())?;
+        let type_id_cell_dep = args
+            .cell_dep_from_type_id
+            .as_ref()
+            .map(|type_id_hash| self.get_type_id_cell_dep(type_id_hash, trace))
+            .transpose()?;
+
+        let mut helper = TxHelper::default();
+        helper.set_hash_fn(args.sign_hash_fn);
+        if let Some(block_hash) = anchor_block_hash {
+            helper.add_header_dep(block_hash.pack());
+        }
+        let mut sender = self.resolve_sender(&args, network_type, trace)?;
+        if args.preview_change_address {
+            let info = TransferInfo {
+                from_address: sender.from_address.to_string(),
+                change_address: Some(
+                    Address::new(network_type, sender.change_address_payload).to_string(),
+                ),
+                ..Default::default()
             };
-            (
-                AddressPayload::from_pubkey_hash(from_account.unwrap()),
-                password,
+            return Ok((helper.transaction().clone(), info));
+        }
+        if let Some(from_locked_address) = sender.from_locked_address.as_ref() {
+            sender.lock_hashes.insert(
+                0,
+                Script::from(from_locked_address.payload()).calc_script_hash(),
+            );
+            for lock_arg in std::iter::once(&sender.from_lock_arg).chain(sender.path_map.keys()) {
+                let mut sighash_addresses = Vec::default();
+                sighash_addresses.push(AddressPayload::from_pubkey_hash(lock_arg.clone()));
+                let require_first_n = 0;
+                let threshold = 1;
+                let cfg = MultisigConfig::new_with(sighash_addresses, require_first_n, threshold)?;
+                if cfg.hash160().as_bytes() == &from_locked_address.payload().args()[0..20] {
+                    helper.add_multisig_config(cfg);
+                    break;
+                }
+            }
+            if helper.multisig_configs().is_empty() {
+                return Err(String::from(
+                    "from-locked-address is not created from the key or derived keys",
+                ));
+            }
+        }
+
+        let change_output_min = get_change_output_min(&args, &sender.change_address_payload)?;
+        let inputs = self.select_inputs(
+            &args,
+            &sender,
+            outputs_capacity,
+            tx_fee,
+            change_output_min,
+            trace,
+        )?;
+        let (outputs, rest_capacity, bumped_capacity) = assemble_outputs(
+            &args,
+            to_outputs,
+            burn_output,
+            &sender.change_address_payload,
+            change_output_min,
+            inputs.capacity - outputs_capacity - tx_fee,
+            tx_fee,
+        )?;
+
+        // Permissive mode also skips the lock script and output capacity checks of the helper
+        let skip_check = validation_mode == CapacityValidationMode::Permissive;
+        let mut live_cell_cache: HashMap<(OutPoint, bool), (CellOutput, Bytes)> =
+            Default::default();
+        let rpc_client = &mut *self.rpc_client;
+        let mut get_live_cell_fn = |out_point: OutPoint, with_data: bool| {
+            get_traced_live_cell(
+                &mut live_cell_cache,
+                rpc_client,
+                trace,
+                out_point,
+                with_data,
             )
         };
-        let from_address = Address::new(network_type, from_address_payload.clone());
+        // Inputs keep the order they come out of the index database (deterministic by creation
+        // order), which is relied on by --preserve-cell-order
+        for info in &inputs.infos {
+            helper.add_input(
+                info.out_point(),
+                None,
+                &mut get_live_cell_fn,
+                &genesis_info,
+                skip_check,
+            )?;
+        }
+        if let Some((out_point, _)) = inputs.fee_cell.as_ref() {
+            helper.add_input(
+                out_point.clone(),
+                None,
+                &mut get_live_cell_fn,
+                &genesis_info,
+                skip_check,
+            )?;
+        }
+        if let Some(cell_dep) = type_id_cell_dep {
+            helper.add_cell_dep(cell_dep);
+        }
+        for (output, data) in outputs {
+            helper.add_output(output, data);
+        }
+        if let Some(witness) = tx_comment {
+            helper.add_extra_witness(witness);
+        }
+
+        let sighashes = if args.dump_sighash {
+            Some(helper.sighash_messages(&mut get_live_cell_fn, skip_check)?)
+        } else {
+            None
+        };
+        let mut info = TransferInfo {
+            from_address: sender.from_address.to_string(),
+            data_cells: inputs.data_cells,
+            skipped_cells: inputs.skipped_cells,
+            consumed_cells: inputs.consumed_cells,
+            typed_cells: inputs.typed_cells,
+            adjusted_capacities,
+            bumped_capacity,
+            input_capacity_target: inputs.input_capacity_target,
+            sighashes,
+            ..Default::default()
+        };
+
+        if args.fee_estimate_only {
+            let change_capacity =
+                if args.has_custom_change() || rest_capacity >= MIN_SECP_CELL_CAPACITY {
+                    rest_capacity
+                } else {
+                    0
+                };
+            let tx_size = estimate_tx_size(&helper, &mut get_live_cell_fn, args.input_size_hint)?;
+            if args.simulate_since_validation {
+                info.since_checks = Some(self.simulate_since_validation(helper.transaction())?);
+            }
+            info.fee_estimate = Some(FeeEstimate {
+                inputs: inputs.infos.len() + inputs.fee_cell.iter().count(),
+                tx_size,
+                min_fee: (tx_size as u64 * MIN_FEE_RATE + 999) / 1000,
+                tx_fee: tx_fee + rest_capacity - change_capacity,
+                change_capacity,
+                outputs_capacity,
+            });
+            return Ok((helper.transaction().clone(), info));
+        }
+
+        let tx = self.sign_transfer(
+            helper,
+            &args,
+            sender,
+            &mut live_cell_cache,
+            skip_check,
+            trace,
+        )?;
+        self.finish_transfer(tx, info, &args, &mut live_cell_cache, trace)
+    }
+
+    // The block hash added as header dep (by --anchor-to-tip or --block-hash)
+    fn get_anchor_block_hash(
+        &mut self,
+        args: &TransferArgs,
+        trace: &Trace,
+    ) -> Result<Option<H256>, String> {
+        if args.anchor_to_tip {
+            let header = trace.rpc(
+                "get_tip_header",
+                serde_json::json!([]),
+                self.rpc_client.get_tip_header(),
+            )?;
+            return Ok(Some(header.hash));
+        }
+        let block_hash = match args.block_hash.as_ref() {
+            Some(block_hash) => block_hash,
+            None => return Ok(None),
+        };
+        let block_hash: H256 = FixedHashParser::<H256>::default().parse(block_hash)?;
+        let header = trace
+            .rpc(
+                "get_header",
+                serde_json::json!([block_hash]),
+                self.rpc_client.get_header(block_hash.clone()),
+            )?
+            .ok_or_else(|| format!("Anchor block not found: {:#x}", block_hash))?;
+        let tip_number = trace.rpc(
+            "get_tip_block_number",
+            serde_json::json!([]),
+            self.rpc_client.get_tip_block_number(),
+        )?;
+        let distance = tip_number.saturating_sub(header.inner.number.0);
+        if distance > MAX_ANCHOR_BLOCK_DISTANCE {
+            return Err(format!(
+                "Anchor block {:#x} is {} blocks behind tip, must be within {} blocks",
+                block_hash, distance, MAX_ANCHOR_BLOCK_DISTANCE,
+            ));
+        }
+        Ok(Some(block_hash))
+    }
+
+    // The code cell dep found by its Type ID (by --cell-dep-from-type-id)
+    fn get_type_id_cell_dep(
+        &mut self,
+        type_id_hash: &str,
+        trace: &Trace,
+    ) -> Result<CellDep, String> {
+        let type_id_hash: H256 = FixedHashParser::<H256>::default().parse(type_id_hash)?;
+        let infos = self.with_db(|db| {
+            db.get_live_cells_by_type(type_id_hash.pack(), None, |idx, _| (idx >= 1, true))
+        })?;
+        trace.index_query(
+            "get_live_cells_by_type",
+            serde_json::json!({ "type_hash": type_id_hash }),
+            infos.len(),
+        );
+        match infos.as_slice() {
+            [info] => Ok(CellDep::new_builder()
+                .out_point(info.out_point())
+                .dep_type(DepType::Code.into())
+                .build()),
+            [] => Err(format!(
+                "No live cell found by Type ID: {:#x}",
+                type_id_hash
+            )),
+            _ => Err(format!(
+                "More than one live cell found by Type ID: {:#x}",
+                type_id_hash
+            )),
+        }
+    }
+
+    // The from-address, its keys and the change address, the keystore password is only read for
+    // --derive-change-address
+    fn resolve_sender(
+        &mut self,
+        args: &TransferArgs,
+        network_type: NetworkType,
+        trace: &Trace,
+    ) -> Result<TransferSender, String> {
+        // The derived key is zeroed when dropped at the end of the transfer
+        let from_privkey: Option<PrivkeyWrapper> = if args.mnemonic_privkey.is_some() {
+            args.mnemonic_privkey.clone()
+        } else if args.from_mnemonic_phrase {
+            let path = DerivationPathParser.parse(
+                args.mnemonic_path
+                    .as_deref()
+                    .unwrap_or(MNEMONIC_DEFAULT_PATH),
+            )?;
+            Some(read_mnemonic_privkey(&path)?)
+        } else {
+            args.privkey_path
+                .as_ref()
+                .map(|input| PrivkeyPathParser.parse(input))
+                .transpose()?
+        };
+        let account_parser = AccountParser::new(Some(network_type));
+        let from_account: Option<H160> = args
+            .from_account
+            .as_ref()
+            .map(|input| account_parser.parse(input))
+            .transpose()?;
+        let extra_from_accounts: Vec<H160> = args
+            .extra_from_accounts
+            .iter()
+            .map(|input| account_parser.parse(input))
+            .collect::<Result<Vec<_>, String>>()?;
+        if !extra_from_accounts.is_empty()
+            && (args.parallel_sign || args.from_locked_address.is_some())
+        {
+            return Err(
+                "Multiple --from-account is not supported with --parallel-sign or --from-locked-address"
+                    .to_string(),
+            );
+        }
+        let change_to: Option<Address> = args
+            .change_to
+            .as_ref()
+            .map(|input| {
+                AddressParser::default()
+                    .set_network(network_type)
+                    .parse(input)
+            })
+            .transpose()?;
+        let from_locked_address: Option<Address> = args
+            .from_locked_address
+            .as_ref()
+            .map(|input| {
+                AddressParser::default()
+                    .set_network(network_type)
+                    .set_full_type(MULTISIG_TYPE_HASH.clone())
+                    .parse(input)
+            })
+            .transpose()?;
+        let receiving_address_length: u32 = args
+            .derive_receiving_address_length
+            .as_ref()
+            .map(|input| FromStrParser::<u32>::default().parse(input))
+            .transpose()?
+            .unwrap_or(1000);
+        let last_change_address_opt: Option<Address> = args
+            .derive_change_address
+            .as_ref()
+            .map(|input| {
+                AddressParser::default()
+                    .set_network(network_type)
+                    .parse(input)
+            })
+            .transpose()?;
 
+        let from_address_payload = if let Some(from_privkey) = from_privkey.as_ref() {
+            let from_pubkey = secp256k1::PublicKey::from_secret_key(&SECP256K1, from_privkey);
+            let payload = AddressPayload::from_pubkey(&from_pubkey);
+            if args.from_mnemonic_phrase {
+                trace.key_derivation(
+                    args.mnemonic_path
+                        .as_deref()
+                        .unwrap_or(MNEMONIC_DEFAULT_PATH),
+                    format!("{:#x}", H160::from_slice(&payload.args()).unwrap()),
+                );
+            }
+            payload
+        } else {
+            AddressPayload::from_pubkey_hash(from_account.unwrap())
+        };
+        let from_address = Address::new(network_type, from_address_payload.clone());
+        if let Some(expected) = args.require_lock_hash.as_ref() {
+            let expected: H256 = FixedHashParser::<H256>::default().parse(expected)?;
+            let actual: H256 = Script::from(&from_address_payload)
+                .calc_script_hash()
+                .unpack();
+            if actual != expected {
+                return Err(format!(
+                    "Lock hash of from-address {} mismatch: expected {:#x}, actual {:#x}",
+                    from_address, expected, actual
+                ));
+            }
+        }
         if let Some(from_locked_address) = from_locked_address.as_ref() {
-            let args = from_locked_address.payload().args();
+            let lock_args = from_locked_address.payload().args();
             let err_prefix = "Invalid from-locked-address's args";
-            if args.len() != 28 {
-                return Err(format!("{}: invalid {}", err_prefix, args.len()));
+            if lock_args.len() != 28 {
+                return Err(format!("{}: invalid {}", err_prefix, lock_args.len()));
             }
             let mut since_bytes = [0u8; 8];
-            since_bytes.copy_from_slice(&args[20..]);
+            since_bytes.copy_from_slice(&lock_args[20..]);
             let since = Since::from_raw_value(u64::from_le_bytes(since_bytes));
             if !since.flags_is_valid() {
                 return Err(format!("{}: invalid since flags", err_prefix));
@@ -251,33 +1605,11 @@ impl<'a> WalletSubCommand<'a> {
             }
         }
 
-        let to_address_hash_type = to_address.payload().hash_type();
-        let to_address_code_hash: H256 = to_address.payload().code_hash().unpack();
-        let to_address_args_len = to_address.payload().args().len();
-        if !(to_address_hash_type == ScriptHashType::Type
-            && to_address_code_hash == SIGHASH_TYPE_HASH
-            && to_address_args_len == 20)
-            && !(to_address_hash_type == ScriptHashType::Type
-                && to_address_code_hash == MULTISIG_TYPE_HASH
-                && (to_address_args_len == 20 || to_address_args_len == 28))
-        {
-            return Err(format!("Invalid to-address: {}", to_address));
-        }
-        check_capacity(to_capacity, to_data.len())?;
-
-        let genesis_info = self.genesis_info()?;
-
-        // For check index database is ready
-        self.with_db(|_| ())?;
-        let index_dir = self.index_dir.clone();
-        let genesis_hash = genesis_info.header().hash();
-        let genesis_info_clone = genesis_info.clone();
-
         // The lock hashes for search live cells
         let mut lock_hashes = vec![Script::from(&from_address_payload).calc_script_hash()];
-        let mut helper = TxHelper::default();
-
         let from_lock_arg = H160::from_slice(from_address.payload().args().as_ref()).unwrap();
+        // The keystore password is read when it is first needed, not for --fee-estimate-only
+        let mut password = args.password.clone();
         let mut path_map: HashMap<H160, DerivationPath> = Default::default();
         let change_address_payload = if let Some(last_change_address) = last_change_address_opt {
             // Behave like HD wallet
@@ -287,67 +1619,285 @@ impl<'a> WalletSubCommand<'a> {
                 .key_store
                 .derived_key_set_with_password(
                     &from_lock_arg,
-                    password.as_bytes(),
+                    get_or_read_password(&mut password, None)?.as_bytes(),
                     receiving_address_length,
                     &change_last,
                     DERIVE_CHANGE_ADDRESS_MAX_LEN,
                 )
                 .map_err(|err| err.to_string())?;
             for (path, hash160) in key_set.external.iter().chain(key_set.change.iter()) {
+                trace.key_derivation(&path.to_string(), format!("{:#x}", hash160));
                 path_map.insert(hash160.clone(), path.clone());
                 let payload = AddressPayload::from_pubkey_hash(hash160.clone());
                 lock_hashes.push(Script::from(&payload).calc_script_hash());
             }
             last_change_address.payload().clone()
+        } else if let Some(change_to) = change_to {
+            change_to.payload().clone()
         } else {
             from_address.payload().clone()
         };
+        // Cells of other accounts (by repeated --from-account) are spent together
+        let mut extra_accounts: Vec<H160> = Vec::new();
+        for account in extra_from_accounts {
+            if account == from_lock_arg || extra_accounts.contains(&account) {
+                return Err(format!("Duplicated --from-account: {:#x}", account));
+            }
+            if !self.key_store.has_account(&account) {
+                return Err(format!("Account not found: {:#x}", account));
+            }
+            let payload = AddressPayload::from_pubkey_hash(account.clone());
+            lock_hashes.push(Script::from(&payload).calc_script_hash());
+            extra_accounts.push(account);
+        }
+        Ok(TransferSender {
+            network_type,
+            from_privkey,
+            from_address,
+            from_lock_arg,
+            from_locked_address,
+            extra_accounts,
+            path_map,
+            change_address_payload,
+            lock_hashes,
+            password,
+        })
+    }
 
-        if let Some(from_locked_address) = from_locked_address.as_ref() {
-            lock_hashes.insert(
-                0,
-                Script::from(from_locked_address.payload()).calc_script_hash(),
-            );
-            for lock_arg in std::iter::once(&from_lock_arg).chain(path_map.keys()) {
-                let mut sighash_addresses = Vec::default();
-                sighash_addresses.push(AddressPayload::from_pubkey_hash(lock_arg.clone()));
-                let require_first_n = 0;
-                let threshold = 1;
-                let cfg = MultisigConfig::new_with(sighash_addresses, require_first_n, threshold)?;
-                if cfg.hash160().as_bytes() == &from_locked_address.payload().args()[0..20] {
-                    helper.add_multisig_config(cfg);
-                    break;
+    // Collect the input cells from the index database, the fee cell (by --fee-input) is not added
+    // to the selected cells but counted in the selected capacity
+    fn select_inputs(
+        &mut self,
+        args: &TransferArgs,
+        sender: &TransferSender,
+        outputs_capacity: u64,
+        tx_fee: u64,
+        change_output_min: u64,
+        trace: &Trace,
+    ) -> Result<SelectedInputs, String> {
+        let capacity_unit = args.capacity_unit;
+        let input_capacity_min: u64 = args
+            .input_capacity_min
+            .as_ref()
+            .map(|input| capacity_unit.parse(input))
+            .transpose()?
+            .unwrap_or(0);
+        let input_capacity_target: Option<u64> = args
+            .input_capacity_target
+            .as_ref()
+            .map(|input| capacity_unit.parse(input))
+            .transpose()?;
+        let gas_reserve: Option<u64> = args
+            .gas_reserve
+            .as_ref()
+            .map(|input| capacity_unit.parse(input))
+            .transpose()?;
+        let from_address = &sender.from_address;
+        let mut lock_hashes = sender.lock_hashes.clone();
+
+        // Calls get_tip_header and get_epoch_by_number
+        let max_mature_number = trace.rpc(
+            "get_max_mature_number",
+            serde_json::json!([]),
+            get_max_mature_number(self.rpc_client),
+        )?;
+        if args.capacity_precheck {
+            let breakdown = self.get_capacity_breakdown(
+                &lock_hashes,
+                max_mature_number,
+                args.allow_cell_data,
+                input_capacity_min,
+            )?;
+            trace.record("capacity_precheck", serde_json::json!(breakdown));
+            if outputs_capacity + tx_fee > breakdown.spendable {
+                return Err(format!(
+                    "Capacity not enough: {} required, {} has {} total but only {} spendable; {} in DAO, {} immature, {} in cells with type script, {} in cells with data, {} in cells below --input-capacity-min",
+                    HumanCapacity::from(outputs_capacity + tx_fee),
+                    from_address,
+                    HumanCapacity::from(breakdown.total),
+                    HumanCapacity::from(breakdown.spendable),
+                    HumanCapacity::from(breakdown.dao),
+                    HumanCapacity::from(breakdown.immature),
+                    HumanCapacity::from(breakdown.with_type),
+                    HumanCapacity::from(breakdown.with_data),
+                    HumanCapacity::from(breakdown.dust),
+                ));
+            }
+        }
+        let input_query: Option<InputQuery> = args
+            .input_from_query
+            .as_ref()
+            .map(|input| {
+                serde_json::from_str(input)
+                    .map_err(|err| format!("Invalid --input-from-query: {}", err))
+            })
+            .transpose()?;
+        if let Some(query) = input_query.as_ref() {
+            if query.script_type == InputQueryScriptType::Lock
+                && query.script_search_mode == InputQuerySearchMode::Exact
+            {
+                let lock_hash = Script::from(query.script.clone()).calc_script_hash();
+                if !lock_hashes.contains(&lock_hash) {
+                    return Err(
+                        "Lock script of --input-from-query is not owned by the sender".to_string(),
+                    );
                 }
+                lock_hashes = vec![lock_hash];
             }
-            if helper.multisig_configs().is_empty() {
-                return Err(String::from(
-                    "from-locked-address is not created from the key or derived keys",
+        }
+        let allow_type_cells = input_query
+            .as_ref()
+            .map(|query| query.script_type == InputQueryScriptType::Type)
+            .unwrap_or(false);
+        // (out point, capacity) of the cell paying the fee (by --fee-input)
+        let fee_cell = if let Some(fee_input) = args.fee_input.as_ref() {
+            let out_point = OutPointParser.parse(fee_input)?;
+            let (output, data) = get_live_cell(self.rpc_client, out_point.clone(), true)?;
+            if !lock_hashes.contains(&output.lock().calc_script_hash()) {
+                return Err(format!("Fee cell {} is not owned by the sender", fee_input));
+            }
+            if output.type_().to_opt().is_some() || !data.is_empty() {
+                return Err(format!(
+                    "Fee cell {} must have no type script and empty data",
+                    fee_input
+                ));
+            }
+            let capacity: u64 = output.capacity().unpack();
+            if capacity < tx_fee + MIN_SECP_CELL_CAPACITY {
+                return Err(format!(
+                    "Fee cell {} capacity {:#} can not cover the fee and the minimum change, at least {:#} required",
+                    fee_input,
+                    HumanCapacity::from(capacity),
+                    HumanCapacity::from(tx_fee + MIN_SECP_CELL_CAPACITY),
+                ));
+            }
+            Some((out_point, capacity))
+        } else {
+            None
+        };
+        // The fee cell covers the fee, other inputs only need to cover the outputs
+        let selection_fee = if fee_cell.is_some() { 0 } else { tx_fee };
+        let required_capacity = outputs_capacity + selection_fee + change_output_min;
+        if let Some(target) = input_capacity_target {
+            if target < required_capacity {
+                return Err(format!(
+                    "--input-capacity-target {} is less than the required {}",
+                    HumanCapacity::from(target),
+                    HumanCapacity::from(required_capacity),
                 ));
             }
         }
+        // Inputs are capped so the from-address keeps the reserve even if no change returns to it
+        let reserve_limit = match gas_reserve {
+            Some(reserve) => {
+                let ((total_capacity, immature_capacity, dao_capacity), _) =
+                    self.get_capacity_with_cells(lock_hashes.clone(), false)?;
+                let spendable = total_capacity - dao_capacity - immature_capacity;
+                if spendable < reserve + outputs_capacity + tx_fee {
+                    return Err(format!(
+                        "Capacity {} minus --gas-reserve {} is less than the required {} (capacity + fee)",
+                        HumanCapacity::from(spendable),
+                        HumanCapacity::from(reserve),
+                        HumanCapacity::from(outputs_capacity + tx_fee),
+                    ));
+                }
+                Some(spendable - reserve)
+            }
+            None => None,
+        };
+        let genesis_info = self.genesis_info()?;
+        let index_dir = self.index_dir.clone();
+        let genesis_hash = genesis_info.header().hash();
+        let network_type = sender.network_type;
+        let allow_cell_data = args.allow_cell_data;
+        let verify_against_rpc = args.verify_against_rpc;
 
-        let max_mature_number = get_max_mature_number(self.rpc_client)?;
+        // Greedy subset-sum: with a target, only take the cells not exceeding it
+        let selection_target = input_capacity_target.unwrap_or(required_capacity);
         let mut from_capacity = 0;
         let mut infos: Vec<LiveCellInfo> = Default::default();
+        let mut data_cells = 0;
+        let mut skipped_cells = 0;
+        let mut consumed_cells = 0;
+        let mut typed_cells = 0;
+        let mut rpc_error: Option<String> = None;
+        let rpc_client = &mut *self.rpc_client;
         let mut terminator = |_, info: &LiveCellInfo| {
-            if from_capacity >= to_capacity + tx_fee {
+            if from_capacity >= selection_target {
                 (true, false)
-            } else if info.type_hashes.is_none()
-                && info.data_bytes == 0
+            } else if fee_cell
+                .as_ref()
+                .map(|(out_point, _)| out_point == &info.out_point())
+                .unwrap_or(false)
+            {
+                (false, false)
+            } else if (info.type_hashes.is_none() || allow_type_cells)
+                && (allow_cell_data || info.data_bytes == 0)
                 && is_mature(info, max_mature_number)
             {
+                if let Some(query) = input_query.as_ref() {
+                    match query.matches(info, rpc_client) {
+                        Ok(true) => {}
+                        Ok(false) => return (false, false),
+                        Err(err) => {
+                            rpc_error = Some(err);
+                            return (true, false);
+                        }
+                    }
+                }
+                if info.capacity < input_capacity_min {
+                    skipped_cells += 1;
+                    return (false, false);
+                }
+                if input_capacity_target.is_some()
+                    && from_capacity + info.capacity > selection_target
+                {
+                    return (false, false);
+                }
+                if reserve_limit
+                    .map(|limit| from_capacity + info.capacity > limit)
+                    .unwrap_or(false)
+                {
+                    return (false, false);
+                }
+                if verify_against_rpc {
+                    match rpc_client.get_live_cell(info.out_point(), false) {
+                        Ok(cell) if cell.status == "live" => {}
+                        Ok(_) => {
+                            consumed_cells += 1;
+                            return (false, false);
+                        }
+                        Err(err) => {
+                            rpc_error = Some(err);
+                            return (true, false);
+                        }
+                    }
+                }
+                if info.data_bytes > 0 {
+                    data_cells += 1;
+                }
+                if info.type_hashes.is_some() {
+                    typed_cells += 1;
+                }
                 from_capacity += info.capacity;
                 infos.push(info.clone());
-                (from_capacity >= to_capacity + tx_fee, false)
+                (from_capacity >= selection_target, false)
             } else {
                 (false, false)
             }
         };
+        let lock_hashes_value = lock_hashes
+            .iter()
+            .map(|lock_hash| {
+                let lock_hash: H256 = lock_hash.unpack();
+                format!("{:#x}", lock_hash)
+            })
+            .collect::<Vec<_>>();
         if let Err(err) = with_index_db(&index_dir, genesis_hash.unpack(), |backend, cf| {
-            IndexDatabase::from_db(backend, cf, network_type, genesis_info_clone, false)
+            IndexDatabase::from_db(backend, cf, network_type, genesis_info, false)
                 .map(|db| {
-                    for lock_hash in lock_hashes {
-                        db.get_live_cells_by_lock(lock_hash, None, &mut terminator);
+                    for lock_hash in &lock_hashes {
+                        db.get_live_cells_by_lock(lock_hash.clone(), None, &mut terminator);
                     }
                 })
                 .map_err(Into::into)
@@ -358,71 +1908,280 @@ impl<'a> WalletSubCommand<'a> {
                 err.to_string(),
             ));
         }
+        trace.index_query(
+            "get_live_cells_by_lock",
+            serde_json::json!({
+                "lock_hashes": lock_hashes_value,
+                "data_cells": data_cells,
+                "skipped_cells": skipped_cells,
+                "consumed_cells": consumed_cells,
+            }),
+            infos.len(),
+        );
+        if let Some(err) = rpc_error {
+            return Err(format!("Get input cells by RPC failed: {}", err));
+        }
+        let input_capacity_target = input_capacity_target.map(|target| (target, from_capacity));
+        if let Some((_, capacity)) = fee_cell.as_ref() {
+            from_capacity += capacity;
+        }
 
-        if tx_fee > ONE_CKB {
+        if tx_fee > ONE_CKB && !args.dry_run {
             return Err("Transaction fee can not be more than 1.0 CKB".to_string());
         }
-        if to_capacity + tx_fee > from_capacity {
-            return Err(format!(
-                "Capacity(mature) not enough: {} => {}",
-                from_address, from_capacity,
-            ));
+        if let Some((target, selected)) = input_capacity_target {
+            if selected < required_capacity {
+                return Err(format!(
+                    "Can not select inputs within --input-capacity-target {}: closest {}, {} required",
+                    HumanCapacity::from(target),
+                    HumanCapacity::from(selected),
+                    HumanCapacity::from(required_capacity),
+                ));
+            }
         }
-
-        let rest_capacity = from_capacity - to_capacity - tx_fee;
-        if rest_capacity < MIN_SECP_CELL_CAPACITY && tx_fee + rest_capacity > ONE_CKB {
-            return Err("Transaction fee can not be more than 1.0 CKB, please change to-capacity value to adjust".to_string());
+        if outputs_capacity + tx_fee > from_capacity {
+            let err = if consumed_cells > 0 {
+                format!(
+                    "Capacity(mature) not enough: {} => {}, {} cells already consumed on chain, the index database may be behind",
+                    from_address, from_capacity, consumed_cells,
+                )
+            } else if skipped_cells > 0 {
+                format!(
+                    "Capacity(mature) not enough: {} => {}, {} cells below --input-capacity-min skipped, consider consolidating them first",
+                    from_address, from_capacity, skipped_cells,
+                )
+            } else if let Some(reserve) = gas_reserve {
+                format!(
+                    "Capacity(mature) not enough: {} => {}, no input combination keeps --gas-reserve {}",
+                    from_address,
+                    from_capacity,
+                    HumanCapacity::from(reserve),
+                )
+            } else {
+                format!(
+                    "Capacity(mature) not enough: {} => {}",
+                    from_address, from_capacity,
+                )
+            };
+            if args.dao_aware_selection {
+                let (dao_capacity, redeemable) = self.get_dao_redeemable(&lock_hashes)?;
+                if dao_capacity > 0 {
+                    return Err(format!(
+                        "{}, {:#} in DAO is not spendable (redeemable {:#} if withdrawn now)",
+                        err,
+                        HumanCapacity::from(dao_capacity),
+                        HumanCapacity::from(redeemable),
+                    ));
+                }
+            }
+            return Err(err);
         }
+        Ok(SelectedInputs {
+            infos,
+            fee_cell,
+            capacity: from_capacity,
+            data_cells,
+            skipped_cells,
+            consumed_cells,
+            typed_cells,
+            input_capacity_target,
+        })
+    }
 
-        let key_store = self.key_store.clone();
-        let mut live_cell_cache: HashMap<(OutPoint, bool), (CellOutput, Bytes)> =
-            Default::default();
-        let mut get_live_cell_fn = |out_point: OutPoint, with_data: bool| {
-            get_live_cell_with_cache(&mut live_cell_cache, self.rpc_client, out_point, with_data)
-                .map(|(output, _)| output)
-        };
-        for info in &infos {
-            helper.add_input(
-                info.out_point(),
-                None,
-                &mut get_live_cell_fn,
-                &genesis_info,
-                skip_check,
-            )?;
-        }
-        let to_output = CellOutput::new_builder()
-            .capacity(Capacity::shannons(to_capacity).pack())
-            .lock(to_address.payload().into())
-            .build();
-        helper.add_output(to_output, to_data);
-        if rest_capacity >= MIN_SECP_CELL_CAPACITY {
-            let change_output = CellOutput::new_builder()
-                .capacity(Capacity::shannons(rest_capacity).pack())
-                .lock((&change_address_payload).into())
-                .build();
-            helper.add_output(change_output, Bytes::default());
-        }
-
-        let signer = if let Some(from_privkey) = from_privkey {
-            get_privkey_signer(from_privkey)
+    // Sign all inputs by the private key or the keystore, reads the keystore password(s) not given
+    fn sign_transfer(
+        &mut self,
+        mut helper: TxHelper,
+        args: &TransferArgs,
+        sender: TransferSender,
+        live_cell_cache: &mut HashMap<(OutPoint, bool), (CellOutput, Bytes)>,
+        skip_check: bool,
+        trace: &Trace,
+    ) -> Result<TransactionView, String> {
+        let TransferSender {
+            from_privkey,
+            from_lock_arg,
+            extra_accounts,
+            path_map,
+            mut password,
+            ..
+        } = sender;
+        let password = if from_privkey.is_some() {
+            Arc::new(Password::new(String::new()))
         } else {
-            get_keystore_signer(key_store, path_map, from_lock_arg, password)
+            get_or_read_password(&mut password, None)?
+        };
+        let mut extra_passwords = args.extra_passwords.iter().cloned();
+        let extra_signers = extra_accounts
+            .into_iter()
+            .map(|account| {
+                let password = match extra_passwords.next().or_else(|| args.password.clone()) {
+                    Some(password) => password,
+                    None => Arc::new(read_password(
+                        false,
+                        Some(&format!("Password of {:#x}", account)),
+                    )?),
+                };
+                Ok((account, password))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        let key_store = self.key_store.clone();
+        let rpc_client = &mut *self.rpc_client;
+        let mut get_live_cell_fn = |out_point: OutPoint, with_data: bool| {
+            get_traced_live_cell(live_cell_cache, rpc_client, trace, out_point, with_data)
         };
-        for (lock_arg, signature) in
+        let signatures = if args.parallel_sign {
+            let signer =
+                get_parallel_signer(&key_store, from_privkey, path_map, from_lock_arg, password)?;
+            helper.sign_inputs_parallel(signer, &mut get_live_cell_fn, skip_check)?
+        } else {
+            let signer = if let Some(from_privkey) = from_privkey {
+                get_privkey_signer(from_privkey)
+            } else if !extra_signers.is_empty() {
+                get_multi_keystore_signer(
+                    key_store,
+                    path_map,
+                    from_lock_arg,
+                    password,
+                    extra_signers,
+                )
+            } else {
+                get_keystore_signer(key_store, path_map, from_lock_arg, password)
+            };
             helper.sign_inputs(signer, &mut get_live_cell_fn, skip_check)?
-        {
+        };
+        for (lock_arg, signature) in signatures {
+            trace.record(
+                "witness",
+                serde_json::json!({
+                    "step": "sign",
+                    "lock_arg": JsonBytes::from_bytes(lock_arg.clone()),
+                    "signature": JsonBytes::from_bytes(signature.clone()),
+                }),
+            );
             helper.add_signature(lock_arg, signature)?;
         }
         let tx = helper.build_tx(&mut get_live_cell_fn, skip_check)?;
-        let tx_hash = self
-            .rpc_client
-            .send_transaction(tx.data())
+        trace.record(
+            "witness",
+            serde_json::json!({
+                "step": "build_tx",
+                "witnesses": tx
+                    .witnesses()
+                    .into_iter()
+                    .map(|witness| JsonBytes::from_bytes(witness.raw_data()))
+                    .collect::<Vec<_>>(),
+            }),
+        );
+        Ok(tx)
+    }
+
+    // Checks and simulations of the signed transaction, then send it unless --no-broadcast or
+    // --dry-run
+    fn finish_transfer(
+        &mut self,
+        tx: TransactionView,
+        mut info: TransferInfo,
+        args: &TransferArgs,
+        live_cell_cache: &mut HashMap<(OutPoint, bool), (CellOutput, Bytes)>,
+        trace: &Trace,
+    ) -> Result<(TransactionView, TransferInfo), String> {
+        if args.verbose_witness {
+            let rpc_client = &mut *self.rpc_client;
+            info.witnesses = Some(decode_witnesses(&tx, |out_point, with_data| {
+                get_traced_live_cell(live_cell_cache, rpc_client, trace, out_point, with_data)
+            })?);
+        }
+        let no_broadcast = args.no_broadcast || args.dry_run;
+        if args.simulate_since_validation {
+            let checks = self.simulate_since_validation(&tx)?;
+            // The node rejects the transaction if any since is not satisfied
+            if !no_broadcast && checks.iter().any(|(_, satisfied, _)| !satisfied) {
+                let lines = checks
+                    .iter()
+                    .map(|(index, _, result)| format!("input #{}: {}", index, result))
+                    .collect::<Vec<_>>();
+                return Err(format!(
+                    "Since validation failed, the transaction is not sent:\n{}",
+                    lines.join("\n")
+                ));
+            }
+            info.since_checks = Some(checks);
+        }
+        if args.simulate_vm_cycles {
+            let mut mock_tx = MockTransaction {
+                mock_info: MockInfo::default(),
+                tx: tx.data(),
+            };
+            let loader = Loader {
+                rpc_client: self.rpc_client,
+            };
+            let cycles = MockTransactionHelper::new(&mut mock_tx)
+                .verify(u64::max_value(), loader)
+                .map_err(|err| format!("Simulate transaction scripts failed: {}", err))?;
+            info.cycles = Some(cycles);
+        }
+        if no_broadcast {
+            return Ok((tx, info));
+        }
+        if args.protect_inputs {
+            self.check_inputs_not_in_pool(&tx)?;
+        }
+
+        let tx_hash = trace
+            .rpc(
+                "send_transaction",
+                serde_json::json!([json_types::Transaction::from(tx.data())]),
+                self.rpc_client.send_transaction(tx.data()),
+            )
             .map_err(|err| format!("Send transaction error: {}", err))?;
         assert_eq!(tx.hash(), tx_hash.pack());
-        Ok(tx)
+        Ok((tx, info))
     }
 
     pub fn get_capacity(&mut self, lock_hashes: Vec<Byte32>) -> Result<(u64, u64, u64), String> {
+        self.get_capacity_with_cells(lock_hashes, false)
+            .map(|(capacity, _)| capacity)
+    }
+
+    // Classify the live cells of the lock hashes by the reason they can not be transfer inputs
+    fn get_capacity_breakdown(
+        &mut self,
+        lock_hashes: &[Byte32],
+        max_mature_number: u64,
+        allow_cell_data: bool,
+        input_capacity_min: u64,
+    ) -> Result<CapacityBreakdown, String> {
+        self.with_db(|db| {
+            let mut breakdown = CapacityBreakdown::default();
+            let mut terminator = |_idx: usize, info: &LiveCellInfo| {
+                breakdown.total += info.capacity;
+                let bucket = match &info.type_hashes {
+                    Some((code_hash, _)) if code_hash == &DAO_TYPE_HASH => &mut breakdown.dao,
+                    Some(_) => &mut breakdown.with_type,
+                    None if !is_mature(info, max_mature_number) => &mut breakdown.immature,
+                    None if !allow_cell_data && info.data_bytes > 0 => &mut breakdown.with_data,
+                    None if info.capacity < input_capacity_min => &mut breakdown.dust,
+                    None => &mut breakdown.spendable,
+                };
+                *bucket += info.capacity;
+                (false, false)
+            };
+            for lock_hash in lock_hashes {
+                db.get_live_cells_by_lock(lock_hash.clone(), None, &mut terminator);
+            }
+            breakdown
+        })
+    }
+
+    // Get (total, immature, dao) capacity and all the live cells (if with_cells) in one index
+    // database open
+    pub fn get_capacity_with_cells(
+        &mut self,
+        lock_hashes: Vec<Byte32>,
+        with_cells: bool,
+    ) -> Result<((u64, u64, u64), Vec<LiveCell>), String> {
         let max_mature_number = get_max_mature_number(self.rpc_client)?;
         self.with_db(|db| {
             let mut total_capacity = 0;
@@ -441,15 +2200,48 @@ impl<'a> WalletSubCommand<'a> {
                     dao_capacity += info.capacity;
                 }
                 total_capacity += info.capacity;
-                (false, false)
+                (false, with_cells)
             };
+            let mut live_cells = Vec::new();
             for lock_hash in lock_hashes {
-                let _ = db.get_live_cells_by_lock(lock_hash, None, &mut terminator);
+                for info in db.get_live_cells_by_lock(lock_hash, None, &mut terminator) {
+                    let mature = is_mature(&info, max_mature_number);
+                    live_cells.push(LiveCell { info, mature });
+                }
             }
-            (total_capacity, immature_capacity, dao_capacity)
+            (
+                (total_capacity, immature_capacity, dao_capacity),
+                live_cells,
+            )
         })
     }
 
+    // (capacity, redeemable capacity if withdrawn now) of the DAO cells
+    pub fn get_dao_redeemable(&mut self, lock_hashes: &[Byte32]) -> Result<(u64, u64), String> {
+        let dao_cells = self.with_db(|db| {
+            let mut dao_cells = Vec::new();
+            for lock_hash in lock_hashes {
+                let terminator = |_, info: &LiveCellInfo| {
+                    let is_dao = info
+                        .type_hashes
+                        .as_ref()
+                        .filter(|(code_hash, _)| code_hash == &DAO_TYPE_HASH)
+                        .is_some();
+                    (false, is_dao)
+                };
+                dao_cells.extend(db.get_live_cells_by_lock(lock_hash.clone(), None, terminator));
+            }
+            dao_cells
+        })?;
+        let mut capacity = 0;
+        let mut redeemable = 0;
+        for cell in &dao_cells {
+            capacity += cell.capacity;
+            redeemable += calculate_dao_redeemable(self.rpc_client, cell)?;
+        }
+        Ok((capacity, redeemable))
+    }
+
     pub fn get_live_cells<F>(
         &mut self,
         to_number: u64,
@@ -512,6 +2304,329 @@ impl<'a> WalletSubCommand<'a> {
             total,
         ))
     }
+
+    // (total, immature, dao) capacity of the address to compare with (by --compare and
+    // --compare-to-address)
+    fn get_address_capacity(&mut self, input: &str) -> Result<(Address, (u64, u64, u64)), String> {
+        let address: Address = AddressParser::default()
+            .set_network(get_network_type(self.rpc_client)?)
+            .parse(input)?;
+        let lock_hash = Script::from(address.payload()).calc_script_hash();
+        let (capacity, _) = self.get_capacity_with_cells(vec![lock_hash], false)?;
+        Ok((address, capacity))
+    }
+
+    // Lock hashes of the derived addresses of the account (by --derived, --gap-limit and
+    // --derive-*-address-length)
+    fn get_derived_lock_hashes(
+        &mut self,
+        m: &ArgMatches,
+        lock_arg: &H160,
+        password: &str,
+    ) -> Result<Vec<Byte32>, String> {
+        let gap_limit_opt: Option<u32> =
+            FromStrParser::<u32>::default().from_matches_opt(m, "gap-limit", false)?;
+        if let Some(gap_limit) = gap_limit_opt {
+            let master_privkey = self
+                .key_store
+                .export_key(lock_arg, password.as_bytes())
+                .map_err(|err| err.to_string())?;
+            return self.scan_derived_lock_hashes(&master_privkey, gap_limit);
+        }
+        let receiving_address_length: u32 =
+            FromStrParser::<u32>::default().from_matches(m, "derive-receiving-address-length")?;
+        let change_address_length: u32 =
+            FromStrParser::<u32>::default().from_matches(m, "derive-change-address-length")?;
+        let key_set = self
+            .key_store
+            .derived_key_set_by_index_with_password(
+                lock_arg,
+                password.as_bytes(),
+                0,
+                receiving_address_length,
+                0,
+                change_address_length,
+            )
+            .map_err(|err| err.to_string())?;
+        Ok(key_set
+            .external
+            .iter()
+            .chain(key_set.change.iter())
+            .map(|(_, hash160)| {
+                let payload = AddressPayload::from_pubkey_hash(hash160.clone());
+                Script::from(&payload).calc_script_hash()
+            })
+            .collect())
+    }
+
+    // Derive receiving/change addresses one by one, stop a chain after `gap_limit`
+    // consecutive addresses have no capacity (see: BIP-44 address gap limit)
+    fn scan_derived_lock_hashes(
+        &mut self,
+        master_privkey: &MasterPrivKey,
+        gap_limit: u32,
+    ) -> Result<Vec<Byte32>, String> {
+        self.with_db(|db| {
+            let mut lock_hashes = Vec::new();
+            // 0: receiving (external) chain, 1: change chain
+            for chain in 0..2 {
+                let mut gap = 0;
+                let mut index = 0;
+                while gap < gap_limit {
+                    let path_string = format!("m/44'/309'/0'/{}/{}", chain, index);
+                    let path = DerivationPath::from_str(path_string.as_str()).unwrap();
+                    let payload = AddressPayload::from_pubkey_hash(master_privkey.hash160(&path));
+                    let lock_hash = Script::from(&payload).calc_script_hash();
+                    if db.get_capacity(lock_hash.clone()).unwrap_or(0) > 0 {
+                        lock_hashes.push(lock_hash);
+                        gap = 0;
+                    } else {
+                        gap += 1;
+                    }
+                    index += 1;
+                }
+            }
+            lock_hashes
+        })
+    }
+
+    // Get (block timestamp, tip number) of the block, cached by block number
+    fn get_tx_meta(
+        &mut self,
+        cache: &mut HashMap<u64, (u64, u64)>,
+        tip_number: u64,
+        number: u64,
+    ) -> Result<(u64, u64), String> {
+        if let Some(meta) = cache.get(&number) {
+            return Ok(*meta);
+        }
+        let header = self
+            .rpc_client
+            .get_header_by_number(number)?
+            .ok_or_else(|| format!("Block header not found: {}", number))?;
+        let meta = (header.inner.timestamp.0, tip_number);
+        cache.insert(number, meta);
+        Ok(meta)
+    }
+
+    // Get the timestamp (milliseconds) of the block, cached by block number
+    fn get_block_timestamp(
+        &mut self,
+        cache: &mut HashMap<u64, u64>,
+        number: u64,
+    ) -> Result<u64, String> {
+        if let Some(timestamp) = cache.get(&number) {
+            return Ok(*timestamp);
+        }
+        let header = self
+            .rpc_client
+            .get_header_by_number(number)?
+            .ok_or_else(|| format!("Block header not found: {}", number))?;
+        cache.insert(number, header.inner.timestamp.0);
+        Ok(header.inner.timestamp.0)
+    }
+
+    // Check the since of each input against the tip, relative since counts from the block the
+    // input cell is committed in
+    fn simulate_since_validation(
+        &mut self,
+        tx: &TransactionView,
+    ) -> Result<Vec<(usize, bool, String)>, String> {
+        let tip = self.rpc_client.get_tip_header()?;
+        // Timestamp since is in seconds
+        let median_time = self.rpc_client.get_blockchain_info()?.median_time.0 / 1000;
+        let mut checks = Vec::new();
+        for (index, input) in tx.inputs().into_iter().enumerate() {
+            let raw_since: u64 = input.since().unpack();
+            if raw_since == 0 {
+                continue;
+            }
+            let since = Since::from_raw_value(raw_since);
+            let (since_type, value) = match since.extract_metric() {
+                Some(metric) if since.flags_is_valid() => metric,
+                _ => {
+                    return Err(format!(
+                        "Invalid since of input #{}: {:#x}",
+                        index, raw_since
+                    ))
+                }
+            };
+            let base = if since.is_absolute() {
+                None
+            } else {
+                let tx_hash: H256 = input.previous_output().tx_hash().unpack();
+                let block_hash = self
+                    .rpc_client
+                    .get_transaction(tx_hash.clone())?
+                    .and_then(|tx| tx.tx_status.block_hash)
+                    .ok_or_else(|| {
+                        format!(
+                            "The transaction of input #{} is not committed: {:#x}",
+                            index, tx_hash
+                        )
+                    })?;
+                let header = self
+                    .rpc_client
+                    .get_header(block_hash.clone())?
+                    .ok_or_else(|| format!("Block header not found: {:#x}", block_hash))?;
+                Some(header)
+            };
+            let (satisfied, result) = match since_type {
+                SinceType::EpochNumberWithFraction => {
+                    let current =
+                        epoch_fraction(EpochNumberWithFraction::from_full_value(tip.inner.epoch.0));
+                    let mut required =
+                        epoch_fraction(EpochNumberWithFraction::from_full_value(value));
+                    if let Some(header) = base.as_ref() {
+                        let start = epoch_fraction(EpochNumberWithFraction::from_full_value(
+                            header.inner.epoch.0,
+                        ));
+                        required = add_epoch_fractions(start, required);
+                    }
+                    compare_epoch_since(current, required)
+                }
+                SinceType::BlockNumber => {
+                    let required = value + base.map(|header| header.inner.number.0).unwrap_or(0);
+                    compare_since(tip.inner.number.0, required, "blocks")
+                }
+                SinceType::Timestamp => {
+                    // The median time of the committed block is approximated by its timestamp
+                    let required = value
+                        + base
+                            .map(|header| header.inner.timestamp.0 / 1000)
+                            .unwrap_or(0);
+                    compare_since(median_time, required, "seconds")
+                }
+            };
+            checks.push((index, satisfied, result));
+        }
+        Ok(checks)
+    }
+
+    // Fail with the hash of the pending (or proposed) transaction spending any input of the tx
+    fn check_inputs_not_in_pool(&mut self, tx: &TransactionView) -> Result<(), String> {
+        let inputs: HashSet<OutPoint> = tx
+            .inputs()
+            .into_iter()
+            .map(|input| input.previous_output())
+            .collect();
+        let pool = self
+            .rpc_client
+            .get_raw_tx_pool()
+            .map_err(|err| format!("Get raw tx-pool failed: {}", err))?;
+        for tx_hash in pool.pending.into_iter().chain(pool.proposed) {
+            // The transaction may be committed or dropped after get_raw_tx_pool
+            let pool_tx = match self.rpc_client.get_transaction(tx_hash.clone())? {
+                Some(pool_tx) => pool_tx,
+                None => continue,
+            };
+            for input in pool_tx.transaction.inner.inputs {
+                let out_point = OutPoint::from(input.previous_output);
+                if inputs.contains(&out_point) {
+                    let input_tx_hash: H256 = out_point.tx_hash().unpack();
+                    let input_index: u32 = out_point.index().unpack();
+                    return Err(format!(
+                        "Input {:#x}-{} is already spent by transaction {:#x} in the tx-pool",
+                        input_tx_hash, input_index, tx_hash
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Get the epoch of the block, cached by block number
+    fn get_block_epoch(
+        &mut self,
+        cache: &mut HashMap<u64, EpochNumberWithFraction>,
+        number: u64,
+    ) -> Result<EpochNumberWithFraction, String> {
+        if let Some(epoch) = cache.get(&number) {
+            return Ok(*epoch);
+        }
+        let header = self
+            .rpc_client
+            .get_header_by_number(number)?
+            .ok_or_else(|| format!("Block header not found: {}", number))?;
+        let epoch = EpochNumberWithFraction::from_full_value(header.inner.epoch.0);
+        cache.insert(number, epoch);
+        Ok(epoch)
+    }
+
+    // Lock script of each lock hash, from the index database or the cell output (by RPC) if the
+    // index does not keep it
+    fn resolve_lock_scripts(
+        &mut self,
+        live_cells: &[LiveCell],
+    ) -> Result<HashMap<H256, Script>, String> {
+        let lock_hashes: HashSet<H256> = live_cells
+            .iter()
+            .map(|cell| cell.info.lock_hash.clone())
+            .collect();
+        let mut lock_scripts: HashMap<H256, Script> = self.with_db(|db| {
+            lock_hashes
+                .into_iter()
+                .filter_map(|lock_hash| {
+                    db.get_lock_script_by_hash(lock_hash.pack())
+                        .map(|script| (lock_hash, script))
+                })
+                .collect()
+        })?;
+        let mut live_cell_cache = HashMap::default();
+        for cell in live_cells {
+            if !lock_scripts.contains_key(&cell.info.lock_hash) {
+                let (output, _) = get_live_cell_with_cache(
+                    &mut live_cell_cache,
+                    self.rpc_client,
+                    cell.info.out_point(),
+                    false,
+                )?;
+                lock_scripts.insert(cell.info.lock_hash.clone(), output.lock());
+            }
+        }
+        Ok(lock_scripts)
+    }
+
+    // Parse the cell data as dep_group (OutPointVec), return None if it is not a dep_group
+    fn resolve_dep_group(
+        &mut self,
+        out_point: OutPoint,
+    ) -> Result<Option<Vec<serde_json::Value>>, String> {
+        let (_, data) = get_live_cell(self.rpc_client, out_point, true)?;
+        let out_points = match OutPointVec::from_slice(&data) {
+            Ok(out_points) if !out_points.is_empty() => out_points,
+            _ => return Ok(None),
+        };
+        let mut cells = Vec::with_capacity(out_points.len());
+        for member in out_points.into_iter() {
+            let json_out_point = json_types::OutPoint::from(member.clone());
+            match get_live_cell(self.rpc_client, member, true) {
+                Ok((output, data)) => {
+                    let capacity: u64 = output.capacity().unpack();
+                    let data_hash: H256 = CellOutput::calc_data_hash(&data).unpack();
+                    let type_code_hash: Option<H256> = output
+                        .type_()
+                        .to_opt()
+                        .map(|script| script.code_hash().unpack());
+                    cells.push(serde_json::json!({
+                        "out_point": json_out_point,
+                        "capacity": format!("{:#}", HumanCapacity::from(capacity)),
+                        "data_bytes": data.len(),
+                        "data_hash": format!("{:#x}", data_hash),
+                        "type_code_hash": type_code_hash,
+                        "status": "live",
+                    }));
+                }
+                Err(err) => {
+                    cells.push(serde_json::json!({
+                        "out_point": json_out_point,
+                        "status": err,
+                    }));
+                }
+            }
+        }
+        Ok(Some(cells))
+    }
 }
 
 impl<'a> CliSubCommand for WalletSubCommand<'a> {
@@ -521,17 +2636,165 @@ impl<'a> CliSubCommand for WalletSubCommand<'a> {
         format: OutputFormat,
         color: bool,
         debug: bool,
+    ) -> Result<String, String> {
+        if let ("get-live-cells", Some(m)) = matches.subcommand() {
+            let processed_block = self.index_controller.state().read().processed_block();
+            let cacheable = self.live_cells_cache.is_some()
+                && !m.is_present("no-cache")
+                && !m.is_present("export-json")
+                && !m.is_present("sampling-rate")
+                && !m.is_present("with-tx-meta");
+            if let (true, Some(block)) = (cacheable, processed_block) {
+                let key = format!("{:?} {} {}", format, color, live_cells_cache_key(m));
+                if let Some(output) = self
+                    .live_cells_cache
+                    .as_ref()
+                    .and_then(|cache| cache.get(&key, &block))
+                {
+                    return Ok(output);
+                }
+                let output = self.process_matches(matches, format, color, debug)?;
+                if let Some(cache) = self.live_cells_cache.as_mut() {
+                    cache.insert(key, block, output.clone());
+                }
+                return Ok(output);
+            }
+        }
+        self.process_matches(matches, format, color, debug)
+    }
+}
+
+impl<'a> WalletSubCommand<'a> {
+    fn process_matches(
+        &mut self,
+        matches: &ArgMatches,
+        format: OutputFormat,
+        color: bool,
+        debug: bool,
     ) -> Result<String, String> {
         match matches.subcommand() {
             ("transfer", Some(m)) => {
-                let to_data = get_to_data(m)?;
-                let args = TransferArgs {
+                let json_path_opt: Option<JsonPath> =
+                    JsonPathParser.from_matches_opt(m, "output-json-path", false)?;
+                let idempotency_key = m.value_of("idempotency-key").map(|s| s.to_string());
+                if let Some(key) = idempotency_key.as_ref() {
+                    if let Some(record) = read_tx_history()?
+                        .into_iter()
+                        .find(|record| record.idempotency_key.as_ref() == Some(key))
+                    {
+                        eprintln!(
+                            "[NOTE] transaction with idempotency key {} was already sent, nothing to do",
+                            key
+                        );
+                        return render_json_path(
+                            serde_json::json!(record.tx_hash),
+                            json_path_opt.as_ref(),
+                            format,
+                            color,
+                        );
+                    }
+                }
+                let to_addresses: Vec<&str> = m
+                    .values_of("to-address")
+                    .map(Iterator::collect)
+                    .unwrap_or_default();
+                let capacities: Vec<&str> = m
+                    .values_of("capacity")
+                    .map(Iterator::collect)
+                    .unwrap_or_default();
+                let to_datas: Vec<&str> = m
+                    .values_of("to-data")
+                    .map(Iterator::collect)
+                    .unwrap_or_default();
+                let single_recipient =
+                    to_addresses.len() <= 1 && capacities.len() <= 1 && to_datas.len() <= 1;
+                if !single_recipient {
+                    if to_addresses.len() != capacities.len() {
+                        return Err(format!(
+                            "{} --to-address but {} --capacity, each recipient requires one capacity",
+                            to_addresses.len(),
+                            capacities.len()
+                        ));
+                    }
+                    if !to_datas.is_empty() && to_datas.len() != to_addresses.len() {
+                        return Err(format!(
+                            "{} --to-address but {} --to-data, give --to-data to all recipients or none",
+                            to_addresses.len(),
+                            to_datas.len()
+                        ));
+                    }
+                    if m.is_present("to-data-path") {
+                        return Err("--to-data-path only supports one recipient".to_string());
+                    }
+                }
+                let to_lock = m
+                    .value_of("to-lock-file")
+                    .map(read_script_file)
+                    .transpose()?;
+                let mut recipients = Vec::with_capacity(capacities.len());
+                for (idx, capacity) in capacities.iter().enumerate() {
+                    let mut data = if single_recipient {
+                        get_to_data(m)?
+                    } else {
+                        match to_datas.get(idx) {
+                            Some(input) => HexParser.parse(input)?,
+                            None => Bytes::new(),
+                        }
+                    };
+                    if let Some(algorithm) = m.value_of("output-data-compression") {
+                        if !data.is_empty() {
+                            data = compress_data(&data, algorithm)?;
+                        }
+                    }
+                    recipients.push(Recipient {
+                        address: to_addresses.get(idx).map(|s| s.to_string()),
+                        lock: to_lock.clone(),
+                        capacity: capacity.to_string(),
+                        data,
+                    });
+                }
+                let change_type_code_hash: Option<H256> = FixedHashParser::<H256>::default()
+                    .from_matches_opt(m, "change-type-code-hash", false)?;
+                let change_type = if let Some(code_hash) = change_type_code_hash {
+                    let hash_type = if m.value_of("change-type-hash-type") == Some("data") {
+                        ScriptHashType::Data
+                    } else {
+                        ScriptHashType::Type
+                    };
+                    let args: Bytes = HexParser
+                        .from_matches_opt(m, "change-type-args", false)?
+                        .unwrap_or_default();
+                    Some(
+                        Script::new_builder()
+                            .code_hash(code_hash.pack())
+                            .hash_type(hash_type.into())
+                            .args(args.pack())
+                            .build(),
+                    )
+                } else {
+                    None
+                };
+                let capacity_unit: CapacityUnit =
+                    FromStrParser::<CapacityUnit>::default().from_matches(m, "capacity-unit")?;
+                let mut args = TransferArgs {
                     privkey_path: m.value_of("privkey-path").map(|s| s.to_string()),
-                    from_account: m.value_of("from-account").map(|s| s.to_string()),
+                    from_mnemonic_phrase: m.is_present("from-mnemonic-phrase"),
+                    mnemonic_path: m.value_of("mnemonic-path").map(|s| s.to_string()),
+                    mnemonic_privkey: None,
+                    from_account: m
+                        .value_of("from-account")
+                        .map(|s| s.to_string())
+                        .or(get_default_account(m)?.map(|account| format!("{:#x}", account))),
                     from_locked_address: m.value_of("from-locked-address").map(|s| s.to_string()),
-                    password: None,
-                    capacity: get_arg_value(m, "capacity")?,
-                    tx_fee: get_arg_value(m, "tx-fee")?,
+                    password: m
+                        .value_of("password-file")
+                        .map(read_password_file)
+                        .transpose()?
+                        .map(Arc::new),
+                    recipients,
+                    // Replaced by the estimated fee when --fee-rate
+                    tx_fee: m.value_of("tx-fee").unwrap_or("0").to_string(),
+                    priority_fee: m.value_of("priority-fee").map(|s| s.to_string()),
                     derive_receiving_address_length: Some(get_arg_value(
                         m,
                         "derive-receiving-address-length",
@@ -539,29 +2802,375 @@ impl<'a> CliSubCommand for WalletSubCommand<'a> {
                     derive_change_address: m
                         .value_of("derive-change-address")
                         .map(|s| s.to_string()),
-                    to_address: get_arg_value(m, "to-address")?,
-                    to_data: Some(to_data),
+                    simulate_vm_cycles: m.is_present("simulate-vm-cycles"),
+                    change_data: HexParser.from_matches_opt(m, "change-data", false)?,
+                    change_type,
+                    block_hash: m.value_of("block-hash").map(|s| s.to_string()),
+                    anchor_to_tip: m.is_present("anchor-to-tip"),
+                    parallel_sign: m.is_present("parallel-sign"),
+                    allow_cell_data: m.is_present("allow-cell-data"),
+                    input_capacity_min: m.value_of("input-capacity-min").map(|s| s.to_string()),
+                    auto_adjust_capacity: m.is_present("auto-adjust-capacity"),
+                    fee_estimate_only: m.is_present("fee-estimate-only"),
+                    sign_hash_fn: FromStrParser::<HashFn>::default()
+                        .from_matches(m, "sign-hash-type")?,
+                    verbose_witness: debug && m.is_present("verbose-witness"),
+                    burn_capacity: m.value_of("burn-capacity").map(|s| s.to_string()),
+                    capacity_unit,
+                    dump_sighash: m.is_present("dump-sighash"),
+                    capacity_precheck: m.is_present("capacity-precheck"),
+                    trace_path: m.value_of("trace").map(|s| s.to_string()),
+                    change_threshold_action: FromStrParser::<ChangeThresholdAction>::default()
+                        .from_matches(m, "change-threshold-action")?,
+                    cell_dep_from_type_id: m
+                        .value_of("cell-dep-from-type-id")
+                        .map(|s| s.to_string()),
+                    fee_input: m.value_of("fee-input").map(|s| s.to_string()),
+                    no_broadcast: m.is_present("no-broadcast") || m.is_present("tx-file"),
+                    verify_against_rpc: m.is_present("verify-against-rpc"),
+                    input_capacity_target: m
+                        .value_of("input-capacity-target")
+                        .map(|s| s.to_string()),
+                    split_output: FromStrParser::<u32>::default().from_matches_opt(
+                        m,
+                        "split-output",
+                        false,
+                    )?,
+                    input_from_query: m.value_of("input-from-query").map(|s| s.to_string()),
+                    preview_change_address: m.is_present("preview-change-address"),
+                    data_length_limit: FromStrParser::<usize>::default().from_matches_opt(
+                        m,
+                        "set-data-length-limit",
+                        false,
+                    )?,
+                    dao_aware_selection: m.is_present("dao-aware-selection"),
+                    recipient_index: FromStrParser::<usize>::default().from_matches_opt(
+                        m,
+                        "recipient-index",
+                        false,
+                    )?,
+                    gas_reserve: m.value_of("gas-reserve").map(|s| s.to_string()),
+                    require_lock_hash: m.value_of("require-lock-hash").map(|s| s.to_string()),
+                    extra_from_accounts: m
+                        .values_of("from-account")
+                        .map(|values| values.skip(1).map(|s| s.to_string()).collect())
+                        .unwrap_or_default(),
+                    extra_passwords: Vec::new(),
+                    change_to: m.value_of("change-to").map(|s| s.to_string()),
+                    input_size_hint: FromStrParser::<usize>::default().from_matches_opt(
+                        m,
+                        "input-size-hint",
+                        false,
+                    )?,
+                    tx_comment: m.value_of("tx-comment").map(|s| s.to_string()),
+                    simulate_since_validation: m.is_present("simulate-since-validation"),
+                    dry_run: m.is_present("dry-run"),
+                    protect_inputs: m.is_present("protect-inputs"),
+                };
+                if let Some(burn_capacity) = m.value_of("burn-capacity") {
+                    eprintln!(
+                        "[WARNING] {:#} will be sent to an unspendable lock and destroyed PERMANENTLY!",
+                        HumanCapacity::from(capacity_unit.parse(burn_capacity)?)
+                    );
+                }
+                let validation_mode = FromStrParser::<CapacityValidationMode>::default()
+                    .from_matches(m, "capacity-validation-mode")?;
+                // No clap default value, which would trigger the conflicts of the arg
+                let consolidate_threshold: usize = FromStrParser::<usize>::default()
+                    .from_matches_opt(m, "auto-consolidate-threshold", false)?
+                    .unwrap_or(0);
+                if consolidate_threshold > 0 {
+                    // Shared by the estimate, the consolidation and the transfer
+                    args.read_credentials()?;
+                    self.auto_consolidate(
+                        &mut args,
+                        consolidate_threshold,
+                        m.is_present("yes"),
+                        validation_mode,
+                    )?;
+                }
+                let fee_rate: Option<u64> =
+                    FromStrParser::<u64>::default().from_matches_opt(m, "fee-rate", false)?;
+                let (tx, info) = if let Some(fee_rate) = fee_rate {
+                    self.transfer_with_fee_rate(&mut args, fee_rate, validation_mode)?
+                } else {
+                    self.transfer_with_info(args.clone(), validation_mode)?
+                };
+                if let Some(change_address) = info.change_address {
+                    let resp = serde_json::json!({
+                        "from_address": info.from_address,
+                        "change_address": change_address,
+                        "derived": m.is_present("derive-change-address"),
+                        "with_type_or_data": m.is_present("change-type-code-hash") || m.is_present("change-data"),
+                    });
+                    return render_json_path(resp, json_path_opt.as_ref(), format, color);
+                }
+                let sighashes_value = info.sighashes.as_ref().map(|sighashes| {
+                    sighashes
+                        .iter()
+                        .map(|(lock_arg, idxs, message)| {
+                            serde_json::json!({
+                                "lock_arg": JsonBytes::from_bytes(lock_arg.clone()),
+                                "input_indexes": idxs,
+                                "message": message,
+                            })
+                        })
+                        .collect::<Vec<_>>()
+                });
+                if info.data_cells > 0 {
+                    eprintln!(
+                        "[WARNING] {} input cell(s) with data spent, the data is destroyed",
+                        info.data_cells
+                    );
+                }
+                if info.typed_cells > 0 {
+                    eprintln!(
+                        "[WARNING] {} input cell(s) with type script spent, the type script is not kept in the outputs",
+                        info.typed_cells
+                    );
+                }
+                if info.consumed_cells > 0 {
+                    eprintln!(
+                        "[WARNING] {} input cell(s) already consumed on chain are replaced, the index database may be behind",
+                        info.consumed_cells
+                    );
+                }
+                for (index, capacity) in &info.adjusted_capacities {
+                    eprintln!(
+                        "[NOTE] capacity of recipient #{} is adjusted to the minimum: {:#}",
+                        index,
+                        HumanCapacity::from(*capacity)
+                    );
+                }
+                if let Some(capacity) = info.bumped_capacity {
+                    eprintln!(
+                        "[NOTE] change {:#} is below the minimum cell capacity, added to the to-address output",
+                        HumanCapacity::from(capacity)
+                    );
+                }
+                if let Some(checks) = info.since_checks.as_ref() {
+                    for (index, _, result) in checks {
+                        eprintln!("[SINCE] input #{}: {}", index, result);
+                    }
+                }
+                if let Some(estimate) = info.fee_estimate {
+                    let mut resp = serde_json::json!({
+                        "inputs": estimate.inputs,
+                        "tx_size": estimate.tx_size,
+                        "min_fee": format!("{:#}", HumanCapacity::from(estimate.min_fee)),
+                        "tx_fee": format!("{:#}", HumanCapacity::from(estimate.tx_fee)),
+                        "change_capacity": format!("{:#}", HumanCapacity::from(estimate.change_capacity)),
+                    });
+                    if let Some(sighashes) = sighashes_value {
+                        resp["sighashes"] = serde_json::json!(sighashes);
+                    }
+                    return render_json_path(resp, json_path_opt.as_ref(), format, color);
+                }
+                if let Some(tx_file) = m.value_of("tx-file") {
+                    let tx_hash: H256 = tx.hash().unpack();
+                    let content =
+                        serde_json::to_string_pretty(&ckb_jsonrpc_types::TransactionView::from(tx))
+                            .map_err(|err| err.to_string())?;
+                    fs::write(tx_file, content).map_err(|err| err.to_string())?;
+                    let resp = serde_json::json!({
+                        "tx_hash": tx_hash,
+                        "tx_file": tx_file,
+                    });
+                    return render_json_path(resp, json_path_opt.as_ref(), format, color);
+                }
+                if m.is_present("dry-run") {
+                    let resp = serde_json::to_value(ckb_jsonrpc_types::TransactionView::from(tx))
+                        .map_err(|err| err.to_string())?;
+                    return render_json_path(resp, json_path_opt.as_ref(), format, color);
+                }
+                if m.is_present("no-broadcast") {
+                    let tx_hash: H256 = tx.hash().unpack();
+                    let tx_hex = format!("0x{}", hex_string(tx.data().as_slice()).unwrap());
+                    let output_opt: Option<PathBuf> =
+                        FilePathParser::new(false).from_matches_opt(m, "output-file", false)?;
+                    let mut resp = serde_json::json!({
+                        "tx_hash": tx_hash,
+                        "transaction": json_types::Transaction::from(tx.data()),
+                    });
+                    if let Some(output) = output_opt {
+                        fs::write(&output, &tx_hex).map_err(|err| err.to_string())?;
+                        resp["output_file"] = serde_json::json!(output);
+                    } else {
+                        resp["tx_hex"] = serde_json::json!(tx_hex);
+                    }
+                    if let Some(sighashes) = sighashes_value {
+                        resp["sighashes"] = serde_json::json!(sighashes);
+                    }
+                    return render_json_path(resp, json_path_opt.as_ref(), format, color);
+                }
+                // Every to-output: address (or the lock file) and capacity after auto-adjust
+                let mut to_outputs = args
+                    .recipients
+                    .iter()
+                    .enumerate()
+                    .map(|(idx, recipient)| {
+                        let to = recipient
+                            .address
+                            .clone()
+                            .or_else(|| m.value_of("to-lock-file").map(|s| s.to_string()))
+                            .unwrap_or_default();
+                        let capacity = match info
+                            .adjusted_capacities
+                            .iter()
+                            .find(|(index, _)| *index == idx)
+                        {
+                            Some((_, capacity)) => *capacity,
+                            None => capacity_unit.parse(&recipient.capacity)?,
+                        };
+                        Ok((to, capacity))
+                    })
+                    .collect::<Result<Vec<(String, u64)>, String>>()?;
+                if let Some(capacity) = info.bumped_capacity {
+                    to_outputs[0].1 += capacity;
+                }
+                let mut record = TxHistoryRecord {
+                    tx_hash: tx.hash().unpack(),
+                    timestamp: SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .map(|duration| duration.as_secs())
+                        .unwrap_or(0),
+                    to: to_outputs[0].0.clone(),
+                    capacity: HumanCapacity::from(to_outputs[0].1).to_string(),
+                    extra_recipients: to_outputs[1..]
+                        .iter()
+                        .map(|(to, capacity)| TxHistoryRecipient {
+                            to: to.clone(),
+                            capacity: HumanCapacity::from(*capacity).to_string(),
+                        })
+                        .collect(),
+                    memo: m.value_of("memo").map(|s| s.to_string()),
+                    note: m.value_of("note").map(|s| s.to_string()),
+                    idempotency_key,
+                    inputs: None,
+                    outputs: None,
+                };
+                if m.is_present("track") {
+                    record.inputs = Some(
+                        tx.inputs()
+                            .into_iter()
+                            .map(|input| json_types::OutPoint::from(input.previous_output()))
+                            .collect(),
+                    );
+                    record.outputs = Some(
+                        tx.outputs_with_data_iter()
+                            .map(|(output, data)| TxHistoryOutput {
+                                output: output.into(),
+                                data: JsonBytes::from_bytes(data),
+                            })
+                            .collect(),
+                    );
+                }
+                if let Err(err) = append_tx_history(&record) {
+                    eprintln!("[WARNING] save tx-history failed: {}", err);
+                }
+                if let Some(hook) = m.value_of("post-broadcast-hook") {
+                    let envs = vec![
+                        ("CKB_TX_HASH", format!("{:#x}", record.tx_hash)),
+                        ("CKB_FROM_ADDRESS", info.from_address.clone()),
+                        (
+                            "CKB_TO_ADDRESS",
+                            to_outputs
+                                .iter()
+                                .map(|(to, _)| to.as_str())
+                                .collect::<Vec<_>>()
+                                .join(","),
+                        ),
+                        (
+                            "CKB_CAPACITY",
+                            to_outputs
+                                .iter()
+                                .map(|(_, capacity)| capacity.to_string())
+                                .collect::<Vec<_>>()
+                                .join(","),
+                        ),
+                        ("CKB_FEE", capacity_unit.parse(&args.tx_fee)?.to_string()),
+                    ];
+                    if let Err(err) = run_post_broadcast_hook(hook, envs) {
+                        if m.is_present("fail-on-hook-error") {
+                            return Err(err);
+                        }
+                        eprintln!("[WARNING] {}", err);
+                    }
+                }
+                // The fee finally paid by --fee-rate and the size of the signed transaction
+                let fee_rate_value = match fee_rate {
+                    Some(fee_rate) if debug => Some(serde_json::json!({
+                        "fee_rate": fee_rate,
+                        "tx_fee": format!("{:#}", HumanCapacity::from(capacity_unit.parse(&args.tx_fee)?)),
+                        "tx_size": tx.data().as_slice().len(),
+                    })),
+                    _ => None,
                 };
-                let tx = self.transfer(args, false)?;
-                if debug {
-                    Ok(ckb_jsonrpc_types::TransactionView::from(tx).render(format, color))
+                let resp = if debug {
+                    serde_json::to_value(ckb_jsonrpc_types::TransactionView::from(tx))
+                        .map_err(|err| err.to_string())?
                 } else {
                     let tx_hash: H256 = tx.hash().unpack();
-                    Ok(tx_hash.render(format, color))
+                    serde_json::json!(tx_hash)
+                };
+                let mut extra = serde_json::Map::default();
+                if let Some(fee_rate_value) = fee_rate_value {
+                    extra.insert("fee_rate".to_string(), fee_rate_value);
+                }
+                if let Some(sighashes) = sighashes_value {
+                    extra.insert("sighashes".to_string(), serde_json::json!(sighashes));
+                }
+                if let Some(witnesses) = info.witnesses {
+                    let witnesses = witnesses
+                        .into_iter()
+                        .map(|witness| {
+                            serde_json::json!({
+                                "input_index": witness.input_index,
+                                "lock_arg": JsonBytes::from_bytes(witness.lock_arg),
+                                "signature": witness.signature.map(JsonBytes::from_bytes),
+                            })
+                        })
+                        .collect::<Vec<_>>();
+                    extra.insert("witnesses".to_string(), serde_json::json!(witnesses));
+                }
+                if let Some(cycles) = info.cycles {
+                    extra.insert("cycles".to_string(), serde_json::json!(cycles));
+                }
+                if let Some((target, selected)) = info.input_capacity_target {
+                    extra.insert(
+                        "input_capacity_target".to_string(),
+                        serde_json::json!({
+                            "target": format!("{:#}", HumanCapacity::from(target)),
+                            "selected": format!("{:#}", HumanCapacity::from(selected)),
+                            "distance": format!("{:#}", HumanCapacity::from(target - selected)),
+                        }),
+                    );
                 }
+                if m.is_present("input-capacity-min") {
+                    extra.insert(
+                        "skipped_cells".to_string(),
+                        serde_json::json!(info.skipped_cells),
+                    );
+                }
+                let resp = if extra.is_empty() {
+                    resp
+                } else {
+                    extra.insert("transaction".to_string(), resp);
+                    serde_json::Value::Object(extra)
+                };
+                render_json_path(resp, json_path_opt.as_ref(), format, color)
             }
             ("get-capacity", Some(m)) => {
                 let lock_hash_opt: Option<H256> =
                     FixedHashParser::<H256>::default().from_matches_opt(m, "lock-hash", false)?;
-                let lock_hashes = if let Some(lock_hash) = lock_hash_opt {
+                let mut address_opt = None;
+                let all_accounts = m.is_present("all-accounts");
+                let lock_hashes = if all_accounts {
+                    Vec::new()
+                } else if let Some(lock_hash) = lock_hash_opt.as_ref() {
                     vec![lock_hash.pack()]
                 } else {
                     let network_type = get_network_type(self.rpc_client)?;
-
-                    let receiving_address_length: u32 = FromStrParser::<u32>::default()
-                        .from_matches(m, "derive-receiving-address-length")?;
-                    let change_address_length: u32 = FromStrParser::<u32>::default()
-                        .from_matches(m, "derive-change-address-length")?;
                     let address_payload = if let Some(address_str) = m.value_of("address") {
                         AddressParser::default()
                             .set_network(network_type)
@@ -571,41 +3180,202 @@ impl<'a> CliSubCommand for WalletSubCommand<'a> {
                     } else {
                         get_address(Some(network_type), m)?
                     };
+                    address_opt = Some(Address::new(network_type, address_payload.clone()));
                     let mut lock_hashes = vec![Script::from(&address_payload).calc_script_hash()];
                     if m.is_present("derived") {
-                        let password = read_password(false, None)?;
+                        let password = get_password(m, None)?;
                         let lock_arg = H160::from_slice(address_payload.args().as_ref()).unwrap();
-                        let key_set = self
-                            .key_store
-                            .derived_key_set_by_index_with_password(
-                                &lock_arg,
-                                password.as_bytes(),
-                                0,
-                                receiving_address_length,
-                                0,
-                                change_address_length,
-                            )
-                            .map_err(|err| err.to_string())?;
-                        for (_, hash160) in key_set.external.iter().chain(key_set.change.iter()) {
-                            let payload = AddressPayload::from_pubkey_hash(hash160.clone());
-                            lock_hashes.push(Script::from(&payload).calc_script_hash());
-                        }
+                        lock_hashes.extend(self.get_derived_lock_hashes(m, &lock_arg, &password)?);
                     }
                     lock_hashes
                 };
 
-                let (total, immature, dao) = self.get_capacity(lock_hashes)?;
-
-                let mut resp =
-                    serde_json::json!({ "total": format!("{:#}", HumanCapacity::from(total)) });
-                if immature > 0 {
-                    resp["immature"] =
-                        serde_json::json!(format!("{:#}", HumanCapacity::from(immature)));
+                if m.is_present("wait-synced") {
+                    let max_lag: u64 =
+                        FromStrParser::<u64>::default().from_matches(m, "max-lag")?;
+                    let timeout: u64 =
+                        FromStrParser::<u64>::default().from_matches(m, "wait-timeout")?;
+                    if let Some(lag) = wait_index_synced(
+                        &self.index_controller,
+                        self.rpc_client,
+                        max_lag,
+                        Duration::from_secs(timeout),
+                    )? {
+                        eprintln!(
+                            "[WARNING] index is still {} blocks behind the tip after {}s, the capacity may be stale",
+                            lag, timeout
+                        );
+                    }
                 }
-                if dao > 0 {
+                let ((total, immature, dao), live_cells) =
+                    self.get_capacity_with_cells(lock_hashes.clone(), m.is_present("with-cells"))?;
+                let format = m
+                    .value_of("format")
+                    .map(OutputFormat::from_str)
+                    .transpose()?
+                    .unwrap_or(format);
+                if format == OutputFormat::Prometheus {
+                    let label = if let Some(address) = address_opt {
+                        ("address", address.to_string())
+                    } else {
+                        ("lock_hash", format!("{:#x}", lock_hash_opt.unwrap()))
+                    };
+                    let metrics = CapacityMetrics { label, total, dao };
+                    return Ok(metrics.render(format, color));
+                }
+
+                let usd_price = if m.value_of("denominate") == Some("usd") {
+                    let url = UrlParser.from_matches(m, "price-oracle-url")?;
+                    Some(fetch_ckb_usd_price(&url)?)
+                } else {
+                    None
+                };
+                let shannon = m.value_of("denominate") == Some("shannon");
+                let denominate = |value: u64| {
+                    if let Some(price) = usd_price {
+                        let usd = value as f64 / ONE_CKB as f64 * price;
+                        serde_json::json!(format!("{:.4} (USD)", usd))
+                    } else if shannon {
+                        serde_json::json!(value)
+                    } else {
+                        serde_json::json!(format!("{:#}", HumanCapacity::from(value)))
+                    }
+                };
+                let denominate_delta = |delta: i128| {
+                    if let Some(price) = usd_price {
+                        let usd = delta as f64 / ONE_CKB as f64 * price;
+                        serde_json::json!(format!("{:.4} (USD)", usd))
+                    } else if shannon {
+                        serde_json::json!(delta as i64)
+                    } else {
+                        let sign = if delta < 0 { "-" } else { "" };
+                        serde_json::json!(format!(
+                            "{}{:#}",
+                            sign,
+                            HumanCapacity::from(delta.abs() as u64)
+                        ))
+                    }
+                };
+                if let Some(compare_address) = m.value_of("compare") {
+                    let (compare_address, (compare_total, compare_immature, compare_dao)) =
+                        self.get_address_capacity(compare_address)?;
+                    let diff = |current: u64, compare: u64| {
+                        serde_json::json!({
+                            "current": denominate(current),
+                            "compare": denominate(compare),
+                            "delta": denominate_delta(compare as i128 - current as i128),
+                        })
+                    };
+                    let mut resp = serde_json::json!({
+                        "compare_address": compare_address.to_string(),
+                        "total": diff(total, compare_total),
+                        "immature": diff(immature, compare_immature),
+                        "dao": diff(dao, compare_dao),
+                        "free": diff(total - dao, compare_total - compare_dao),
+                    });
+                    if let Some(address) = address_opt {
+                        resp["address"] = serde_json::json!(address.to_string());
+                    }
+                    if let Some(price) = usd_price {
+                        resp["usd_price"] = serde_json::json!(price);
+                    }
+                    return Ok(resp.render(format, color));
+                }
+                if let Some(secondary_address) = m.value_of("compare-to-address") {
+                    let (secondary_address, (secondary_total, _, _)) =
+                        self.get_address_capacity(secondary_address)?;
+                    // Ratio is null if the secondary address is empty
+                    let ratio = if secondary_total > 0 {
+                        Some(total as f64 / secondary_total as f64)
+                    } else {
+                        None
+                    };
+                    let mut resp = serde_json::json!({
+                        "secondary_address": secondary_address.to_string(),
+                        "primary_capacity": denominate(total),
+                        "secondary_capacity": denominate(secondary_total),
+                        "ratio": ratio,
+                        "difference": denominate_delta(total as i128 - secondary_total as i128),
+                    });
+                    if let Some(address) = address_opt {
+                        resp["primary_address"] = serde_json::json!(address.to_string());
+                    }
+                    if let Some(price) = usd_price {
+                        resp["usd_price"] = serde_json::json!(price);
+                    }
+                    return Ok(resp.render(format, color));
+                }
+                if all_accounts {
+                    let mut lock_args = self
+                        .key_store
+                        .get_accounts()
+                        .keys()
+                        .cloned()
+                        .collect::<Vec<_>>();
+                    lock_args.sort();
+                    let mut accounts = serde_json::Map::new();
+                    let (mut grand_total, mut grand_immature, mut grand_dao) = (0, 0, 0);
+                    for lock_arg in lock_args {
+                        let payload = AddressPayload::from_pubkey_hash(lock_arg.clone());
+                        let mut lock_hashes = vec![Script::from(&payload).calc_script_hash()];
+                        if m.is_present("derived") {
+                            let prompt = format!("Password of {:#x}", lock_arg);
+                            let password = get_password(m, Some(&prompt))?;
+                            lock_hashes
+                                .extend(self.get_derived_lock_hashes(m, &lock_arg, &password)?);
+                        }
+                        let ((total, immature, dao), _) =
+                            self.get_capacity_with_cells(lock_hashes, false)?;
+                        grand_total += total;
+                        grand_immature += immature;
+                        grand_dao += dao;
+                        accounts.insert(
+                            format!("{:#x}", lock_arg),
+                            serde_json::json!({
+                                "total": denominate(total),
+                                "immature": denominate(immature),
+                                "dao": denominate(dao),
+                                "free": denominate(total - dao),
+                            }),
+                        );
+                    }
+                    let mut resp = serde_json::json!({
+                        "accounts": accounts,
+                        "grand_total": {
+                            "total": denominate(grand_total),
+                            "immature": denominate(grand_immature),
+                            "dao": denominate(grand_dao),
+                            "free": denominate(grand_total - grand_dao),
+                        },
+                    });
+                    if let Some(price) = usd_price {
+                        resp["usd_price"] = serde_json::json!(price);
+                    }
+                    return Ok(resp.render(format, color));
+                }
+                let mut resp = serde_json::json!({ "total": denominate(total) });
+                if immature > 0 {
+                    resp["immature"] = denominate(immature);
+                }
+                if dao > 0 {
                     let free = total - dao;
-                    resp["dao"] = serde_json::json!(format!("{:#}", HumanCapacity::from(dao)));
-                    resp["free"] = serde_json::json!(format!("{:#}", HumanCapacity::from(free)));
+                    resp["dao"] = denominate(dao);
+                    resp["free"] = denominate(free);
+                }
+                if m.is_present("dao-aware") {
+                    let (dao_capacity, redeemable) = self.get_dao_redeemable(&lock_hashes)?;
+                    resp["dao_redeemable"] = denominate(redeemable);
+                    resp["dao_compensation"] = denominate(redeemable - dao_capacity);
+                    resp["total_worth"] = denominate(total - dao_capacity + redeemable);
+                }
+                if let Some(price) = usd_price {
+                    resp["usd_price"] = serde_json::json!(price);
+                }
+                if m.is_present("with-cells") {
+                    resp["live_cells"] = live_cells
+                        .iter()
+                        .map(|live_cell| live_cell_value(&live_cell.info, live_cell.mature))
+                        .collect();
                 }
                 Ok(resp.render(format, color))
             }
@@ -616,12 +3386,31 @@ impl<'a> CliSubCommand for WalletSubCommand<'a> {
                     FixedHashParser::<H256>::default().from_matches_opt(m, "type-hash", false)?;
                 let code_hash_opt: Option<H256> =
                     FixedHashParser::<H256>::default().from_matches_opt(m, "code-hash", false)?;
+                let created_tx_hash_opt: Option<H256> = FixedHashParser::<H256>::default()
+                    .from_matches_opt(m, "by-created-in-tx", false)?;
                 let limit: usize = FromStrParser::<usize>::default().from_matches(m, "limit")?;
                 let from_number_opt: Option<u64> =
                     FromStrParser::<u64>::default().from_matches_opt(m, "from", false)?;
                 let to_number_opt: Option<u64> =
                     FromStrParser::<u64>::default().from_matches_opt(m, "to", false)?;
                 let fast_mode = m.is_present("fast-mode");
+                let indexer_compatible = m.value_of("output") == Some("ckb-indexer-compatible");
+                let follow_dep_group = m.is_present("follow-dep-group");
+                let resolve_dep_groups = m.is_present("resolve-dep-groups");
+                let with_tx_meta = m.is_present("with-tx-meta");
+                let with_epoch = m.is_present("with-epoch");
+                let out_points_only = m.is_present("out-points-only");
+                let include_consumed = m.is_present("include-consumed");
+                let cellbase_opt = if m.is_present("cellbase-only") {
+                    Some(true)
+                } else if m.is_present("no-cellbase") {
+                    Some(false)
+                } else {
+                    None
+                };
+                let cellbase_match = |info: &LiveCellInfo| {
+                    cellbase_opt.map_or(true, |cellbase| is_cellbase(info) == cellbase)
+                };
 
                 let network_type = get_network_type(self.rpc_client)?;
                 let lock_hash_opt = if lock_hash_opt.is_none() {
@@ -634,13 +3423,304 @@ impl<'a> CliSubCommand for WalletSubCommand<'a> {
                     lock_hash_opt
                 };
 
-                if lock_hash_opt.is_none() && type_hash_opt.is_none() && code_hash_opt.is_none() {
+                if lock_hash_opt.is_none()
+                    && type_hash_opt.is_none()
+                    && code_hash_opt.is_none()
+                    && created_tx_hash_opt.is_none()
+                {
                     return Err(
-                        "lock-hash or type-hash or code-hash or address is required".to_owned()
+                        "lock-hash or type-hash or code-hash or address or by-created-in-tx is required".to_owned()
                     );
                 }
 
                 let to_number = to_number_opt.unwrap_or(std::u64::MAX);
+                let query_cells = |db: &IndexDatabase,
+                                   terminator: &mut dyn FnMut(
+                    usize,
+                    &LiveCellInfo,
+                ) -> (bool, bool)| {
+                    if let Some(tx_hash) = &created_tx_hash_opt {
+                        db.get_live_cells_by_tx(tx_hash.clone().pack(), terminator)
+                    } else if let Some(lock_hash) = &lock_hash_opt {
+                        db.get_live_cells_by_lock(
+                            lock_hash.clone().pack(),
+                            from_number_opt,
+                            terminator,
+                        )
+                    } else if let Some(type_hash) = &type_hash_opt {
+                        db.get_live_cells_by_type(
+                            type_hash.clone().pack(),
+                            from_number_opt,
+                            terminator,
+                        )
+                    } else {
+                        db.get_live_cells_by_code(
+                            code_hash_opt.clone().unwrap().pack(),
+                            from_number_opt,
+                            terminator,
+                        )
+                    }
+                };
+                if m.is_present("capacity-sum-only") {
+                    let mut total_capacity = 0u64;
+                    let mut cell_count = 0u64;
+                    self.with_db(|db| {
+                        let mut terminator = |_, info: &LiveCellInfo| {
+                            if info.number <= to_number && cellbase_match(info) {
+                                total_capacity += info.capacity;
+                                cell_count += 1;
+                            }
+                            (false, false)
+                        };
+                        query_cells(&db, &mut terminator);
+                    })?;
+                    let resp = serde_json::json!({
+                        "total_capacity_shannons": total_capacity,
+                        "cell_count": cell_count,
+                    });
+                    return Ok(resp.render(format, color));
+                }
+                let type_id_only = m.is_present("output-type-id-only");
+                let summary_only = m.is_present("output-summary-only");
+                if m.is_present("capacity-stats") || summary_only {
+                    let boundaries =
+                        parse_histogram_buckets(m.value_of("histogram-buckets").unwrap())?;
+                    let dust_threshold: u64 = CapacityParser
+                        .from_matches_opt::<HumanCapacity>(m, "dust-threshold", false)?
+                        .map(u64::from)
+                        .unwrap_or(boundaries[0]);
+                    // (count, capacity) of each bucket, the last one is above all boundaries
+                    let mut buckets = vec![(0u64, 0u64); boundaries.len() + 1];
+                    let mut dust = (0u64, 0u64);
+                    self.with_db(|db| {
+                        let mut terminator = |_, info: &LiveCellInfo| {
+                            if info.number <= to_number && cellbase_match(info) {
+                                let idx = boundaries
+                                    .iter()
+                                    .position(|boundary| info.capacity < *boundary)
+                                    .unwrap_or(boundaries.len());
+                                buckets[idx].0 += 1;
+                                buckets[idx].1 += info.capacity;
+                                if info.capacity < dust_threshold {
+                                    dust.0 += 1;
+                                    dust.1 += info.capacity;
+                                }
+                            }
+                            (false, false)
+                        };
+                        query_cells(&db, &mut terminator);
+                    })?;
+                    let label = |capacity: u64| {
+                        HumanCapacity::from(capacity)
+                            .to_string()
+                            .trim_end_matches(".0")
+                            .to_string()
+                    };
+                    let range_label = |idx: usize| {
+                        if idx == 0 {
+                            format!("<{}CKB", label(boundaries[0]))
+                        } else if idx == boundaries.len() {
+                            format!(">={}CKB", label(boundaries[idx - 1]))
+                        } else {
+                            format!(
+                                "{}-{}CKB",
+                                label(boundaries[idx - 1]),
+                                label(boundaries[idx])
+                            )
+                        }
+                    };
+                    if summary_only {
+                        let mut summary = buckets
+                            .iter()
+                            .enumerate()
+                            .map(|(idx, (count, capacity))| {
+                                serde_json::json!({
+                                    "range": range_label(idx),
+                                    "count": count,
+                                    "total_capacity": format!("{:#}", HumanCapacity::from(*capacity)),
+                                })
+                            })
+                            .collect::<Vec<_>>();
+                        summary.push(serde_json::json!({
+                            "range": format!("dust (<{}CKB)", label(dust_threshold)),
+                            "count": dust.0,
+                            "total_capacity": format!("{:#}", HumanCapacity::from(dust.1)),
+                        }));
+                        return Ok(serde_json::json!(summary).render(format, color));
+                    }
+                    let buckets_value = buckets
+                        .iter()
+                        .enumerate()
+                        .map(|(idx, (count, capacity))| {
+                            serde_json::json!({
+                                "range": range_label(idx),
+                                "count": count,
+                                "capacity": format!("{:#}", HumanCapacity::from(*capacity)),
+                            })
+                        })
+                        .collect::<Vec<_>>();
+                    let resp = serde_json::json!({
+                        "buckets": buckets_value,
+                        "total_count": buckets.iter().map(|(count, _)| count).sum::<u64>(),
+                        "total_capacity": format!(
+                            "{:#}",
+                            HumanCapacity::from(buckets.iter().map(|(_, capacity)| capacity).sum::<u64>())
+                        ),
+                    });
+                    return Ok(resp.render(format, color));
+                }
+                let export_opt: Option<PathBuf> =
+                    FilePathParser::new(false).from_matches_opt(m, "export-json", false)?;
+                if let Some(export_path) = export_opt {
+                    let checkpoint_path: Option<PathBuf> = FilePathParser::new(false)
+                        .from_matches_opt(m, "checkpoint-resume", false)?;
+                    let resumed = match checkpoint_path.as_ref() {
+                        Some(path) if path.exists() => Some(load_export_checkpoint(path)?),
+                        _ => None,
+                    };
+                    let file = if let Some(checkpoint) = resumed.as_ref() {
+                        // Drop the cells written after the last checkpoint
+                        let file = fs::OpenOptions::new()
+                            .write(true)
+                            .open(&export_path)
+                            .map_err(|err| err.to_string())?;
+                        file.set_len(checkpoint.file_len)
+                            .map_err(|err| err.to_string())?;
+                        file
+                    } else {
+                        fs::File::create(&export_path).map_err(|err| err.to_string())?
+                    };
+                    let mut writer = io::BufWriter::new(file);
+                    writer
+                        .seek(io::SeekFrom::End(0))
+                        .map_err(|err| err.to_string())?;
+                    let mut checkpoint = resumed.clone().unwrap_or_default();
+                    let max_mature_number = get_max_mature_number(self.rpc_client)?;
+                    let mut export_error: Option<String> = None;
+                    self.with_db(|db| {
+                        let mut terminator = |_, info: &LiveCellInfo| {
+                            if info.number > to_number || !cellbase_match(info) {
+                                return (false, false);
+                            }
+                            if let Some(last) = resumed.as_ref() {
+                                let position =
+                                    (info.number, info.index.tx_index, info.index.output_index);
+                                if position <= (last.number, last.tx_index, last.output_index) {
+                                    return (false, false);
+                                }
+                            }
+                            let line = format!(
+                                "{}\n",
+                                live_cell_value(info, is_mature(info, max_mature_number))
+                            );
+                            if let Err(err) = writer.write_all(line.as_bytes()) {
+                                export_error = Some(err.to_string());
+                                return (true, false);
+                            }
+                            checkpoint.count += 1;
+                            checkpoint.file_len += line.len() as u64;
+                            checkpoint.number = info.number;
+                            checkpoint.tx_index = info.index.tx_index;
+                            checkpoint.output_index = info.index.output_index;
+                            if let Some(path) = checkpoint_path.as_ref() {
+                                if checkpoint.count % EXPORT_CHECKPOINT_INTERVAL == 0 {
+                                    let result = writer
+                                        .flush()
+                                        .map_err(|err| err.to_string())
+                                        .and_then(|_| save_export_checkpoint(path, &checkpoint));
+                                    if let Err(err) = result {
+                                        export_error = Some(err);
+                                        return (true, false);
+                                    }
+                                }
+                            }
+                            (false, false)
+                        };
+                        query_cells(&db, &mut terminator);
+                    })?;
+                    if let Some(err) = export_error {
+                        return Err(format!("Export live cells failed: {}", err));
+                    }
+                    writer.flush().map_err(|err| err.to_string())?;
+                    if let Some(path) = checkpoint_path.as_ref().filter(|path| path.exists()) {
+                        fs::remove_file(path).map_err(|err| err.to_string())?;
+                    }
+                    let resp = serde_json::json!({
+                        "export_file": export_path,
+                        "count": checkpoint.count,
+                        "resumed_from": resumed.map(|checkpoint| checkpoint.count),
+                    });
+                    return Ok(resp.render(format, color));
+                }
+                let sampling_rate_opt: Option<f64> =
+                    FromStrParser::<f64>::default().from_matches_opt(m, "sampling-rate", false)?;
+                if let Some(sampling_rate) = sampling_rate_opt {
+                    let seed_opt: Option<u64> = FromStrParser::<u64>::default().from_matches_opt(
+                        m,
+                        "sampling-seed",
+                        false,
+                    )?;
+                    let mut rng = match seed_opt {
+                        Some(seed) => StdRng::seed_from_u64(seed),
+                        None => StdRng::from_entropy(),
+                    };
+                    let (key_prefix, key_start) = if let Some(lock_hash) = lock_hash_opt {
+                        (
+                            IndexKey::LockLiveCellIndexPrefix(lock_hash.clone(), None),
+                            IndexKey::LockLiveCellIndexPrefix(lock_hash, from_number_opt),
+                        )
+                    } else if let Some(type_hash) = type_hash_opt {
+                        (
+                            IndexKey::TypeLiveCellIndexPrefix(type_hash.clone(), None),
+                            IndexKey::TypeLiveCellIndexPrefix(type_hash, from_number_opt),
+                        )
+                    } else {
+                        let code_hash = code_hash_opt.unwrap();
+                        (
+                            IndexKey::CodeLiveCellIndexPrefix(code_hash.clone(), None),
+                            IndexKey::CodeLiveCellIndexPrefix(code_hash, from_number_opt),
+                        )
+                    };
+                    let (total_count, infos) = self.with_db(|db| {
+                        db.sample_live_cell_infos(key_prefix, key_start, to_number, || {
+                            rng.gen_bool(sampling_rate)
+                        })
+                    })?;
+                    // Horvitz-Thompson estimator of the total capacity (poisson sampling)
+                    let sample_capacity: u64 = infos.iter().map(|info| info.capacity).sum();
+                    let estimated = sample_capacity as f64 / sampling_rate;
+                    let variance = infos
+                        .iter()
+                        .map(|info| (info.capacity as f64).powi(2))
+                        .sum::<f64>()
+                        * (1.0 - sampling_rate)
+                        / sampling_rate.powi(2);
+                    let margin = 1.96 * variance.sqrt();
+                    let to_capacity = |value: f64| {
+                        format!("{:#}", HumanCapacity::from(value.max(0.0).round() as u64))
+                    };
+
+                    let max_mature_number = get_max_mature_number(self.rpc_client)?;
+                    let live_cells_value = infos
+                        .iter()
+                        .take(limit)
+                        .map(|info| live_cell_value(info, is_mature(info, max_mature_number)))
+                        .collect::<Vec<_>>();
+                    let resp = serde_json::json!({
+                        "live_cells": live_cells_value,
+                        "sampling_rate": sampling_rate,
+                        "sampling_seed": seed_opt,
+                        "total_count": total_count,
+                        "sample_count": infos.len(),
+                        "sample_capacity": format!("{:#}", HumanCapacity::from(sample_capacity)),
+                        "estimated_total_capacity": to_capacity(estimated),
+                        "total_capacity_95_confidence_interval": [
+                            to_capacity(estimated - margin),
+                            to_capacity(estimated + margin),
+                        ],
+                    });
+                    return Ok(resp.render(format, color));
+                }
                 let (
                     LiveCells {
                         live_cells,
@@ -652,39 +3732,174 @@ impl<'a> CliSubCommand for WalletSubCommand<'a> {
                     to_number,
                     limit,
                     |db, terminator| {
-                        if let Some(lock_hash) = &lock_hash_opt {
-                            db.get_live_cells_by_lock(
-                                lock_hash.clone().pack(),
-                                from_number_opt,
-                                terminator,
-                            )
-                        } else if let Some(type_hash) = &type_hash_opt {
-                            db.get_live_cells_by_type(
-                                type_hash.clone().pack(),
-                                from_number_opt,
-                                terminator,
-                            )
-                        } else {
-                            db.get_live_cells_by_code(
-                                code_hash_opt.clone().unwrap().pack(),
-                                from_number_opt,
-                                terminator,
-                            )
-                        }
+                        // Skip cells not matching cellbase filter, so <limit> counts matched cells
+                        let mut matched_count = 0;
+                        let mut matched_terminator = |_, info: &LiveCellInfo| {
+                            if cellbase_match(info) && (!type_id_only || is_type_id_cell(info)) {
+                                let result = terminator(matched_count, info);
+                                matched_count += 1;
+                                result
+                            } else {
+                                (false, false)
+                            }
+                        };
+                        query_cells(db, &mut matched_terminator)
                     },
                     fast_mode,
                 )?;
-                let mut resp = serde_json::json!({
-                    "live_cells": live_cells.into_iter().map(|live_cell| {
-                        let LiveCell{ info, mature } = live_cell;
-                        let mut value = serde_json::to_value(&info).unwrap();
-                        let mature = serde_json::Value::Bool(mature);
-                        let capacity_string = serde_json::Value::String(format!("{:#}", HumanCapacity::from(info.capacity)));
+                let (live_cells, current_count, current_capacity) = if m.is_present("deduplicate") {
+                    let (live_cells, duplicates) = deduplicate_live_cells(live_cells);
+                    if !duplicates.is_empty() {
+                        eprintln!(
+                            "[WARNING] {} duplicated out point(s) removed, the index database may be corrupted (try rebuilding it)",
+                            duplicates.len()
+                        );
+                    }
+                    let removed_capacity: u64 =
+                        duplicates.iter().map(|cell| cell.info.capacity).sum();
+                    (
+                        live_cells,
+                        current_count - duplicates.len() as u32,
+                        current_capacity - removed_capacity,
+                    )
+                } else {
+                    (live_cells, current_count, current_capacity)
+                };
+                if out_points_only {
+                    let out_points = live_cells
+                        .into_iter()
+                        .map(|LiveCell { info, .. }| {
+                            format!("{:#x}-{}", info.tx_hash, info.tx_index)
+                        })
+                        .collect::<Vec<_>>();
+                    return Ok(serde_json::json!(out_points).render(format, color));
+                }
+                let live_cells_value = if indexer_compatible {
+                    let mut cells = Vec::with_capacity(live_cells.len());
+                    for LiveCell { info, .. } in live_cells {
+                        let (output, mut output_data) =
+                            get_live_cell(self.rpc_client, info.out_point(), true)?;
+                        if let Some(algorithm) = m.value_of("input-data-decompression") {
+                            output_data = decompress_data(&output_data, algorithm)?;
+                        }
+                        cells.push(serde_json::json!({
+                            "block_number": json_types::BlockNumber::from(info.number),
+                            "out_point": json_types::OutPoint::from(info.out_point()),
+                            "output": json_types::CellOutput::from(output),
+                            "output_data": JsonBytes::from_bytes(output_data),
+                            "tx_index": json_types::Uint32::from(info.index.tx_index),
+                        }));
+                    }
+                    cells
+                } else {
+                    let mut cells = Vec::with_capacity(live_cells.len());
+                    let tip_number_opt = if with_tx_meta {
+                        Some(self.rpc_client.get_tip_block_number()?)
+                    } else {
+                        None
+                    };
+                    let mut tx_meta_cache: HashMap<u64, (u64, u64)> = HashMap::default();
+                    let mut epoch_cache: HashMap<u64, EpochNumberWithFraction> = HashMap::default();
+                    let annotate_with_block_time = m.is_present("annotate-with-block-time");
+                    let mut timestamp_cache: HashMap<u64, u64> = HashMap::default();
+                    let resolve_lock_script = m.is_present("resolve-lock-script");
+                    let show_since = m.is_present("show-since");
+                    let lock_scripts = if resolve_lock_script || show_since {
+                        Some(self.resolve_lock_scripts(&live_cells)?)
+                    } else {
+                        None
+                    };
+                    for LiveCell { info, mature } in live_cells {
+                        let dep_group_cells = if follow_dep_group && is_dep_group_like(&info) {
+                            self.resolve_dep_group(info.out_point())?
+                        } else {
+                            None
+                        };
+                        // Code cells deployed with type id keep the dep_group under a type script
+                        let resolved_dep_cells = if resolve_dep_groups
+                            && info.type_hashes.is_some()
+                            && is_dep_group_like(&info)
+                        {
+                            self.resolve_dep_group(info.out_point())?
+                        } else {
+                            None
+                        };
+                        let tx_meta = if let Some(tip_number) = tip_number_opt {
+                            Some(self.get_tx_meta(&mut tx_meta_cache, tip_number, info.number)?)
+                        } else {
+                            None
+                        };
+                        let mut value = live_cell_value(&info, mature);
                         let map = value.as_object_mut().unwrap();
-                        map.insert("capacity".to_string(), capacity_string);
-                        map.insert("mature".to_string(), mature);
-                        value
-                    }).collect::<Vec<_>>(),
+                        if type_id_only {
+                            let (output, _) =
+                                get_live_cell(self.rpc_client, info.out_point(), false)?;
+                            let type_id_args = output
+                                .type_()
+                                .to_opt()
+                                .map(|script| JsonBytes::from_bytes(script.args().unpack()));
+                            map.insert("type_id_args".to_string(), serde_json::json!(type_id_args));
+                        }
+                        if let Some(lock_scripts) = lock_scripts.as_ref() {
+                            let lock_script = lock_scripts.get(&info.lock_hash).cloned();
+                            if show_since {
+                                let time_lock = lock_script
+                                    .as_ref()
+                                    .map(time_lock_condition)
+                                    .unwrap_or_else(|| "No time lock".to_string());
+                                map.insert("time_lock".to_string(), serde_json::json!(time_lock));
+                            }
+                            if resolve_lock_script {
+                                map.insert(
+                                    "lock_script".to_string(),
+                                    serde_json::json!(lock_script.map(json_types::Script::from)),
+                                );
+                            }
+                        }
+                        if with_epoch {
+                            let epoch = self.get_block_epoch(&mut epoch_cache, info.number)?;
+                            map.insert(
+                                "created_at_epoch".to_string(),
+                                serde_json::json!({
+                                    "number": epoch.number(),
+                                    "index": epoch.index(),
+                                    "length": epoch.length(),
+                                }),
+                            );
+                        }
+                        if annotate_with_block_time {
+                            let timestamp =
+                                self.get_block_timestamp(&mut timestamp_cache, info.number)?;
+                            map.insert(
+                                "created_at_iso".to_string(),
+                                serde_json::json!(iso8601_time(timestamp)),
+                            );
+                        }
+                        if let Some((timestamp, tip_number)) = tx_meta {
+                            map.insert("block_timestamp".to_string(), serde_json::json!(timestamp));
+                            map.insert(
+                                "confirmations".to_string(),
+                                serde_json::json!(tip_number.saturating_sub(info.number)),
+                            );
+                        }
+                        if let Some(dep_group_cells) = dep_group_cells {
+                            map.insert(
+                                "dep_group_cells".to_string(),
+                                serde_json::json!(dep_group_cells),
+                            );
+                        }
+                        if let Some(resolved_dep_cells) = resolved_dep_cells {
+                            map.insert(
+                                "resolved_dep_cells".to_string(),
+                                serde_json::json!(resolved_dep_cells),
+                            );
+                        }
+                        cells.push(value);
+                    }
+                    cells
+                };
+                let mut resp = serde_json::json!({
+                    "live_cells": live_cells_value,
                     "current_count": current_count,
                     "current_capacity": format!("{:#}", HumanCapacity::from(current_capacity)),
                 });
@@ -693,6 +3908,51 @@ impl<'a> CliSubCommand for WalletSubCommand<'a> {
                     resp["total_capacity"] =
                         serde_json::json!(format!("{:#}", HumanCapacity::from(total_capacity)));
                 }
+                if include_consumed {
+                    let filter = |info: &LiveCellInfo| {
+                        if !cellbase_match(info) {
+                            false
+                        } else if let Some(tx_hash) = created_tx_hash_opt.as_ref() {
+                            &info.tx_hash == tx_hash
+                        } else if let Some(lock_hash) = lock_hash_opt.as_ref() {
+                            &info.lock_hash == lock_hash
+                        } else if let Some(type_hash) = type_hash_opt.as_ref() {
+                            info.type_hashes.as_ref().map(|(_, hash)| hash) == Some(type_hash)
+                        } else {
+                            info.type_hashes.as_ref().map(|(hash, _)| hash)
+                                == code_hash_opt.as_ref()
+                        }
+                    };
+                    let from_number = from_number_opt.unwrap_or(0);
+                    let (consumed_cells, first_number) =
+                        self.with_db(|db| db.get_consumed_cells(from_number, to_number, filter))?;
+                    if let Some(first_number) = first_number {
+                        if from_number_opt.is_some() && from_number < first_number {
+                            return Err(format!(
+                                "Consumed cells before block #{} are not kept by the index",
+                                first_number
+                            ));
+                        }
+                    }
+                    let consumed_cells_value = consumed_cells
+                        .into_iter()
+                        .map(|(info, consumed_number)| {
+                            let mut value = live_cell_value(&info, true);
+                            let map = value.as_object_mut().unwrap();
+                            map.remove("mature");
+                            map.insert(
+                                "created_number".to_string(),
+                                serde_json::json!(info.number),
+                            );
+                            map.insert(
+                                "consumed_number".to_string(),
+                                serde_json::json!(consumed_number),
+                            );
+                            value
+                        })
+                        .collect::<Vec<_>>();
+                    resp["consumed_cells"] = serde_json::json!(consumed_cells_value);
+                }
 
                 Ok(resp.render(format, color))
             }
@@ -716,6 +3976,299 @@ impl<'a> CliSubCommand for WalletSubCommand<'a> {
                 })?;
                 Ok(resp.render(format, color))
             }
+            ("check", _) => {
+                let current_url = self.rpc_client.url().to_string();
+                let resp = self
+                    .rpc_client
+                    .urls()
+                    .into_iter()
+                    .map(|url| {
+                        let mut client = HttpRpcClient::new(url.clone());
+                        let result = client.get_blockchain_info().and_then(|chain_info| {
+                            client
+                                .get_tip_block_number()
+                                .map(|tip_number| (chain_info.chain, tip_number))
+                        });
+                        match result {
+                            Ok((chain, tip_number)) => serde_json::json!({
+                                "url": url,
+                                "in_use": url == current_url,
+                                "reachable": true,
+                                "chain": chain,
+                                "tip_block_number": tip_number,
+                            }),
+                            Err(err) => serde_json::json!({
+                                "url": url,
+                                "in_use": url == current_url,
+                                "reachable": false,
+                                "error": err,
+                            }),
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                Ok(resp.render(format, color))
+            }
+            ("estimate-witness-size", Some(m)) => {
+                let address: Address = AddressParser::default().from_matches(m, "address")?;
+                let hash_type = address.payload().hash_type();
+                let code_hash: H256 = address.payload().code_hash().unpack();
+                let (lock_type, lock_size) = if hash_type == ScriptHashType::Type
+                    && code_hash == SIGHASH_TYPE_HASH
+                {
+                    ("sighash", SECP_SIGNATURE_SIZE)
+                } else if hash_type == ScriptHashType::Type && code_hash == MULTISIG_TYPE_HASH {
+                    let threshold_opt: Option<u8> = FromStrParser::<u8>::default()
+                        .from_matches_opt(m, "multisig-threshold", false)?;
+                    let signers_opt: Option<u8> = FromStrParser::<u8>::default().from_matches_opt(
+                        m,
+                        "multisig-signers",
+                        false,
+                    )?;
+                    let (threshold, signers) = match (threshold_opt, signers_opt) {
+                        (Some(threshold), Some(signers)) => (threshold, signers),
+                        _ => {
+                            return Err("<multisig-threshold> and <multisig-signers> are required for multisig address".to_string());
+                        }
+                    };
+                    if threshold == 0 || threshold > signers {
+                        return Err(format!(
+                            "Invalid multisig threshold {} of {} signers",
+                            threshold, signers
+                        ));
+                    }
+                    // Multisig script (4 bytes header + 20 bytes per signer) and the signatures
+                    (
+                        "multisig",
+                        4 + 20 * signers as usize + SECP_SIGNATURE_SIZE * threshold as usize,
+                    )
+                } else {
+                    return Err(format!(
+                        "Only sighash and multisig address are supported: {}",
+                        address
+                    ));
+                };
+                // The lock field is put in WitnessArgs (16 bytes header, 4 bytes length)
+                let resp = serde_json::json!({
+                    "lock_type": lock_type,
+                    "lock_size": lock_size,
+                    "witness_size": lock_size + 20,
+                });
+                Ok(resp.render(format, color))
+            }
+            ("sign-tx", Some(m)) => {
+                let tx_file: PathBuf = FilePathParser::new(true).from_matches(m, "tx-file")?;
+                let output_opt: Option<PathBuf> =
+                    FilePathParser::new(false).from_matches_opt(m, "output", false)?;
+                let privkey_opt: Option<PrivkeyWrapper> =
+                    PrivkeyPathParser.from_matches_opt(m, "privkey-path", false)?;
+                let signer = if let Some(privkey) = privkey_opt {
+                    get_privkey_signer(privkey)
+                } else {
+                    let account: H160 = FixedHashParser::<H160>::default()
+                        .from_matches_opt(m, "from-account", false)?
+                        .or(get_default_account(m)?)
+                        .ok_or_else(|| {
+                            "<privkey-path> or <from-account> is required".to_string()
+                        })?;
+                    let password = Arc::new(get_password(m, None)?);
+                    get_keystore_signer(self.key_store.clone(), HashMap::new(), account, password)
+                };
+
+                let content = fs::read_to_string(&tx_file).map_err(|err| err.to_string())?;
+                // The multisig transaction file keeps the multisig configs and the signatures
+                // collected so far, a plain transaction only carries the witnesses
+                let repr_opt = serde_json::from_str::<ReprTxHelper>(&content).ok();
+                let is_repr = repr_opt.is_some();
+                let network = get_network_type(self.rpc_client)?;
+                let mut helper = if let Some(repr) = repr_opt {
+                    TxHelper::try_from(repr)?
+                } else {
+                    TxHelper::new(parse_json_tx(&content)?.into_view())
+                };
+
+                let mut live_cell_cache: HashMap<(OutPoint, bool), (CellOutput, Bytes)> =
+                    Default::default();
+                let mut get_live_cell = |out_point: OutPoint, with_data: bool| {
+                    get_live_cell_with_cache(
+                        &mut live_cell_cache,
+                        self.rpc_client,
+                        out_point,
+                        with_data,
+                    )
+                    .map(|(output, _)| output)
+                };
+
+                let signatures = helper.sign_inputs(signer, &mut get_live_cell, false)?;
+                if signatures.is_empty() {
+                    return Err("No input of the transaction can be signed by this key".to_string());
+                }
+                for (lock_arg, signature) in signatures {
+                    helper.add_signature(lock_arg, signature)?;
+                }
+
+                let resp = if is_repr {
+                    serde_json::to_value(ReprTxHelper::new(helper, network))
+                } else {
+                    // Input groups signed by others keep their witnesses
+                    let tx = helper.build_tx(&mut get_live_cell, true)?;
+                    serde_json::to_value(ckb_jsonrpc_types::TransactionView::from(tx))
+                }
+                .map_err(|err| err.to_string())?;
+                let content = serde_json::to_string_pretty(&resp).map_err(|err| err.to_string())?;
+                fs::write(output_opt.unwrap_or(tx_file), content).map_err(|err| err.to_string())?;
+                Ok(resp.render(format, color))
+            }
+            ("get-tx-note", Some(m)) => {
+                let tx_hash: H256 =
+                    FixedHashParser::<H256>::default().from_matches(m, "tx-hash")?;
+                read_tx_history()?
+                    .into_iter()
+                    .rev()
+                    .find(|record| record.tx_hash == tx_hash && record.note.is_some())
+                    .and_then(|record| record.note)
+                    .ok_or_else(|| format!("No note for transaction: {:#x}", tx_hash))
+            }
+            ("list-tx-notes", _) => {
+                // tx_hash => note
+                let notes = read_tx_history()?
+                    .into_iter()
+                    .filter_map(|record| {
+                        record
+                            .note
+                            .map(|note| (format!("{:#x}", record.tx_hash), note))
+                    })
+                    .collect::<BTreeMap<_, _>>();
+                Ok(serde_json::json!(notes).render(format, color))
+            }
+            ("tx-history", Some(m)) => {
+                let limit_opt: Option<usize> =
+                    FromStrParser::<usize>::default().from_matches_opt(m, "limit", false)?;
+                let mut records = read_tx_history()?;
+                records.reverse();
+                if let Some(limit) = limit_opt {
+                    records.truncate(limit);
+                }
+                let resp = records
+                    .into_iter()
+                    .map(|record| {
+                        let time = NaiveDateTime::from_timestamp(record.timestamp as i64, 0);
+                        let mut value = serde_json::json!({
+                            "tx_hash": record.tx_hash,
+                            "time": format!("{} (UTC)", time),
+                            "to": record.to,
+                            "capacity": record.capacity,
+                            "memo": record.memo,
+                        });
+                        if record.note.is_some() {
+                            value["note"] = serde_json::json!(record.note);
+                        }
+                        if !record.extra_recipients.is_empty() {
+                            value["extra_recipients"] = serde_json::json!(record.extra_recipients);
+                        }
+                        if record.inputs.is_some() {
+                            value["inputs"] = serde_json::json!(record.inputs);
+                            value["outputs"] = serde_json::json!(record.outputs);
+                        }
+                        value
+                    })
+                    .collect::<Vec<_>>();
+                Ok(serde_json::json!(resp).render(format, color))
+            }
+            ("decode-tx-comment", Some(m)) => {
+                let tx_hash: H256 =
+                    FixedHashParser::<H256>::default().from_matches(m, "tx-hash")?;
+                let tx = self
+                    .rpc_client
+                    .get_transaction(tx_hash.clone())?
+                    .ok_or_else(|| format!("Transaction not found: {:#x}", tx_hash))?
+                    .transaction
+                    .inner;
+                let comment = tx
+                    .witnesses
+                    .iter()
+                    .skip(tx.inputs.len())
+                    .find_map(|witness| decode_tx_comment(witness.as_bytes()))
+                    .ok_or_else(|| format!("No tx comment in transaction {:#x}", tx_hash))??;
+                Ok(comment.render(format, color))
+            }
+            ("index-diff", Some(m)) => {
+                let network_type = get_network_type(self.rpc_client)?;
+                let address: Address = AddressParser::default()
+                    .set_network(network_type)
+                    .from_matches(m, "address")?;
+                let from_number: u64 =
+                    FromStrParser::<u64>::default().from_matches(m, "from-block")?;
+                let to_number: u64 = FromStrParser::<u64>::default().from_matches(m, "to-block")?;
+                if from_number > to_number {
+                    return Err(format!(
+                        "--from-block {} is greater than --to-block {}",
+                        from_number, to_number
+                    ));
+                }
+                let lock_hash: H256 = Script::from(address.payload()).calc_script_hash().unpack();
+                let (live_cells, (consumed_cells, first_number)) = self.with_db(|db| {
+                    let terminator = |_, info: &LiveCellInfo| {
+                        (info.number > to_number, info.number <= to_number)
+                    };
+                    let live_cells =
+                        db.get_live_cells_by_lock(lock_hash.pack(), Some(from_number), terminator);
+                    // Cells created in the range may be consumed after it
+                    let consumed = db.get_consumed_cells(from_number, u64::max_value(), |info| {
+                        info.lock_hash == lock_hash
+                    });
+                    (live_cells, consumed)
+                })?;
+                match first_number {
+                    Some(first_number) if from_number >= first_number => {}
+                    _ => {
+                        return Err(format!(
+                            "Consumed cells before block #{} are not kept by the index",
+                            first_number.unwrap_or(to_number + 1)
+                        ));
+                    }
+                }
+                let cell_value = |info: &LiveCellInfo, number: u64| {
+                    serde_json::json!({
+                        "out_point": json_types::OutPoint::from(info.out_point()),
+                        "capacity": format!("{:#}", HumanCapacity::from(info.capacity)),
+                        "block_number": number,
+                    })
+                };
+                let mut created = live_cells
+                    .iter()
+                    .map(|info| (info.number, cell_value(info, info.number)))
+                    .collect::<Vec<_>>();
+                created.extend(
+                    consumed_cells
+                        .iter()
+                        .filter(|(info, _)| info.number >= from_number && info.number <= to_number)
+                        .map(|(info, _)| (info.number, cell_value(info, info.number))),
+                );
+                created.sort_by_key(|(number, _)| *number);
+                let destroyed = consumed_cells
+                    .iter()
+                    .filter(|(_, number)| *number <= to_number)
+                    .map(|(info, number)| cell_value(info, *number))
+                    .collect::<Vec<_>>();
+                let resp = serde_json::json!({
+                    "created": created.into_iter().map(|(_, value)| value).collect::<Vec<_>>(),
+                    "destroyed": destroyed,
+                });
+                Ok(resp.render(format, color))
+            }
+            ("index-status", _) => {
+                let state = self.index_controller.state().read().clone();
+                let progress = self.index_controller.progress().read().clone();
+                let resp = serde_json::json!({
+                    "state": state.to_string(),
+                    "synced": state.is_synced(),
+                    "processed_number": progress.current_number(),
+                    "tip_number": progress.tip_number(),
+                    "blocks_per_sec": progress.blocks_per_sec().map(|speed| format!("{:.2}", speed)),
+                    "eta_secs": progress.eta().map(|eta| eta.as_secs()),
+                });
+                Ok(resp.render(format, color))
+            }
             ("db-metrics", _) => {
                 let metrcis = self.with_db(|db| db.get_metrics(None))?;
                 let resp = serde_json::to_value(metrcis).map_err(|err| err.to_string())?;
@@ -726,11 +4279,621 @@ impl<'a> CliSubCommand for WalletSubCommand<'a> {
     }
 }
 
+// Cache key of the present get-live-cells arguments
+fn live_cells_cache_key(m: &ArgMatches) -> String {
+    GET_LIVE_CELLS_ARGS
+        .iter()
+        .filter(|name| m.is_present(name))
+        .map(|name| format!("{}={:?}", name, m.values_of_lossy(name)))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// Get the live cell through the cache, the RPC call is traced when not cached
+fn get_traced_live_cell(
+    cache: &mut HashMap<(OutPoint, bool), (CellOutput, Bytes)>,
+    rpc_client: &mut HttpRpcClient,
+    trace: &Trace,
+    out_point: OutPoint,
+    with_data: bool,
+) -> Result<CellOutput, String> {
+    let cached = cache.contains_key(&(out_point.clone(), with_data));
+    let result = get_live_cell_with_cache(cache, rpc_client, out_point.clone(), with_data)
+        .map(|(output, _)| output);
+    if trace.is_enabled() && !cached {
+        let _ = trace.rpc(
+            "get_live_cell",
+            serde_json::json!([json_types::OutPoint::from(out_point), with_data]),
+            result.clone().map(json_types::CellOutput::from),
+        );
+    }
+    result
+}
+
+// Estimate the size of the signed transaction, use zero signatures as placeholders
+// (--input-size-hint replaces the witnesses by <hint> bytes per input)
+fn estimate_tx_size<C>(
+    helper: &TxHelper,
+    mut get_live_cell: C,
+    input_size_hint: Option<usize>,
+) -> Result<usize, String>
+where
+    C: FnMut(OutPoint, bool) -> Result<CellOutput, String>,
+{
+    let mut helper = helper.clone();
+    let lock_args = helper
+        .input_group(&mut get_live_cell, true)?
+        .into_iter()
+        .map(|((_, lock_arg), _)| lock_arg)
+        .collect::<Vec<_>>();
+    for lock_arg in lock_args {
+        helper.add_signature(lock_arg, Bytes::from(vec![0u8; SECP_SIGNATURE_SIZE]))?;
+    }
+    let tx = helper.build_tx(&mut get_live_cell, true)?;
+    if let Some(hint) = input_size_hint {
+        // Keep the extra witnesses (--tx-comment)
+        let extra_witnesses = tx.witnesses().into_iter().skip(tx.inputs().len()).collect();
+        let skeleton = tx
+            .data()
+            .as_advanced_builder()
+            .set_witnesses(extra_witnesses)
+            .build();
+        return Ok(skeleton.data().serialized_size_in_block() + tx.inputs().len() * hint);
+    }
+    Ok(tx.data().serialized_size_in_block())
+}
+
+// Decode WitnessArgs.lock of each input
+fn decode_witnesses<C>(
+    tx: &TransactionView,
+    mut get_live_cell: C,
+) -> Result<Vec<WitnessInfo>, String>
+where
+    C: FnMut(OutPoint, bool) -> Result<CellOutput, String>,
+{
+    let witnesses = tx.witnesses();
+    let mut infos = Vec::new();
+    for (input_index, input) in tx.inputs().into_iter().enumerate() {
+        let lock = get_live_cell(input.previous_output(), false)?.lock();
+        let lock_field = match witnesses.get(input_index) {
+            Some(witness) if !witness.raw_data().is_empty() => {
+                WitnessArgs::from_slice(witness.raw_data().as_ref())
+                    .map_err(|err| format!("Invalid witness(no.{}): {}", input_index, err))?
+                    .lock()
+                    .to_opt()
+                    .map(|data| data.raw_data())
+            }
+            _ => None,
+        };
+        // Strip the multisig script from multisig lock field
+        let signature = lock_field.map(|data| {
+            if lock.code_hash() == MULTISIG_TYPE_HASH.pack() && data.len() > 4 {
+                let script_len = 4 + 20 * data[3] as usize;
+                Bytes::from(&data[std::cmp::min(script_len, data.len())..])
+            } else {
+                data
+            }
+        });
+        infos.push(WitnessInfo {
+            input_index,
+            lock_arg: lock.args().raw_data(),
+            signature,
+        });
+    }
+    Ok(infos)
+}
+
+// Parse the recipients and --burn-capacity into outputs, the capacities are adjusted by
+// --auto-adjust-capacity and checked by the validation mode
+fn parse_transfer_outputs(
+    args: &TransferArgs,
+    network_type: NetworkType,
+    validation_mode: CapacityValidationMode,
+) -> Result<TransferOutputs, String> {
+    let capacity_unit = args.capacity_unit;
+    let data_length_limit = match args.data_length_limit {
+        Some(limit) => limit,
+        None => read_data_length_limit()?.unwrap_or(DEFAULT_DATA_LENGTH_LIMIT),
+    };
+    if args.recipients.is_empty() {
+        return Err("<to-address> or <to-lock-file> is required".to_string());
+    }
+    if args.split_output == Some(0) {
+        return Err("--split-output can not be 0".to_string());
+    }
+    // (lock, capacity, data) of each recipient, in the order given
+    let mut to_outputs: Vec<(Script, u64, Bytes)> = Vec::with_capacity(args.recipients.len());
+    let mut adjusted_capacities = Vec::new();
+    for (idx, recipient) in args.recipients.iter().enumerate() {
+        // Lock script from file is used as it is
+        let lock: Script = if let Some(lock) = recipient.lock.clone() {
+            lock
+        } else {
+            let address = recipient
+                .address
+                .as_ref()
+                .ok_or_else(|| "<to-address> or <to-lock-file> is required".to_string())?;
+            let address = AddressParser::default()
+                .set_network(network_type)
+                .parse(address)?;
+            check_to_address(&address)?;
+            address.payload().into()
+        };
+        let data = recipient.data.clone();
+        if data.len() > data_length_limit {
+            return Err(format!(
+                "Data size {} bytes of recipient #{} exceeds the limit {} bytes (see --set-data-length-limit)",
+                data.len(),
+                idx,
+                data_length_limit
+            ));
+        }
+        let mut capacity: u64 = capacity_unit.parse(&recipient.capacity)?;
+        if args.auto_adjust_capacity {
+            let data_capacity = Capacity::bytes(data.len()).map_err(|err| err.to_string())?;
+            let exact = CellOutput::new_builder()
+                .lock(lock.clone())
+                .build_exact_capacity(data_capacity)
+                .map_err(|err| err.to_string())?;
+            let min_capacity: u64 = exact.capacity().unpack();
+            if capacity < min_capacity {
+                capacity = min_capacity;
+                adjusted_capacities.push((idx, min_capacity));
+            }
+        }
+        check_output_capacity(validation_mode, capacity, &lock, data.len())?;
+        if let Some(split_output) = args.split_output {
+            let exact = CellOutput::new_builder()
+                .lock(lock.clone())
+                .build_exact_capacity(Capacity::zero())
+                .map_err(|err| err.to_string())?;
+            let min_capacity: u64 = exact.capacity().unpack();
+            if capacity / u64::from(split_output) < min_capacity {
+                return Err(format!(
+                    "Capacity {:#} can not be split into {} cells, each cell requires at least {:#}",
+                    HumanCapacity::from(capacity),
+                    split_output,
+                    HumanCapacity::from(min_capacity),
+                ));
+            }
+        }
+        to_outputs.push((lock, capacity, data));
+    }
+    let burn_capacity: Option<u64> = args
+        .burn_capacity
+        .as_ref()
+        .map(|input| capacity_unit.parse(input))
+        .transpose()?;
+    let burn_output = if let Some(burn_capacity) = burn_capacity {
+        let output = CellOutput::new_builder()
+            .capacity(Capacity::shannons(burn_capacity).pack())
+            .lock(burn_lock_script())
+            .build();
+        let occupied = output
+            .occupied_capacity(Capacity::zero())
+            .map_err(|err| err.to_string())?;
+        if occupied.as_u64() > burn_capacity {
+            return Err(format!(
+                "Burn capacity can not less than {:#}",
+                HumanCapacity::from(occupied.as_u64())
+            ));
+        }
+        Some(output)
+    } else {
+        None
+    };
+    let outputs_capacity = to_outputs
+        .iter()
+        .map(|(_, capacity, _)| capacity)
+        .sum::<u64>()
+        + burn_capacity.unwrap_or(0);
+    Ok(TransferOutputs {
+        to_outputs,
+        adjusted_capacities,
+        burn_output,
+        outputs_capacity,
+    })
+}
+
+// Minimum capacity of the custom change cell (by --change-data or --change-type)
+fn get_change_output_min(
+    args: &TransferArgs,
+    change_address_payload: &AddressPayload,
+) -> Result<u64, String> {
+    if !args.has_custom_change() {
+        return Ok(0);
+    }
+    let data_len = args.change_data.as_ref().map(Bytes::len).unwrap_or(0);
+    let data_capacity = Capacity::bytes(data_len).map_err(|err| err.to_string())?;
+    let exact = CellOutput::new_builder()
+        .lock(change_address_payload.into())
+        .type_(args.change_type.clone().pack())
+        .build_exact_capacity(data_capacity)
+        .map_err(|err| err.to_string())?;
+    Ok(exact.capacity().unpack())
+}
+
+// Outputs of the transfer (burn and change, with the to-outputs placed at --recipient-index),
+// the rest capacity and the capacity bumped to the first to-output (by --change-threshold-action)
+fn assemble_outputs(
+    args: &TransferArgs,
+    mut to_outputs: Vec<(Script, u64, Bytes)>,
+    burn_output: Option<CellOutput>,
+    change_address_payload: &AddressPayload,
+    change_output_min: u64,
+    mut rest_capacity: u64,
+    tx_fee: u64,
+) -> Result<(Vec<(CellOutput, Bytes)>, u64, Option<u64>), String> {
+    let custom_change = args.has_custom_change();
+    let change_data = args.change_data.clone().unwrap_or_default();
+    if custom_change && rest_capacity < change_output_min {
+        return Err(format!(
+            "Change capacity {} can not hold the change cell (data: {} bytes, type script: {}), at least {} required",
+            HumanCapacity::from(rest_capacity),
+            change_data.len(),
+            args.change_type.is_some(),
+            HumanCapacity::from(change_output_min),
+        ));
+    }
+    let mut bumped_capacity = None;
+    if !custom_change && rest_capacity > 0 && rest_capacity < MIN_SECP_CELL_CAPACITY {
+        match args.change_threshold_action {
+            ChangeThresholdAction::Fee => {}
+            ChangeThresholdAction::Error => {
+                return Err(format!(
+                    "Change capacity {:#} is below the minimum cell capacity {:#}",
+                    HumanCapacity::from(rest_capacity),
+                    HumanCapacity::from(MIN_SECP_CELL_CAPACITY),
+                ));
+            }
+            ChangeThresholdAction::BumpToAddress => {
+                to_outputs[0].1 += rest_capacity;
+                bumped_capacity = Some(rest_capacity);
+                rest_capacity = 0;
+            }
+        }
+    }
+    if !custom_change
+        && rest_capacity < MIN_SECP_CELL_CAPACITY
+        && tx_fee + rest_capacity > ONE_CKB
+        && !args.dry_run
+    {
+        return Err("Transaction fee can not be more than 1.0 CKB, please change to-capacity value to adjust".to_string());
+    }
+
+    let mut outputs: Vec<(CellOutput, Bytes)> = Vec::new();
+    if let Some(burn_output) = burn_output {
+        outputs.push((burn_output, Bytes::default()));
+    }
+    if custom_change {
+        let change_output = CellOutput::new_builder()
+            .capacity(Capacity::shannons(rest_capacity).pack())
+            .lock(change_address_payload.into())
+            .type_(args.change_type.clone().pack())
+            .build();
+        outputs.push((change_output, change_data));
+    } else if rest_capacity >= MIN_SECP_CELL_CAPACITY {
+        let change_output = CellOutput::new_builder()
+            .capacity(Capacity::shannons(rest_capacity).pack())
+            .lock(change_address_payload.into())
+            .build();
+        outputs.push((change_output, Bytes::default()));
+    }
+    let split_output = u64::from(args.split_output.unwrap_or(1));
+    let to_outputs = to_outputs.into_iter().flat_map(|(lock, capacity, data)| {
+        let part_capacity = capacity / split_output;
+        (0..split_output).map(move |idx| {
+            let capacity = if idx + 1 == split_output {
+                capacity - part_capacity * (split_output - 1)
+            } else {
+                part_capacity
+            };
+            let to_output = CellOutput::new_builder()
+                .capacity(Capacity::shannons(capacity).pack())
+                .lock(lock.clone())
+                .build();
+            (to_output, data.clone())
+        })
+    });
+    // The to-output(s) are placed at --recipient-index, other outputs keep their order
+    let recipient_index = args.recipient_index.unwrap_or(0);
+    if recipient_index > outputs.len() {
+        return Err(format!(
+            "--recipient-index {} is out of range, only {} other output(s) (burn, change)",
+            recipient_index,
+            outputs.len()
+        ));
+    }
+    outputs.splice(recipient_index..recipient_index, to_outputs);
+    Ok((outputs, rest_capacity, bumped_capacity))
+}
+
+// Lock script can never be unlocked: no cell's data hash is zero
+fn burn_lock_script() -> Script {
+    Script::new_builder()
+        .code_hash(H256::default().pack())
+        .hash_type(ScriptHashType::Data.into())
+        .build()
+}
+
+// Read script from json file (same format as RPC)
+fn read_script_file(path: &str) -> Result<Script, String> {
+    let content = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+    let script: json_types::Script =
+        serde_json::from_str(&content).map_err(|err| format!("Invalid script file: {}", err))?;
+    Ok(script.into())
+}
+
+// Split out the cells with duplicated out point, the one with the highest block number is kept
+// at the position of its first occurrence
+fn deduplicate_live_cells(live_cells: Vec<LiveCell>) -> (Vec<LiveCell>, Vec<LiveCell>) {
+    let mut kept: Vec<LiveCell> = Vec::with_capacity(live_cells.len());
+    let mut positions: HashMap<(H256, u32), usize> = HashMap::default();
+    let mut duplicates = Vec::new();
+    for cell in live_cells {
+        let key = (cell.info.tx_hash.clone(), cell.info.tx_index);
+        if let Some(&pos) = positions.get(&key) {
+            if cell.info.number > kept[pos].info.number {
+                duplicates.push(std::mem::replace(&mut kept[pos], cell));
+            } else {
+                duplicates.push(cell);
+            }
+        } else {
+            positions.insert(key, kept.len());
+            kept.push(cell);
+        }
+    }
+    (kept, duplicates)
+}
+
+// Parse `--histogram-buckets`: ascending CKB amounts separated by comma
+fn parse_histogram_buckets(input: &str) -> Result<Vec<u64>, String> {
+    let boundaries = input
+        .split(',')
+        .map(|part| CapacityParser.parse(part.trim()).map(u64::from))
+        .collect::<Result<Vec<_>, String>>()?;
+    if boundaries.windows(2).any(|pair| pair[0] >= pair[1]) {
+        return Err(format!(
+            "histogram buckets must be in ascending order: {}",
+            input
+        ));
+    }
+    Ok(boundaries)
+}
+
+// Live cell json with human readable capacity and mature status
+fn live_cell_value(info: &LiveCellInfo, mature: bool) -> serde_json::Value {
+    let mut value = serde_json::to_value(info).unwrap();
+    let mature = serde_json::Value::Bool(mature);
+    let capacity_string =
+        serde_json::Value::String(format!("{:#}", HumanCapacity::from(info.capacity)));
+    let map = value.as_object_mut().unwrap();
+    map.insert("capacity".to_string(), capacity_string);
+    map.insert("mature".to_string(), mature);
+    map.insert("cellbase".to_string(), serde_json::json!(is_cellbase(info)));
+    value
+}
+
+// Progress of `get-live-cells --export-json`, the last exported cell and the export file length
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+struct ExportCheckpoint {
+    count: u64,
+    file_len: u64,
+    number: u64,
+    tx_index: u32,
+    output_index: u32,
+}
+
+fn load_export_checkpoint(path: &Path) -> Result<ExportCheckpoint, String> {
+    let content = fs::read_to_string(path).map_err(|err| err.to_string())?;
+    serde_json::from_str(&content)
+        .map_err(|err| format!("Invalid checkpoint file {}: {}", path.display(), err))
+}
+
+fn save_export_checkpoint(path: &Path, checkpoint: &ExportCheckpoint) -> Result<(), String> {
+    let content = serde_json::to_string(checkpoint).map_err(|err| err.to_string())?;
+    fs::write(path, content).map_err(|err| err.to_string())
+}
+
+fn is_type_id_cell(info: &LiveCellInfo) -> bool {
+    info.type_hashes
+        .as_ref()
+        .map(|(code_hash, _)| code_hash == &TYPE_ID_CODE_HASH)
+        .unwrap_or(false)
+}
+
+// Extra witness of --tx-comment: the magic followed by the serialized json object
+fn encode_tx_comment(input: &str) -> Result<Bytes, String> {
+    let value: serde_json::Value =
+        serde_json::from_str(input).map_err(|err| format!("Invalid --tx-comment json: {}", err))?;
+    if !value.is_object() {
+        return Err("--tx-comment must be a json object".to_string());
+    }
+    let content = serde_json::to_vec(&value).map_err(|err| err.to_string())?;
+    if content.len() > TX_COMMENT_MAX_SIZE {
+        return Err(format!(
+            "--tx-comment is {} bytes serialized, the limit is {} bytes",
+            content.len(),
+            TX_COMMENT_MAX_SIZE
+        ));
+    }
+    let mut witness = TX_COMMENT_MAGIC.to_vec();
+    witness.extend(content);
+    Ok(Bytes::from(witness))
+}
+
+// The json of a --tx-comment witness, None if the witness is not one
+fn decode_tx_comment(witness: &[u8]) -> Option<Result<serde_json::Value, String>> {
+    if witness.len() < TX_COMMENT_MAGIC.len()
+        || &witness[..TX_COMMENT_MAGIC.len()] != TX_COMMENT_MAGIC
+    {
+        return None;
+    }
+    Some(
+        serde_json::from_slice(&witness[TX_COMMENT_MAGIC.len()..])
+            .map_err(|err| format!("Invalid tx comment json: {}", err)),
+    )
+}
+
+// Unlock condition of a time-locked multisig lock (since in the last 8 bytes of the args)
+fn time_lock_condition(lock: &Script) -> String {
+    let args = lock.args().raw_data();
+    if lock.code_hash() != MULTISIG_TYPE_HASH.pack() || args.len() != 28 {
+        return "No time lock".to_string();
+    }
+    let mut since_bytes = [0u8; 8];
+    since_bytes.copy_from_slice(&args[20..]);
+    let since = Since::from_raw_value(u64::from_le_bytes(since_bytes));
+    let (since_type, value) = match since.extract_metric() {
+        Some(metric) if since.flags_is_valid() => metric,
+        _ => return format!("Invalid since: {:#x}", u64::from_le_bytes(since_bytes)),
+    };
+    let condition = match since_type {
+        SinceType::EpochNumberWithFraction => {
+            let epoch = EpochNumberWithFraction::from_full_value(value);
+            if epoch.index() == 0 {
+                format!("epoch {}", epoch.number())
+            } else {
+                format!("epoch {}", epoch)
+            }
+        }
+        SinceType::BlockNumber => format!("block {}", value),
+        SinceType::Timestamp => format!("timestamp {}", value),
+    };
+    if since.is_absolute() {
+        format!("Unlocks at {}", condition)
+    } else {
+        format!("Unlocks after {} since the cell created", condition)
+    }
+}
+
+// Block timestamp (milliseconds) as ISO 8601 UTC time, eg: 2019-11-16T06:00:00.000Z
+fn iso8601_time(timestamp: u64) -> String {
+    let time = NaiveDateTime::from_timestamp(
+        (timestamp / 1000) as i64,
+        (timestamp % 1000) as u32 * 1_000_000,
+    );
+    time.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string()
+}
+
+// Only secp256k1 and multisig (optional since) locks are allowed as to-address
+fn check_to_address(to_address: &Address) -> Result<(), String> {
+    let hash_type = to_address.payload().hash_type();
+    let code_hash: H256 = to_address.payload().code_hash().unpack();
+    let args_len = to_address.payload().args().len();
+    if !(hash_type == ScriptHashType::Type && code_hash == SIGHASH_TYPE_HASH && args_len == 20)
+        && !(hash_type == ScriptHashType::Type
+            && code_hash == MULTISIG_TYPE_HASH
+            && (args_len == 20 || args_len == 28))
+    {
+        return Err(format!("Invalid to-address: {}", to_address));
+    }
+    Ok(())
+}
+
+fn check_output_capacity(
+    validation_mode: CapacityValidationMode,
+    capacity: u64,
+    lock: &Script,
+    data_len: usize,
+) -> Result<(), String> {
+    match validation_mode {
+        CapacityValidationMode::Strict => {
+            check_capacity(capacity, data_len)?;
+            // Occupied capacity of all the fields, the lock args may be longer than secp's
+            let data_capacity = Capacity::bytes(data_len).map_err(|err| err.to_string())?;
+            let exact = CellOutput::new_builder()
+                .lock(lock.clone())
+                .build_exact_capacity(data_capacity)
+                .map_err(|err| err.to_string())?;
+            let min_capacity: u64 = exact.capacity().unpack();
+            if capacity < min_capacity {
+                return Err(format!(
+                    "Capacity {:#} can not hold the to-output, at least {:#} required",
+                    HumanCapacity::from(capacity),
+                    HumanCapacity::from(min_capacity),
+                ));
+            }
+            Ok(())
+        }
+        CapacityValidationMode::Permissive => check_capacity(capacity, 0),
+    }
+}
+
+// Epoch as (numerator, denominator) fraction, an epoch with zero length is a whole epoch
+fn epoch_fraction(epoch: EpochNumberWithFraction) -> (u128, u128) {
+    if epoch.length() == 0 {
+        (u128::from(epoch.number()), 1)
+    } else {
+        let length = u128::from(epoch.length());
+        (
+            u128::from(epoch.number()) * length + u128::from(epoch.index()),
+            length,
+        )
+    }
+}
+
+fn add_epoch_fractions(a: (u128, u128), b: (u128, u128)) -> (u128, u128) {
+    (a.0 * b.1 + b.0 * a.1, a.1 * b.1)
+}
+
+fn compare_epoch_since(current: (u128, u128), required: (u128, u128)) -> (bool, String) {
+    let as_f64 = |(num, den): (u128, u128)| num as f64 / den as f64;
+    if current.0 * required.1 >= required.0 * current.1 {
+        (
+            true,
+            format!(
+                "satisfied (current: {:.1}, required: {:.1})",
+                as_f64(current),
+                as_f64(required)
+            ),
+        )
+    } else {
+        (
+            false,
+            format!(
+                "NOT SATISFIED: unlocks in {:.1} epochs",
+                as_f64(required) - as_f64(current)
+            ),
+        )
+    }
+}
+
+fn compare_since(current: u64, required: u64, unit: &str) -> (bool, String) {
+    if current >= required {
+        (
+            true,
+            format!("satisfied (current: {}, required: {})", current, required),
+        )
+    } else {
+        (
+            false,
+            format!("NOT SATISFIED: unlocks in {} {}", required - current, unit),
+        )
+    }
+}
+
+// The data of a dep_group cell is a molecule OutPointVec: 4 bytes length + 36 bytes per out point
+fn is_dep_group_like(info: &LiveCellInfo) -> bool {
+    info.data_bytes > 4 && (info.data_bytes - 4) % 36 == 0
+}
+
+// Prompts for the password if it is not given yet, it is kept for the later uses
+fn get_or_read_password(
+    password: &mut Option<Arc<Password>>,
+    prompt: Option<&str>,
+) -> Result<Arc<Password>, String> {
+    if password.is_none() {
+        *password = Some(Arc::new(read_password(false, prompt)?));
+    }
+    Ok(Arc::clone(password.as_ref().unwrap()))
+}
+
 fn get_keystore_signer(
     key_store: KeyStore,
     path_map: HashMap<H160, DerivationPath>,
     account: H160,
-    password: String,
+    password: Arc<Password>,
 ) -> SignerFn {
     Box::new(move |lock_args: &HashSet<H160>, message: &H256| {
         let path: &[_] = if lock_args.contains(&account) {
@@ -751,18 +4914,635 @@ fn get_keystore_signer(
     })
 }
 
-#[derive(Clone, Debug)]
+// Each input group is signed by the first account owning it, the first account also signs for its
+// derived addresses
+fn get_multi_keystore_signer(
+    key_store: KeyStore,
+    path_map: HashMap<H160, DerivationPath>,
+    account: H160,
+    password: Arc<Password>,
+    extra_accounts: Vec<(H160, Arc<Password>)>,
+) -> SignerFn {
+    let mut signers = vec![get_keystore_signer(
+        key_store.clone(),
+        path_map,
+        account,
+        password,
+    )];
+    for (account, password) in extra_accounts {
+        signers.push(get_keystore_signer(
+            key_store.clone(),
+            HashMap::default(),
+            account,
+            password,
+        ));
+    }
+    Box::new(move |lock_args: &HashSet<H160>, message: &H256| {
+        for signer in signers.iter_mut() {
+            if let Some(signature) = signer(lock_args, message)? {
+                return Ok(Some(signature));
+            }
+        }
+        Ok(None)
+    })
+}
+
+// Thread safe signer for signing input groups concurrently, the keystore key is exported
+// (decrypted) only once instead of once per signature.
+fn get_parallel_signer(
+    key_store: &KeyStore,
+    from_privkey: Option<PrivkeyWrapper>,
+    path_map: HashMap<H160, DerivationPath>,
+    account: H160,
+    password: Arc<Password>,
+) -> Result<
+    Box<dyn Fn(&HashSet<H160>, &H256) -> Result<Option<[u8; 65]>, String> + Sync + Send>,
+    String,
+> {
+    if let Some(privkey) = from_privkey {
+        let pubkey = secp256k1::PublicKey::from_secret_key(&SECP256K1, &privkey);
+        let lock_arg = H160::from_slice(&blake2b_256(&pubkey.serialize()[..])[0..20])
+            .expect("Generate hash(H160) from pubkey failed");
+        return Ok(Box::new(
+            move |lock_args: &HashSet<H160>, message: &H256| {
+                if !lock_args.contains(&lock_arg) {
+                    return Ok(None);
+                }
+                if message == &h256!("0x0") {
+                    return Ok(Some([0u8; 65]));
+                }
+                let message = secp256k1::Message::from_slice(message.as_bytes())
+                    .expect("Convert to secp256k1 message failed");
+                let signature = SECP256K1.sign_recoverable(&message, &privkey);
+                Ok(Some(serialize_signature(&signature)))
+            },
+        ));
+    }
+    let master_privkey = key_store
+        .export_key(&account, password.as_bytes())
+        .map_err(|err| err.to_string())?;
+    Ok(Box::new(
+        move |lock_args: &HashSet<H160>, message: &H256| {
+            let path: &[_] = if lock_args.contains(&account) {
+                &[]
+            } else {
+                match lock_args.iter().find_map(|lock_arg| path_map.get(lock_arg)) {
+                    None => return Ok(None),
+                    Some(path) => path.as_ref(),
+                }
+            };
+            if message == &h256!("0x0") {
+                return Ok(Some([0u8; 65]));
+            }
+            let signature = master_privkey.sign_recoverable(message, path);
+            Ok(Some(serialize_signature(&signature)))
+        },
+    ))
+}
+
+#[derive(Clone, Debug, Default)]
 pub struct TransferArgs {
     pub privkey_path: Option<String>,
+    // Read mnemonic phrase from terminal, only valid for the duration of transfer
+    pub from_mnemonic_phrase: bool,
+    pub mnemonic_path: Option<String>,
+    // Key of the mnemonic phrase if it is read before the transfer (by read_credentials)
+    pub mnemonic_privkey: Option<PrivkeyWrapper>,
     pub from_account: Option<String>,
     pub from_locked_address: Option<String>,
-    pub password: Option<String>,
+    // Shared by the rounds of --fee-rate and the consolidation transfer
+    pub password: Option<Arc<Password>>,
     pub derive_receiving_address_length: Option<String>,
     pub derive_change_address: Option<String>,
-    pub capacity: String,
+    pub recipients: Vec<Recipient>,
     pub tx_fee: String,
-    pub to_address: String,
-    pub to_data: Option<Bytes>,
+    // Extra fee in shannons added to tx_fee
+    pub priority_fee: Option<String>,
+    pub simulate_vm_cycles: bool,
+    pub change_data: Option<Bytes>,
+    pub change_type: Option<Script>,
+    pub block_hash: Option<String>,
+    pub anchor_to_tip: bool,
+    pub parallel_sign: bool,
+    pub allow_cell_data: bool,
+    pub input_capacity_min: Option<String>,
+    pub auto_adjust_capacity: bool,
+    pub fee_estimate_only: bool,
+    pub sign_hash_fn: HashFn,
+    pub verbose_witness: bool,
+    pub burn_capacity: Option<String>,
+    // Unit of capacity, tx_fee, input_capacity_min, input_capacity_target, gas_reserve and burn_capacity
+    pub capacity_unit: CapacityUnit,
+    pub dump_sighash: bool,
+    // Fail early with a capacity breakdown before collecting inputs
+    pub capacity_precheck: bool,
+    // Write the execution trace to this file
+    pub trace_path: Option<String>,
+    pub change_threshold_action: ChangeThresholdAction,
+    // Type ID type script hash of a code cell dep
+    pub cell_dep_from_type_id: Option<String>,
+    // Out point of the cell paying the fee, format: {tx-hash}-{index}
+    pub fee_input: Option<String>,
+    // Sign the transaction but do not send it
+    pub no_broadcast: bool,
+    // Check the selected inputs are live by RPC
+    pub verify_against_rpc: bool,
+    // Select inputs totaling as close to this capacity as possible
+    pub input_capacity_target: Option<String>,
+    // Number of equal cells the to-capacity is split into
+    pub split_output: Option<u32>,
+    // Search key json of the input cells
+    pub input_from_query: Option<String>,
+    // Only resolve the change address
+    pub preview_change_address: bool,
+    // Max length of to_data, default by config file
+    pub data_length_limit: Option<usize>,
+    // Report the redeemable capacity of DAO cells when capacity is not enough
+    pub dao_aware_selection: bool,
+    // Output index of the to-output
+    pub recipient_index: Option<usize>,
+    // Minimum capacity kept in the from-address
+    pub gas_reserve: Option<String>,
+    // Expected lock hash of the from-address
+    pub require_lock_hash: Option<String>,
+    // Other accounts spent together with from_account
+    pub extra_from_accounts: Vec<String>,
+    // Passwords of extra_from_accounts in the same order, if read before the transfer
+    pub extra_passwords: Vec<Arc<Password>>,
+    // Change address instead of the from-address
+    pub change_to: Option<String>,
+    // Witness size of each input for fee estimation
+    pub input_size_hint: Option<usize>,
+    // Json metadata appended as an extra witness
+    pub tx_comment: Option<String>,
+    // Check the since of inputs against the tip before sending
+    pub simulate_since_validation: bool,
+    // Sign but do not send, the fee limit is not checked
+    pub dry_run: bool,
+    // Abort if any input is spent by a transaction in the tx-pool
+    pub protect_inputs: bool,
+}
+
+impl TransferArgs {
+    // Reads the mnemonic key and the passwords once for the transfers sharing the args, the
+    // passwords are not needed by --fee-estimate-only and --preview-change-address
+    pub fn read_credentials(&mut self) -> Result<(), String> {
+        if self.from_mnemonic_phrase {
+            if self.mnemonic_privkey.is_none() {
+                let path = DerivationPathParser.parse(
+                    self.mnemonic_path
+                        .as_deref()
+                        .unwrap_or(MNEMONIC_DEFAULT_PATH),
+                )?;
+                self.mnemonic_privkey = Some(read_mnemonic_privkey(&path)?);
+            }
+            return Ok(());
+        }
+        if self.privkey_path.is_some() {
+            return Ok(());
+        }
+        let signing = !self.fee_estimate_only && !self.preview_change_address;
+        let shared_password = self.password.clone();
+        if signing || self.derive_change_address.is_some() {
+            get_or_read_password(&mut self.password, None)?;
+        }
+        if signing && self.extra_passwords.is_empty() {
+            for account in &self.extra_from_accounts {
+                let password = match shared_password.as_ref() {
+                    Some(password) => Arc::clone(password),
+                    None => Arc::new(read_password(
+                        false,
+                        Some(&format!("Password of {}", account)),
+                    )?),
+                };
+                self.extra_passwords.push(password);
+            }
+        }
+        Ok(())
+    }
+
+    // Change cell with data or type script is always created
+    fn has_custom_change(&self) -> bool {
+        self.change_data
+            .as_ref()
+            .map(|data| !data.is_empty())
+            .unwrap_or(false)
+            || self.change_type.is_some()
+    }
+}
+
+// The from-address of the transfer and its keys
+struct TransferSender {
+    network_type: NetworkType,
+    from_privkey: Option<PrivkeyWrapper>,
+    from_address: Address,
+    from_lock_arg: H160,
+    from_locked_address: Option<Address>,
+    // Other accounts spent together (by repeated --from-account)
+    extra_accounts: Vec<H160>,
+    // Derived keys (by --derive-change-address)
+    path_map: HashMap<H160, DerivationPath>,
+    change_address_payload: AddressPayload,
+    // The lock hashes for search live cells
+    lock_hashes: Vec<Byte32>,
+    password: Option<Arc<Password>>,
+}
+
+// The to-outputs and the burn output before the change is known
+struct TransferOutputs {
+    // (lock, capacity, data) of each recipient, in the order given
+    to_outputs: Vec<(Script, u64, Bytes)>,
+    adjusted_capacities: Vec<(usize, u64)>,
+    burn_output: Option<CellOutput>,
+    outputs_capacity: u64,
+}
+
+// The input cells collected from the index database
+struct SelectedInputs {
+    infos: Vec<LiveCellInfo>,
+    // (out point, capacity) of the cell paying the fee (by --fee-input)
+    fee_cell: Option<(OutPoint, u64)>,
+    // Total capacity of the inputs, including the fee cell
+    capacity: u64,
+    data_cells: usize,
+    skipped_cells: usize,
+    consumed_cells: usize,
+    typed_cells: usize,
+    // (target, selected capacity without the fee cell)
+    input_capacity_target: Option<(u64, u64)>,
+}
+
+// A to-output of the transfer
+#[derive(Clone, Debug)]
+pub struct Recipient {
+    pub address: Option<String>,
+    // Lock script used as it is instead of the address (by --to-lock-file)
+    pub lock: Option<Script>,
+    pub capacity: String,
+    pub data: Bytes,
+}
+
+// How to handle the change below the minimum cell capacity
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChangeThresholdAction {
+    Fee,
+    Error,
+    BumpToAddress,
+}
+
+impl Default for ChangeThresholdAction {
+    fn default() -> ChangeThresholdAction {
+        ChangeThresholdAction::Fee
+    }
+}
+
+impl FromStr for ChangeThresholdAction {
+    type Err = String;
+    fn from_str(input: &str) -> Result<ChangeThresholdAction, String> {
+        match input {
+            "fee" => Ok(ChangeThresholdAction::Fee),
+            "error" => Ok(ChangeThresholdAction::Error),
+            "bump-to-address" => Ok(ChangeThresholdAction::BumpToAddress),
+            _ => Err(format!("Invalid change threshold action: {}", input)),
+        }
+    }
+}
+
+// How the capacity of transfer outputs is checked
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CapacityValidationMode {
+    Strict,
+    Permissive,
+}
+
+impl Default for CapacityValidationMode {
+    fn default() -> CapacityValidationMode {
+        CapacityValidationMode::Strict
+    }
+}
+
+impl FromStr for CapacityValidationMode {
+    type Err = String;
+    fn from_str(input: &str) -> Result<CapacityValidationMode, String> {
+        match input {
+            "strict" => Ok(CapacityValidationMode::Strict),
+            "permissive" => Ok(CapacityValidationMode::Permissive),
+            _ => Err(format!("Invalid capacity validation mode: {}", input)),
+        }
+    }
+}
+
+// Render the field selected by --output-json-path, string is printed without quotes
+fn render_json_path(
+    value: serde_json::Value,
+    json_path_opt: Option<&JsonPath>,
+    format: OutputFormat,
+    color: bool,
+) -> Result<String, String> {
+    let json_path = match json_path_opt {
+        Some(json_path) => json_path,
+        None => return Ok(value.render(format, color)),
+    };
+    match json_path.select(&value) {
+        Some(serde_json::Value::String(s)) => Ok(s.clone()),
+        Some(selected) => Ok(selected.render(format, color)),
+        None => Err("The json path is not found in output".to_string()),
+    }
+}
+
+// A sent transfer recorded in local tx-history file
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TxHistoryRecord {
+    pub tx_hash: H256,
+    // Seconds since UNIX epoch
+    pub timestamp: u64,
+    // The first to-output
+    pub to: String,
+    pub capacity: String,
+    // The other to-outputs of a transfer with multiple recipients
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extra_recipients: Vec<TxHistoryRecipient>,
+    pub memo: Option<String>,
+    // Saved by --note
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    // Saved by --idempotency-key
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idempotency_key: Option<String>,
+    // Saved by --track
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inputs: Option<Vec<json_types::OutPoint>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub outputs: Option<Vec<TxHistoryOutput>>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TxHistoryRecipient {
+    pub to: String,
+    pub capacity: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TxHistoryOutput {
+    pub output: json_types::CellOutput,
+    pub data: JsonBytes,
+}
+
+fn append_tx_history(record: &TxHistoryRecord) -> Result<(), String> {
+    let mut line = serde_json::to_string(record).map_err(|err| err.to_string())?;
+    line.push('\n');
+    fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(get_tx_history_file())
+        .and_then(|mut file| file.write_all(line.as_bytes()))
+        .map_err(|err| err.to_string())
+}
+
+fn read_tx_history() -> Result<Vec<TxHistoryRecord>, String> {
+    let history_file = get_tx_history_file();
+    if !history_file.exists() {
+        return Ok(Vec::new());
+    }
+    let content = fs::read_to_string(&history_file).map_err(|err| err.to_string())?;
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| serde_json::from_str(line).map_err(|err| err.to_string()))
+        .collect()
+}
+
+fn run_post_broadcast_hook(path: &str, envs: Vec<(&str, String)>) -> Result<(), String> {
+    let status = Command::new(path)
+        .envs(envs)
+        .status()
+        .map_err(|err| format!("Run post-broadcast hook {} failed: {}", path, err))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("Post-broadcast hook {} failed: {}", path, status))
+    }
+}
+
+// Extra information collected while building the transfer transaction
+#[derive(Clone, Debug, Default)]
+pub struct TransferInfo {
+    // The sender address
+    pub from_address: String,
+    // Set when --preview-change-address, the transaction is not built
+    pub change_address: Option<String>,
+    // Cycles consumed by all scripts (by --simulate-vm-cycles)
+    pub cycles: Option<u64>,
+    // Number of input cells with non-empty data (by --allow-cell-data)
+    pub data_cells: usize,
+    // Number of cells skipped by --input-capacity-min
+    pub skipped_cells: usize,
+    // Number of selected cells found consumed and replaced (by --verify-against-rpc)
+    pub consumed_cells: usize,
+    // Number of input cells with type script (by --input-from-query)
+    pub typed_cells: usize,
+    // (recipient index, capacity) of the to-capacities rounded up (by --auto-adjust-capacity)
+    pub adjusted_capacities: Vec<(usize, u64)>,
+    // The dust change added to the first to-output (by --change-threshold-action bump-to-address)
+    pub bumped_capacity: Option<u64>,
+    // (target, selected) input capacity (by --input-capacity-target)
+    pub input_capacity_target: Option<(u64, u64)>,
+    // Set when --fee-estimate-only, the transaction is not signed or sent
+    pub fee_estimate: Option<FeeEstimate>,
+    // Decoded witness of each input (by --verbose-witness)
+    pub witnesses: Option<Vec<WitnessInfo>>,
+    // (lock_arg, input indexes, message) of each input group (by --dump-sighash)
+    pub sighashes: Option<Vec<(Bytes, Vec<usize>, H256)>>,
+    // (input index, satisfied, result) of each input with since (by --simulate-since-validation)
+    pub since_checks: Option<Vec<(usize, bool, String)>>,
+}
+
+#[derive(Clone, Debug)]
+pub struct WitnessInfo {
+    pub input_index: usize,
+    pub lock_arg: Bytes,
+    // Signature(s) in WitnessArgs.lock, None if this is not the first input of the group
+    pub signature: Option<Bytes>,
+}
+
+#[derive(Clone, Debug)]
+pub struct FeeEstimate {
+    pub inputs: usize,
+    // Serialized size in block with placeholder signatures
+    pub tx_size: usize,
+    // Fee required by the minimal fee rate of ckb node
+    pub min_fee: u64,
+    // Actual fee (include change too small to hold a cell)
+    pub tx_fee: u64,
+    pub change_capacity: u64,
+    // Capacity of the to-outputs and the burn output
+    pub outputs_capacity: u64,
+}
+
+// Balance metrics, (label name, label value) identify the wallet
+#[derive(Clone, Debug)]
+pub struct CapacityMetrics {
+    pub label: (&'static str, String),
+    pub total: u64,
+    pub dao: u64,
+}
+
+impl Printable for CapacityMetrics {
+    fn render(&self, format: OutputFormat, color: bool) -> String {
+        let (name, value) = &self.label;
+        if format == OutputFormat::Prometheus {
+            format!(
+                "ckb_wallet_balance_shannons{{{name}=\"{value}\"}} {}\nckb_wallet_dao_balance_shannons{{{name}=\"{value}\"}} {}",
+                self.total,
+                self.dao,
+                name = name,
+                value = value,
+            )
+        } else {
+            let mut resp = serde_json::json!({
+                "total": self.total,
+                "dao": self.dao,
+            });
+            resp[*name] = serde_json::json!(value);
+            resp.render(format, color)
+        }
+    }
+}
+
+// Search key of `transfer --input-from-query`, a subset of ckb-indexer's `get_cells`
+#[derive(Clone, Debug, Deserialize)]
+pub struct InputQuery {
+    pub script: json_types::Script,
+    pub script_type: InputQueryScriptType,
+    #[serde(default)]
+    pub script_search_mode: InputQuerySearchMode,
+    #[serde(default)]
+    pub filter: Option<InputQueryFilter>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InputQueryScriptType {
+    Lock,
+    Type,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InputQuerySearchMode {
+    Exact,
+    Prefix,
+}
+
+impl Default for InputQuerySearchMode {
+    fn default() -> InputQuerySearchMode {
+        InputQuerySearchMode::Exact
+    }
+}
+
+// Ranges are [start, end)
+#[derive(Clone, Debug, Deserialize)]
+pub struct InputQueryFilter {
+    #[serde(default)]
+    pub block_range: Option<[json_types::BlockNumber; 2]>,
+    #[serde(default)]
+    pub output_capacity_range: Option<[json_types::Capacity; 2]>,
+}
+
+impl InputQuery {
+    // Script args are not in the index database, prefix mode fetches the cell by RPC
+    fn matches(&self, info: &LiveCellInfo, rpc_client: &mut HttpRpcClient) -> Result<bool, String> {
+        if let Some(filter) = self.filter.as_ref() {
+            if let Some([start, end]) = filter.block_range {
+                if info.number < start.value() || info.number >= end.value() {
+                    return Ok(false);
+                }
+            }
+            if let Some([start, end]) = filter.output_capacity_range {
+                if info.capacity < start.value() || info.capacity >= end.value() {
+                    return Ok(false);
+                }
+            }
+        }
+        let script_hash: H256 = Script::from(self.script.clone())
+            .calc_script_hash()
+            .unpack();
+        let prefix = self.script_search_mode == InputQuerySearchMode::Prefix;
+        match (self.script_type, info.type_hashes.as_ref()) {
+            (InputQueryScriptType::Lock, _) if !prefix => return Ok(info.lock_hash == script_hash),
+            (InputQueryScriptType::Type, None) => return Ok(false),
+            (InputQueryScriptType::Type, Some((_, type_hash))) if !prefix => {
+                return Ok(type_hash == &script_hash)
+            }
+            (InputQueryScriptType::Type, Some((code_hash, _)))
+                if code_hash != &self.script.code_hash =>
+            {
+                return Ok(false)
+            }
+            _ => {}
+        }
+        let output = match rpc_client.get_live_cell(info.out_point(), false)?.cell {
+            Some(cell) => cell.output,
+            None => return Ok(false),
+        };
+        let script = match self.script_type {
+            InputQueryScriptType::Lock => Some(output.lock),
+            InputQueryScriptType::Type => output.type_,
+        };
+        Ok(script
+            .map(|script| {
+                script.code_hash == self.script.code_hash
+                    && script.hash_type == self.script.hash_type
+                    && script
+                        .args
+                        .as_bytes()
+                        .starts_with(self.script.args.as_bytes())
+            })
+            .unwrap_or(false))
+    }
+}
+
+// Capacity of live cells by category, only `spendable` can be used by transfer
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct CapacityBreakdown {
+    pub total: u64,
+    pub spendable: u64,
+    pub dao: u64,
+    pub with_type: u64,
+    pub immature: u64,
+    pub with_data: u64,
+    pub dust: u64,
+}
+
+// Rendered get-live-cells outputs by query, all dropped once the index database processes a new
+// block, or the same number of another fork
+#[derive(Default)]
+pub struct LiveCellsCache {
+    // (number, hash) of the last processed block
+    processed_block: (u64, H256),
+    outputs: HashMap<String, String>,
+}
+
+impl LiveCellsCache {
+    pub fn get(&self, key: &str, processed_block: &(u64, H256)) -> Option<String> {
+        if &self.processed_block == processed_block {
+            self.outputs.get(key).cloned()
+        } else {
+            None
+        }
+    }
+
+    pub fn insert(&mut self, key: String, processed_block: (u64, H256), output: String) {
+        if self.processed_block != processed_block {
+            self.outputs.clear();
+            self.processed_block = processed_block;
+        }
+        self.outputs.insert(key, output);
+    }
+
+    pub fn clear(&mut self) {
+        self.outputs.clear();
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -777,3 +5557,253 @@ pub struct LiveCell {
     pub info: LiveCellInfo,
     pub mature: bool,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ckb_index::CellIndex;
+
+    fn live_cell(tx_hash: H256, tx_index: u32, number: u64) -> LiveCell {
+        LiveCell {
+            info: LiveCellInfo {
+                tx_hash,
+                tx_index,
+                data_bytes: 0,
+                lock_hash: H256::default(),
+                type_hashes: None,
+                capacity: 100 * ONE_CKB,
+                number,
+                index: CellIndex {
+                    tx_index: 0,
+                    output_index: tx_index,
+                },
+            },
+            mature: true,
+        }
+    }
+
+    fn time_lock(since: Since) -> Script {
+        let mut args = vec![0u8; 20];
+        args.extend_from_slice(&since.value().to_le_bytes());
+        Script::new_builder()
+            .code_hash(MULTISIG_TYPE_HASH.pack())
+            .hash_type(ScriptHashType::Type.into())
+            .args(Bytes::from(args).pack())
+            .build()
+    }
+
+    #[test]
+    fn test_deduplicate_live_cells() {
+        let hash1 = h256!("0x1");
+        let hash2 = h256!("0x2");
+        let cells = vec![
+            live_cell(hash1.clone(), 0, 10),
+            live_cell(hash2.clone(), 0, 11),
+            live_cell(hash1.clone(), 0, 12),
+            live_cell(hash1.clone(), 1, 13),
+            live_cell(hash2.clone(), 0, 9),
+        ];
+        let (kept, duplicates) = deduplicate_live_cells(cells);
+        let kept = kept
+            .iter()
+            .map(|cell| {
+                (
+                    cell.info.tx_hash.clone(),
+                    cell.info.tx_index,
+                    cell.info.number,
+                )
+            })
+            .collect::<Vec<_>>();
+        // The highest block number is kept at the position of the first occurrence
+        assert_eq!(
+            kept,
+            vec![(hash1.clone(), 0, 12), (hash2, 0, 11), (hash1, 1, 13),]
+        );
+        let duplicates = duplicates
+            .iter()
+            .map(|cell| cell.info.number)
+            .collect::<Vec<_>>();
+        assert_eq!(duplicates, vec![10, 9]);
+
+        let (kept, duplicates) = deduplicate_live_cells(Vec::new());
+        assert!(kept.is_empty());
+        assert!(duplicates.is_empty());
+    }
+
+    #[test]
+    fn test_parse_histogram_buckets() {
+        assert_eq!(
+            parse_histogram_buckets("100, 1000,10000"),
+            Ok(vec![100 * ONE_CKB, 1000 * ONE_CKB, 10000 * ONE_CKB])
+        );
+        assert_eq!(parse_histogram_buckets("61.5"), Ok(vec![6_150_000_000]));
+        assert!(parse_histogram_buckets("1000,100").is_err());
+        assert!(parse_histogram_buckets("100,100").is_err());
+        assert!(parse_histogram_buckets("100,abc").is_err());
+        assert!(parse_histogram_buckets("").is_err());
+    }
+
+    #[test]
+    fn test_time_lock_condition() {
+        let epoch = EpochNumberWithFraction::new(10, 0, 1).full_value();
+        assert_eq!(
+            time_lock_condition(&time_lock(Since::new(
+                SinceType::EpochNumberWithFraction,
+                epoch,
+                false
+            ))),
+            "Unlocks at epoch 10"
+        );
+        assert_eq!(
+            time_lock_condition(&time_lock(Since::new(SinceType::BlockNumber, 100, true))),
+            "Unlocks after block 100 since the cell created"
+        );
+        assert_eq!(
+            time_lock_condition(&time_lock(Since::new(
+                SinceType::Timestamp,
+                1_600_000_000,
+                false
+            ))),
+            "Unlocks at timestamp 1600000000"
+        );
+        assert_eq!(
+            time_lock_condition(&time_lock(Since::from_raw_value(0x0100_0000_0000_0000))),
+            "Invalid since: 0x100000000000000"
+        );
+
+        let sighash_lock = Script::new_builder()
+            .code_hash(SIGHASH_TYPE_HASH.pack())
+            .hash_type(ScriptHashType::Type.into())
+            .args(Bytes::from(vec![0u8; 20]).pack())
+            .build();
+        assert_eq!(time_lock_condition(&sighash_lock), "No time lock");
+        let no_since = Script::new_builder()
+            .code_hash(MULTISIG_TYPE_HASH.pack())
+            .hash_type(ScriptHashType::Type.into())
+            .args(Bytes::from(vec![0u8; 20]).pack())
+            .build();
+        assert_eq!(time_lock_condition(&no_since), "No time lock");
+    }
+
+    #[test]
+    fn test_decode_tx_comment() {
+        let mut witness = TX_COMMENT_MAGIC.to_vec();
+        witness.extend_from_slice(br#"{"order":42}"#);
+        assert_eq!(
+            decode_tx_comment(&witness),
+            Some(Ok(serde_json::json!({ "order": 42 })))
+        );
+        assert!(decode_tx_comment(b"CMNTnot json").unwrap().is_err());
+        // Not a tx comment witness
+        assert_eq!(decode_tx_comment(b""), None);
+        assert_eq!(decode_tx_comment(b"CMN"), None);
+        assert_eq!(decode_tx_comment(br#"MEMO{"order":42}"#), None);
+    }
+
+    #[test]
+    fn test_epoch_since() {
+        assert_eq!(
+            epoch_fraction(EpochNumberWithFraction::new(10, 1, 4)),
+            (41, 4)
+        );
+        assert_eq!(
+            epoch_fraction(EpochNumberWithFraction::new(10, 0, 0)),
+            (10, 1)
+        );
+
+        // Relative 1.5 epochs since the input committed at epoch 10.25
+        let start = epoch_fraction(EpochNumberWithFraction::new(10, 1, 4));
+        let relative = epoch_fraction(EpochNumberWithFraction::new(1, 1, 2));
+        let required = add_epoch_fractions(start, relative);
+        assert_eq!(required, (94, 8));
+        assert!(compare_epoch_since((47, 4), required).0);
+        assert!(compare_epoch_since((12, 1), required).0);
+        assert_eq!(
+            compare_epoch_since((43, 4), required),
+            (false, "NOT SATISFIED: unlocks in 1.0 epochs".to_string())
+        );
+        // Zero length epochs are whole epochs
+        assert_eq!(add_epoch_fractions((10, 1), (3, 2)), (23, 2));
+    }
+
+    #[test]
+    fn test_compare_since() {
+        assert_eq!(
+            compare_since(100, 100, "blocks"),
+            (true, "satisfied (current: 100, required: 100)".to_string())
+        );
+        assert_eq!(
+            compare_since(95, 100, "blocks"),
+            (false, "NOT SATISFIED: unlocks in 5 blocks".to_string())
+        );
+        assert_eq!(
+            compare_since(1000, 1060, "seconds"),
+            (false, "NOT SATISFIED: unlocks in 60 seconds".to_string())
+        );
+    }
+
+    #[test]
+    fn test_input_query_matches() {
+        let script = serde_json::json!({
+            "code_hash": format!("{:#x}", SIGHASH_TYPE_HASH),
+            "hash_type": "type",
+            "args": format!("0x{}", "01".repeat(20)),
+        });
+        let query = |script_type: &str, mode: &str, filter: serde_json::Value| -> InputQuery {
+            serde_json::from_value(serde_json::json!({
+                "script": script,
+                "script_type": script_type,
+                "script_search_mode": mode,
+                "filter": filter,
+            }))
+            .unwrap()
+        };
+        let script_hash: H256 =
+            Script::from(query("lock", "exact", serde_json::Value::Null).script)
+                .calc_script_hash()
+                .unpack();
+        // Exact mode and the mismatched prefix mode do not call the RPC
+        let mut rpc_client = HttpRpcClient::new("http://127.0.0.1:8114".to_string());
+        let mut info = live_cell(h256!("0x1"), 0, 100).info;
+        info.lock_hash = script_hash.clone();
+
+        let lock_query = query("lock", "exact", serde_json::Value::Null);
+        assert_eq!(lock_query.matches(&info, &mut rpc_client), Ok(true));
+        let mut other_info = info.clone();
+        other_info.lock_hash = H256::default();
+        assert_eq!(lock_query.matches(&other_info, &mut rpc_client), Ok(false));
+
+        // Ranges are [start, end)
+        for (filter, expected) in vec![
+            (serde_json::json!({ "block_range": ["0x64", "0x65"] }), true),
+            (serde_json::json!({ "block_range": ["0x0", "0x64"] }), false),
+            (
+                serde_json::json!({ "block_range": ["0x65", "0xc8"] }),
+                false,
+            ),
+            (
+                serde_json::json!({ "output_capacity_range": ["0x2540be400", "0x2540be401"] }),
+                true,
+            ),
+            (
+                serde_json::json!({ "output_capacity_range": ["0x0", "0x2540be400"] }),
+                false,
+            ),
+        ] {
+            assert_eq!(
+                query("lock", "exact", filter).matches(&info, &mut rpc_client),
+                Ok(expected)
+            );
+        }
+
+        let type_query = query("type", "exact", serde_json::Value::Null);
+        assert_eq!(type_query.matches(&info, &mut rpc_client), Ok(false));
+        info.type_hashes = Some((SIGHASH_TYPE_HASH.clone(), script_hash.clone()));
+        assert_eq!(type_query.matches(&info, &mut rpc_client), Ok(true));
+        info.type_hashes = Some((H256::default(), script_hash));
+        assert_eq!(
+            query("type", "prefix", serde_json::Value::Null).matches(&info, &mut rpc_client),
+            Ok(false)
+        );
+    }
+}
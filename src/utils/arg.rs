@@ -96,6 +96,14 @@ pub fn from_account<'a, 'b>() -> Arg<'a, 'b> {
         .help("The account's lock-arg or sighash address (transfer from this account)")
 }
 
+pub fn password_file<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("password-file")
+        .long("password-file")
+        .takes_value(true)
+        .validator(|input| FilePathParser::new(true).validate(input))
+        .help("Read keystore password from this file instead of prompt (trailing newline is trimmed, the file should only be readable by owner)")
+}
+
 pub fn from_locked_address<'a, 'b>() -> Arg<'a, 'b> {
     Arg::with_name("from-locked-address")
         .long("from-locked-address")
@@ -7,7 +7,7 @@ use std::str::FromStr;
 use std::time::Duration;
 
 use ckb_sdk::{
-    wallet::{zeroize_privkey, MasterPrivKey},
+    wallet::{zeroize_privkey, ChildNumber, DerivationPath, MasterPrivKey},
     Address, AddressPayload, AddressType, CodeHashIndex, HumanCapacity, NetworkType, OldAddress,
 };
 use ckb_types::{packed::OutPoint, prelude::*, H160, H256};
@@ -252,6 +252,12 @@ impl std::ops::Deref for PrivkeyWrapper {
     }
 }
 
+impl std::fmt::Debug for PrivkeyWrapper {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "PrivkeyWrapper(<redacted>)")
+    }
+}
+
 pub struct PrivkeyPathParser;
 
 impl ArgParser<PrivkeyWrapper> for PrivkeyPathParser {
@@ -486,6 +492,32 @@ impl ArgParser<Address> for AddressParser {
     }
 }
 
+// Account lock-arg, or its sighash address
+#[derive(Default)]
+pub struct AccountParser {
+    network: Option<NetworkType>,
+}
+
+impl AccountParser {
+    pub fn new(network: Option<NetworkType>) -> AccountParser {
+        AccountParser { network }
+    }
+}
+
+impl ArgParser<H160> for AccountParser {
+    fn parse(&self, input: &str) -> Result<H160, String> {
+        FixedHashParser::<H160>::default()
+            .parse(input)
+            .or_else(|err| {
+                AddressParser::new_sighash()
+                    .set_network_opt(self.network)
+                    .parse(input)
+                    .map(|address| H160::from_slice(&address.payload().args()).unwrap())
+                    .map_err(|_| err)
+            })
+    }
+}
+
 /// Default unit CKB format: xxx.xxxxx
 pub struct CapacityParser;
 
@@ -495,6 +527,74 @@ impl ArgParser<HumanCapacity> for CapacityParser {
     }
 }
 
+/// BIP32 derivation path, format: m/44'/309'/0'/0/0 (hardened segment ends with ')
+pub struct DerivationPathParser;
+
+impl ArgParser<DerivationPath> for DerivationPathParser {
+    fn parse(&self, input: &str) -> Result<DerivationPath, String> {
+        let mut parts = input.split('/');
+        if parts.next() != Some("m") {
+            return Err(format!(
+                "Invalid derivation path: {}, must start with `m`, e.g. m/44'/309'/0'",
+                input
+            ));
+        }
+        let children = parts
+            .map(|part| {
+                part.parse::<ChildNumber>().map_err(|err| {
+                    format!(
+                        "Invalid derivation path segment `{}` in {}: {}",
+                        part, input, err
+                    )
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        Ok(DerivationPath::from(children))
+    }
+}
+
+// Unit of bare numeric capacity arguments (by --capacity-unit)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CapacityUnit {
+    Ckb,
+    Shannon,
+}
+
+impl Default for CapacityUnit {
+    fn default() -> CapacityUnit {
+        CapacityUnit::Ckb
+    }
+}
+
+impl FromStr for CapacityUnit {
+    type Err = String;
+    fn from_str(input: &str) -> Result<CapacityUnit, String> {
+        match input {
+            "ckb" => Ok(CapacityUnit::Ckb),
+            "shannon" => Ok(CapacityUnit::Shannon),
+            _ => Err(format!("Invalid capacity unit: {}", input)),
+        }
+    }
+}
+
+impl CapacityUnit {
+    // Parse the capacity into shannons
+    pub fn parse(self, input: &str) -> Result<u64, String> {
+        match self {
+            CapacityUnit::Ckb => CapacityParser.parse(input).map(u64::from),
+            CapacityUnit::Shannon => FromStrParser::<u64>::default().parse(input),
+        }
+    }
+
+    // Format shannons in this unit, the result can be parsed back
+    pub fn format(self, shannons: u64) -> String {
+        match self {
+            CapacityUnit::Ckb => HumanCapacity::from(shannons).to_string(),
+            CapacityUnit::Shannon => shannons.to_string(),
+        }
+    }
+}
+
 pub struct OutPointParser;
 
 impl ArgParser<OutPoint> for OutPointParser {
@@ -538,6 +638,67 @@ impl ArgParser<Duration> for DurationParser {
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JsonPathItem {
+    Field(String),
+    Index(usize),
+}
+
+// Simplified jq path: `.`, `.field.subfield`, `.field[0]`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonPath(pub Vec<JsonPathItem>);
+
+impl JsonPath {
+    pub fn select<'a>(&self, value: &'a serde_json::Value) -> Option<&'a serde_json::Value> {
+        self.0.iter().try_fold(value, |value, item| match item {
+            JsonPathItem::Field(name) => value.get(name),
+            JsonPathItem::Index(index) => value.get(index),
+        })
+    }
+}
+
+pub struct JsonPathParser;
+
+impl ArgParser<JsonPath> for JsonPathParser {
+    fn parse(&self, input: &str) -> Result<JsonPath, String> {
+        let invalid = || {
+            format!(
+                "Invalid json path: {}, format: .field.subfield or .field[index]",
+                input
+            )
+        };
+        if !input.starts_with('.') {
+            return Err(invalid());
+        }
+        let mut items = Vec::new();
+        let mut rest = &input[1..];
+        while !rest.is_empty() {
+            if rest.starts_with('[') {
+                let end = rest.find(']').ok_or_else(invalid)?;
+                let index = rest[1..end].parse::<usize>().map_err(|_| invalid())?;
+                items.push(JsonPathItem::Index(index));
+                rest = &rest[end + 1..];
+            } else {
+                if !items.is_empty() {
+                    if !rest.starts_with('.') {
+                        return Err(invalid());
+                    }
+                    rest = &rest[1..];
+                }
+                let end = rest
+                    .find(|c| c == '.' || c == '[')
+                    .unwrap_or_else(|| rest.len());
+                if end == 0 {
+                    return Err(invalid());
+                }
+                items.push(JsonPathItem::Field(rest[..end].to_string()));
+                rest = &rest[end..];
+            }
+        }
+        Ok(JsonPath(items))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use ckb_types::{h160, h256};
@@ -631,4 +792,56 @@ mod tests {
             .parse("kb1qyqp8eqad7ffy42ezmchkjyz54rhcqf8q9pqrn323p")
             .is_err());
     }
+
+    #[test]
+    fn test_derivation_path() {
+        assert_eq!(
+            DerivationPathParser.parse("m/44'/309'/0'/0/0"),
+            Ok(DerivationPath::from_str("m/44'/309'/0'/0/0").unwrap())
+        );
+        assert_eq!(
+            DerivationPathParser.parse("m"),
+            Ok(DerivationPath::from(Vec::new()))
+        );
+        assert!(DerivationPathParser.parse("44'/309'").is_err());
+        assert!(DerivationPathParser.parse("m/44'/").is_err());
+        assert!(DerivationPathParser.parse("m/44x/309'").is_err());
+        assert!(DerivationPathParser.parse("m/2147483648").is_err());
+    }
+
+    #[test]
+    fn test_capacity_unit() {
+        assert_eq!(CapacityUnit::Ckb.parse("1.5"), Ok(150_000_000));
+        assert_eq!(CapacityUnit::Shannon.parse("150000000"), Ok(150_000_000));
+        assert!(CapacityUnit::Shannon.parse("1.5").is_err());
+        assert!(CapacityUnit::Ckb.parse("184467440738").is_err());
+        for unit in &[CapacityUnit::Ckb, CapacityUnit::Shannon] {
+            for shannons in &[0, 1, 150_000_000, 100_000_001] {
+                assert_eq!(unit.parse(&unit.format(*shannons)), Ok(*shannons));
+            }
+        }
+    }
+
+    #[test]
+    fn test_json_path() {
+        let value = serde_json::json!({
+            "hash": "0x01",
+            "outputs": [{ "capacity": "0x64" }],
+        });
+        let select = |path: &str| JsonPathParser.parse(path).unwrap().select(&value).cloned();
+        assert_eq!(select("."), Some(value.clone()));
+        assert_eq!(select(".hash"), Some(serde_json::json!("0x01")));
+        assert_eq!(
+            select(".outputs[0].capacity"),
+            Some(serde_json::json!("0x64"))
+        );
+        assert_eq!(select(".outputs[1]"), None);
+        assert_eq!(select(".inputs"), None);
+
+        assert!(JsonPathParser.parse("hash").is_err());
+        assert!(JsonPathParser.parse(".a..b").is_err());
+        assert!(JsonPathParser.parse(".a[x]").is_err());
+        assert!(JsonPathParser.parse(".a[0").is_err());
+        assert!(JsonPathParser.parse(".a[0]b").is_err());
+    }
 }
@@ -107,6 +107,22 @@ impl IndexThreadState {
             _ => false,
         }
     }
+    // Number of the last processed block
+    pub fn processed_number(&self) -> Option<u64> {
+        match self {
+            IndexThreadState::Processing(Some(SimpleBlockInfo { number, .. }), _) => Some(*number),
+            _ => None,
+        }
+    }
+    // Number and hash of the last processed block, the hash changes on fork rollback
+    pub fn processed_block(&self) -> Option<(u64, H256)> {
+        match self {
+            IndexThreadState::Processing(Some(SimpleBlockInfo { number, hash, .. }), _) => {
+                Some((*number, hash.clone()))
+            }
+            _ => None,
+        }
+    }
     #[cfg_attr(windows, allow(dead_code))]
     pub fn is_processing(&self) -> bool {
         match self {
@@ -145,8 +161,50 @@ impl Default for IndexThreadState {
     }
 }
 
+// Sync speed since the index thread started processing
+#[derive(Debug, Clone, Default)]
+pub struct SyncProgress {
+    // (time, block number) of the first processed block
+    start: Option<(Instant, u64)>,
+    current_number: u64,
+    tip_number: u64,
+}
+
+impl SyncProgress {
+    pub fn update(&mut self, number: u64, tip_number: u64) {
+        if self.start.is_none() {
+            self.start = Some((Instant::now(), number));
+        }
+        self.current_number = number;
+        self.tip_number = tip_number;
+    }
+    pub fn current_number(&self) -> u64 {
+        self.current_number
+    }
+    pub fn tip_number(&self) -> u64 {
+        self.tip_number
+    }
+    pub fn blocks_per_sec(&self) -> Option<f64> {
+        self.start.and_then(|(time, number)| {
+            let secs = time.elapsed().as_secs_f64();
+            if secs > 0.0 && self.current_number > number {
+                Some((self.current_number - number) as f64 / secs)
+            } else {
+                None
+            }
+        })
+    }
+    pub fn eta(&self) -> Option<Duration> {
+        self.blocks_per_sec().map(|speed| {
+            let rest = self.tip_number.saturating_sub(self.current_number);
+            Duration::from_secs_f64(rest as f64 / speed)
+        })
+    }
+}
+
 pub struct IndexController {
     state: Arc<RwLock<IndexThreadState>>,
+    progress: Arc<RwLock<SyncProgress>>,
     sender: Sender<Request<IndexRequest, IndexResponse>>,
     shutdown: Arc<AtomicBool>,
 }
@@ -155,6 +213,7 @@ impl Clone for IndexController {
     fn clone(&self) -> IndexController {
         IndexController {
             state: Arc::clone(&self.state),
+            progress: Arc::clone(&self.progress),
             shutdown: Arc::clone(&self.shutdown),
             sender: self.sender.clone(),
         }
@@ -164,11 +223,13 @@ impl Clone for IndexController {
 impl IndexController {
     pub fn new(
         state: Arc<RwLock<IndexThreadState>>,
+        progress: Arc<RwLock<SyncProgress>>,
         sender: Sender<Request<IndexRequest, IndexResponse>>,
         shutdown: Arc<AtomicBool>,
     ) -> IndexController {
         IndexController {
             state,
+            progress,
             sender,
             shutdown,
         }
@@ -176,6 +237,9 @@ impl IndexController {
     pub fn state(&self) -> &Arc<RwLock<IndexThreadState>> {
         &self.state
     }
+    pub fn progress(&self) -> &Arc<RwLock<SyncProgress>> {
+        &self.progress
+    }
     pub fn sender(&self) -> &Sender<Request<IndexRequest, IndexResponse>> {
         &self.sender
     }
@@ -6,6 +6,7 @@ pub mod index;
 pub mod json_color;
 pub mod other;
 pub mod printer;
+pub mod trace;
 
 #[allow(clippy::cast_lossless)]
 pub mod yaml_ser;
@@ -1,17 +1,18 @@
 use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::io::Read;
+use std::io::{Read, Write};
 use std::path::PathBuf;
 use std::thread;
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
+use bip39::{Language, Mnemonic};
 use ckb_hash::blake2b_256;
 use ckb_index::{LiveCellInfo, VERSION};
 use ckb_sdk::{
     calc_max_mature_number,
     constants::{CELLBASE_MATURITY, MIN_SECP_CELL_CAPACITY, ONE_CKB},
     rpc::AlertMessage,
-    wallet::{KeyStore, ScryptType},
+    wallet::{zeroize_slice, DerivationPath, ExtendedPrivKey, KeyStore, ScryptType},
     Address, AddressPayload, CodeHashIndex, GenesisInfo, HttpRpcClient, NetworkType, SignerFn,
     SECP256K1,
 };
@@ -25,27 +26,198 @@ use ckb_types::{
 };
 use clap::ArgMatches;
 use colored::Colorize;
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use hmac::Hmac;
 use rpassword::prompt_password_stdout;
+use sha2::Sha512;
+use url::Url;
 
 use super::arg_parser::{
     AddressParser, ArgParser, FixedHashParser, HexParser, PrivkeyWrapper, PubkeyHexParser,
 };
 use super::index::{IndexController, IndexRequest, IndexThreadState};
 
-pub fn read_password(repeat: bool, prompt: Option<&str>) -> Result<String, String> {
+// Keystore password, zeroized when dropped
+pub struct Password(String);
+
+impl Password {
+    pub fn new(password: String) -> Password {
+        Password(password)
+    }
+}
+
+// For security purpose
+impl Drop for Password {
+    fn drop(&mut self) {
+        // Zero bytes are still valid UTF-8
+        zeroize_slice(unsafe { self.0.as_bytes_mut() });
+    }
+}
+
+impl std::ops::Deref for Password {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl std::fmt::Debug for Password {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Password(<redacted>)")
+    }
+}
+
+pub fn read_password(repeat: bool, prompt: Option<&str>) -> Result<Password, String> {
     let prompt = prompt.unwrap_or("Password");
-    let pass =
-        prompt_password_stdout(format!("{}: ", prompt).as_str()).map_err(|err| err.to_string())?;
+    let pass = prompt_password_stdout(format!("{}: ", prompt).as_str())
+        .map(Password::new)
+        .map_err(|err| err.to_string())?;
     if repeat {
-        let repeat_pass =
-            prompt_password_stdout("Repeat password: ").map_err(|err| err.to_string())?;
-        if pass != repeat_pass {
+        let repeat_pass = prompt_password_stdout("Repeat password: ")
+            .map(Password::new)
+            .map_err(|err| err.to_string())?;
+        if *pass != *repeat_pass {
             return Err("Passwords do not match".to_owned());
         }
     }
     Ok(pass)
 }
 
+// Read a BIP-39 mnemonic phrase from terminal and derive the private key at the
+// derivation path, nothing is written to KeyStore
+pub fn read_mnemonic_privkey(path: &DerivationPath) -> Result<PrivkeyWrapper, String> {
+    let input =
+        Password::new(prompt_password_stdout("Mnemonic phrase: ").map_err(|err| err.to_string())?);
+    // English words are ASCII, so the phrase is already NFKD normalized
+    let phrase = Password::new(input.split_whitespace().collect::<Vec<_>>().join(" "));
+    Mnemonic::validate(&phrase, Language::English)
+        .map_err(|err| format!("Invalid mnemonic phrase: {}", err))?;
+    // BIP-39 seed with empty passphrase, bip39::Seed is not zeroized on drop
+    let mut seed = [0u8; 64];
+    pbkdf2::pbkdf2::<Hmac<Sha512>>(phrase.as_bytes(), b"mnemonic", 2048, &mut seed);
+    let master = ExtendedPrivKey::new_master(&seed);
+    zeroize_slice(&mut seed);
+    // The master and derived ExtendedPrivKey are zeroized on drop
+    let key = master
+        .map_err(|err| err.to_string())?
+        .derive_priv(&SECP256K1, path)
+        .map_err(|err| err.to_string())?;
+    Ok(PrivkeyWrapper(key.private_key))
+}
+
+// Read keystore password from file, only the trailing newline is trimmed
+pub fn read_password_file(path: &str) -> Result<Password, String> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = fs::metadata(path)
+            .map_err(|err| err.to_string())?
+            .permissions()
+            .mode();
+        if mode & 0o077 != 0 {
+            eprintln!(
+                "[{}]: password file {} is accessible by other users (mode: {:o}), please run `chmod 600` on it",
+                "warning".yellow().bold(),
+                path,
+                mode & 0o777,
+            );
+        }
+    }
+    let mut content = fs::read(path).map_err(|err| err.to_string())?;
+    let mut end = content.len();
+    if end > 0 && content[end - 1] == b'\n' {
+        end -= 1;
+        if end > 0 && content[end - 1] == b'\r' {
+            end -= 1;
+        }
+    }
+    let result = String::from_utf8(content[..end].to_vec())
+        .map(Password::new)
+        .map_err(|_| format!("Password file is not valid UTF-8: {}", path));
+    zeroize_slice(&mut content);
+    result
+}
+
+// Read password from --password-file if given, otherwise prompt for it
+pub fn get_password(m: &ArgMatches, prompt: Option<&str>) -> Result<Password, String> {
+    if let Some(path) = m.value_of("password-file") {
+        read_password_file(path)
+    } else {
+        read_password(false, prompt)
+    }
+}
+
+pub fn get_config_file() -> PathBuf {
+    let mut config_file = dirs::home_dir().unwrap();
+    config_file.push(".ckb-cli");
+    config_file.push("config");
+    config_file
+}
+
+// Local records of sent transfers (one JSON object per line)
+pub fn get_tx_history_file() -> PathBuf {
+    let mut history_file = dirs::home_dir().unwrap();
+    history_file.push(".ckb-cli");
+    history_file.push("tx-history");
+    history_file
+}
+
+// The json object of config file, empty if the file does not exist
+pub fn read_config() -> Result<serde_json::Value, String> {
+    let config_file = get_config_file();
+    if !config_file.exists() {
+        return Ok(serde_json::json!({}));
+    }
+    let content = fs::read_to_string(&config_file).map_err(|err| err.to_string())?;
+    serde_json::from_str(&content).map_err(|err| err.to_string())
+}
+
+// Modify the config file in place, keys not touched by `update` are kept
+pub fn update_config<F: FnOnce(&mut serde_json::Value)>(update: F) -> Result<(), String> {
+    let mut configs = read_config()?;
+    update(&mut configs);
+    let content = serde_json::to_string_pretty(&configs).unwrap();
+    fs::write(get_config_file(), content).map_err(|err| format!("save config error: {:?}", err))
+}
+
+// The account set by `account set-default`
+pub fn read_default_account() -> Result<Option<H160>, String> {
+    read_config()?["default_account"]
+        .as_str()
+        .map(|input| FixedHashParser::<H160>::default().parse(input))
+        .transpose()
+}
+
+// Default of `transfer --set-data-length-limit` if `data_length_limit` is not in config file
+pub const DEFAULT_DATA_LENGTH_LIMIT: usize = 512 * 1024;
+
+// The max data length of transfer output, set by `config --data-length-limit`
+pub fn read_data_length_limit() -> Result<Option<usize>, String> {
+    Ok(read_config()?["data_length_limit"]
+        .as_u64()
+        .map(|limit| limit as usize))
+}
+
+pub fn write_default_account(account: &H160) -> Result<(), String> {
+    update_config(|configs| {
+        configs["default_account"] = serde_json::json!(format!("{:#x}", account));
+    })
+}
+
+// Fallback to the default account when both --privkey-path and --from-account are absent
+pub fn get_default_account(m: &ArgMatches) -> Result<Option<H160>, String> {
+    if m.is_present("privkey-path")
+        || m.is_present("from-account")
+        || m.is_present("from-mnemonic-phrase")
+    {
+        return Ok(None);
+    }
+    read_default_account()?.map(Some).ok_or_else(|| {
+        "<privkey-path> or <from-account> is required (or set a default account by `account set-default`)".to_string()
+    })
+}
+
 pub fn get_key_store(ckb_cli_dir: &PathBuf) -> Result<KeyStore, String> {
     let mut keystore_dir = ckb_cli_dir.clone();
     keystore_dir.push("keystore");
@@ -211,6 +383,35 @@ pub fn get_network_type(rpc_client: &mut HttpRpcClient) -> Result<NetworkType, S
         .ok_or_else(|| format!("Unexpected network type: {}", chain_info.chain))
 }
 
+// Fetch CKB/USD price by an HTTP(S) GET request. The response body can be a
+// number or a JSON object like: {"price": 0.01}, {"usd": 0.01} or {"nervos-network": {"usd": 0.01}}
+pub fn fetch_ckb_usd_price(url: &Url) -> Result<f64, String> {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()
+        .map_err(|err| err.to_string())?;
+    let mut response = client
+        .get(url.clone())
+        .header(reqwest::header::ACCEPT, "application/json")
+        .send()
+        .map_err(|err| format!("Request price oracle {} failed: {}", url, err))?;
+    if !response.status().is_success() {
+        return Err(format!(
+            "Price oracle response error: {}",
+            response.status()
+        ));
+    }
+    let body = response.text().map_err(|err| err.to_string())?;
+    let value: serde_json::Value = serde_json::from_str(body.trim())
+        .map_err(|err| format!("Invalid price oracle response: {}", err))?;
+    value
+        .as_f64()
+        .or_else(|| value["price"].as_f64())
+        .or_else(|| value["usd"].as_f64())
+        .or_else(|| value["nervos-network"]["usd"].as_f64())
+        .ok_or_else(|| format!("Can not find CKB/USD price in response: {}", body))
+}
+
 pub fn index_dirname() -> String {
     format!("index-v{}", VERSION)
 }
@@ -231,6 +432,35 @@ pub fn sync_to_tip(index_controller: &IndexController) -> Result<(), String> {
     Ok(())
 }
 
+// Wait until the index is at most `max_lag` blocks behind the RPC tip, return the lag if
+// still behind after `timeout`
+pub fn wait_index_synced(
+    index_controller: &IndexController,
+    rpc_client: &mut HttpRpcClient,
+    max_lag: u64,
+    timeout: Duration,
+) -> Result<Option<u64>, String> {
+    Request::call(index_controller.sender(), IndexRequest::Kick);
+    let start = Instant::now();
+    loop {
+        let state = IndexThreadState::clone(&index_controller.state().read());
+        if state.is_error() {
+            return Err(state.get_error().unwrap());
+        }
+        let tip_number = rpc_client.get_tip_block_number()?;
+        let lag = state
+            .processed_number()
+            .map(|number| tip_number.saturating_sub(number))
+            .unwrap_or(tip_number);
+        if lag <= max_lag {
+            return Ok(None);
+        } else if start.elapsed() >= timeout {
+            return Ok(Some(lag));
+        }
+        thread::sleep(Duration::from_millis(500));
+    }
+}
+
 pub fn check_capacity(capacity: u64, to_data_len: usize) -> Result<(), String> {
     if capacity < MIN_SECP_CELL_CAPACITY {
         return Err(format!(
@@ -287,6 +517,42 @@ pub fn get_to_data(m: &ArgMatches) -> Result<Bytes, String> {
     }
 }
 
+// Compress cell data by gzip or lz4 (by transfer --output-data-compression)
+pub fn compress_data(data: &[u8], algorithm: &str) -> Result<Bytes, String> {
+    match algorithm {
+        "gzip" => {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+            encoder.write_all(data).map_err(|err| err.to_string())?;
+            encoder
+                .finish()
+                .map(Bytes::from)
+                .map_err(|err| err.to_string())
+        }
+        "lz4" => {
+            let mut encoder = lz4::EncoderBuilder::new()
+                .level(16)
+                .build(Vec::new())
+                .map_err(|err| err.to_string())?;
+            encoder.write_all(data).map_err(|err| err.to_string())?;
+            let (content, result) = encoder.finish();
+            result.map_err(|err| err.to_string())?;
+            Ok(Bytes::from(content))
+        }
+        _ => Err(format!("Unsupported compression algorithm: {}", algorithm)),
+    }
+}
+
+pub fn decompress_data(data: &[u8], algorithm: &str) -> Result<Bytes, String> {
+    let mut content = Vec::new();
+    let result = match algorithm {
+        "gzip" => GzDecoder::new(data).read_to_end(&mut content),
+        "lz4" => lz4::Decoder::new(data).and_then(|mut decoder| decoder.read_to_end(&mut content)),
+        _ => return Err(format!("Unsupported compression algorithm: {}", algorithm)),
+    };
+    result.map_err(|err| format!("Decompress data failed: {}", err))?;
+    Ok(Bytes::from(content))
+}
+
 pub fn get_privkey_signer(privkey: PrivkeyWrapper) -> SignerFn {
     let pubkey = secp256k1::PublicKey::from_secret_key(&SECP256K1, &privkey);
     let lock_arg = H160::from_slice(&blake2b_256(&pubkey.serialize()[..])[0..20])
@@ -315,9 +581,14 @@ pub fn serialize_signature(signature: &secp256k1::recovery::RecoverableSignature
     signature_bytes
 }
 
+// The first transaction of a block is cellbase
+pub fn is_cellbase(info: &LiveCellInfo) -> bool {
+    info.index.tx_index == 0
+}
+
 pub fn is_mature(info: &LiveCellInfo, max_mature_number: u64) -> bool {
     // Not cellbase cell
-    info.index.tx_index > 0
+    !is_cellbase(info)
     // Live cells in genesis are all mature
         || info.number == 0
         || info.number <= max_mature_number
@@ -329,3 +600,31 @@ pub fn get_arg_value<'a>(matches: &'a ArgMatches, name: &str) -> Result<String,
         .map(|s| s.to_string())
         .ok_or_else(|| format!("<{}> is required", name))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::process;
+
+    #[test]
+    fn test_read_password_file() {
+        let path = env::temp_dir().join(format!("ckb-cli-test-password-{}", process::id()));
+        let path_str = path.to_str().unwrap();
+        // Only one trailing newline is trimmed, other whitespaces are part of the password
+        for (content, expected) in vec![
+            ("secret", "secret"),
+            ("secret\n", "secret"),
+            ("secret\r\n", "secret"),
+            ("secret\n\n", "secret\n"),
+            (" secret \n", " secret "),
+            ("\n", ""),
+        ] {
+            fs::write(&path, content).unwrap();
+            assert_eq!(&*read_password_file(path_str).unwrap(), expected);
+        }
+        fs::write(&path, [0xffu8, 0xfe]).unwrap();
+        assert!(read_password_file(path_str).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+}
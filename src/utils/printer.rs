@@ -24,6 +24,8 @@ pub fn is_term_dumb() -> bool {
 pub enum OutputFormat {
     Yaml,
     Json,
+    // Prometheus text exposition format, only for metrics (others fallback to json)
+    Prometheus,
 }
 
 impl fmt::Display for OutputFormat {
@@ -34,6 +36,7 @@ impl fmt::Display for OutputFormat {
             match self {
                 OutputFormat::Yaml => "yaml",
                 OutputFormat::Json => "json",
+                OutputFormat::Prometheus => "prometheus",
             }
         )
     }
@@ -44,6 +47,7 @@ impl OutputFormat {
         match format {
             "yaml" => Ok(OutputFormat::Yaml),
             "json" => Ok(OutputFormat::Json),
+            "prometheus" => Ok(OutputFormat::Prometheus),
             _ => Err(format!("Invalid output format: {}", format)),
         }
     }
@@ -101,7 +105,7 @@ where
     fn render(&self, format: OutputFormat, color: bool) -> String {
         match format {
             OutputFormat::Yaml => yaml_ser::to_string(self, color).unwrap(),
-            OutputFormat::Json => {
+            OutputFormat::Json | OutputFormat::Prometheus => {
                 let value = serde_json::to_value(self).unwrap();
                 if color {
                     Colorizer::arbitrary().colorize_json_value(&value).unwrap()
@@ -0,0 +1,92 @@
+use std::cell::RefCell;
+use std::fs;
+use std::time::Instant;
+
+use serde::Serialize;
+use serde_json::json;
+
+// Placeholder of sensitive values (password, private key)
+pub const REDACTED: &str = "<redacted>";
+
+// Execution trace for debugging, events are only recorded when enabled. Never record passwords
+// or private keys, the trace file is meant to be shared.
+pub struct Trace {
+    enabled: bool,
+    start: Instant,
+    events: RefCell<Vec<serde_json::Value>>,
+}
+
+impl Trace {
+    pub fn new(enabled: bool) -> Trace {
+        Trace {
+            enabled,
+            start: Instant::now(),
+            events: RefCell::new(Vec::new()),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn record(&self, kind: &str, detail: serde_json::Value) {
+        if self.enabled {
+            self.events.borrow_mut().push(json!({
+                "elapsed_ms": self.start.elapsed().as_millis() as u64,
+                "kind": kind,
+                "detail": detail,
+            }));
+        }
+    }
+
+    // Record the call and pass the result through
+    pub fn rpc<T: Serialize>(
+        &self,
+        method: &str,
+        params: serde_json::Value,
+        result: Result<T, String>,
+    ) -> Result<T, String> {
+        if self.enabled {
+            let response = match &result {
+                Ok(value) => json!({ "ok": value }),
+                Err(err) => json!({ "error": err }),
+            };
+            self.record(
+                "rpc",
+                json!({
+                    "method": method,
+                    "params": params,
+                    "response": response,
+                }),
+            );
+        }
+        result
+    }
+
+    pub fn index_query(&self, query: &str, params: serde_json::Value, result_count: usize) {
+        self.record(
+            "index_query",
+            json!({
+                "query": query,
+                "params": params,
+                "result_count": result_count,
+            }),
+        );
+    }
+
+    pub fn key_derivation(&self, path: &str, lock_arg: String) {
+        self.record(
+            "key_derivation",
+            json!({
+                "path": path,
+                "lock_arg": lock_arg,
+            }),
+        );
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let content =
+            serde_json::to_string_pretty(&*self.events.borrow()).map_err(|err| err.to_string())?;
+        fs::write(path, content).map_err(|err| format!("Write trace file {} failed: {}", path, err))
+    }
+}